@@ -0,0 +1,720 @@
+//! Boolean operations on outlines.
+//!
+//! The operations work on closed contours, and use the non-zero winding rule
+//! to decide what is inside an outline; open contours do not enclose an area
+//! and are ignored. Curves are approximated by polygons to find where the
+//! outlines intersect, but the result is made of the pieces of the original
+//! curves between the intersections. Contours that do not intersect
+//! anything are kept as they are, point names and identifiers included.
+//!
+//! # Examples
+//!
+//! ```
+//! use norad::{Contour, ContourPoint, Glyph, PointType};
+//!
+//! fn square(x: f32, y: f32) -> Contour {
+//!     let corners = [(x, y), (x + 100., y), (x + 100., y + 100.), (x, y + 100.)];
+//!     let points = corners
+//!         .iter()
+//!         .map(|&(x, y)| ContourPoint::new(x, y, PointType::Line, false, None, None, None))
+//!         .collect();
+//!     Contour::new(points, None, None)
+//! }
+//!
+//! let mut glyph = Glyph::new_named("overlap");
+//! glyph.contours.push(square(0., 0.));
+//! glyph.contours.push(square(50., 50.));
+//! glyph.remove_overlap().unwrap();
+//! assert_eq!(glyph.contours.len(), 1);
+//! assert_eq!(glyph.contours[0].points.len(), 8);
+//! ```
+
+use std::collections::HashMap;
+
+use crate::error::ErrorKind;
+use crate::glyph::contour_from_path_points;
+use crate::glyph::segment::{winding_contribution, Point, Segment};
+use crate::{Contour, Glyph, PointType};
+
+/// The maximum distance between a curve and the polygon approximating it.
+const FLATTEN_TOLERANCE: f64 = 0.01;
+/// Points closer than this are considered the same vertex.
+const MERGE_DISTANCE: f64 = 1e-6;
+/// Intersections closer than this to the end of an edge snap to it.
+const SNAP_DISTANCE: f64 = 1e-7;
+/// How far from an edge to look for what is on either side of it.
+const SAMPLE_DISTANCE: f64 = 1e-5;
+/// Loops enclosing less area than this are dropped.
+const MIN_AREA: f64 = 1e-4;
+
+/// A boolean operation on two outlines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// The area covered by either outline.
+    Union,
+    /// The area covered by the first outline but not the second.
+    Difference,
+    /// The area covered by both outlines.
+    Intersection,
+    /// The area covered by exactly one of the outlines.
+    Xor,
+}
+
+impl Operation {
+    fn contains(self, in_subject: bool, in_clip: bool) -> bool {
+        match self {
+            Operation::Union => in_subject || in_clip,
+            Operation::Difference => in_subject && !in_clip,
+            Operation::Intersection => in_subject && in_clip,
+            Operation::Xor => in_subject != in_clip,
+        }
+    }
+}
+
+/// Apply a boolean operation to the closed contours of two outlines.
+///
+/// The resulting outer contours run counter-clockwise, and the counters
+/// clockwise. Passing an empty `clip` with [`Operation::Union`] removes the
+/// overlaps of the `subject`.
+pub fn apply(
+    operation: Operation,
+    subject: &[Contour],
+    clip: &[Contour],
+) -> Result<Vec<Contour>, ErrorKind> {
+    let mut outline = Outline::default();
+    for (operand, contours) in [subject, clip].iter().enumerate() {
+        for contour in contours.iter().filter(|contour| contour.is_closed()) {
+            outline.add_contour(operand, contour)?;
+        }
+    }
+    Ok(outline.evaluate(operation))
+}
+
+impl Glyph {
+    /// Remove the overlaps between the glyph's contours.
+    ///
+    /// See the [`boolean`](crate::boolean) module for details. Open contours
+    /// and components are left alone.
+    pub fn remove_overlap(&mut self) -> Result<(), ErrorKind> {
+        self.apply_boolean(Operation::Union, &[])
+    }
+
+    /// Combine the glyph's outline with the contours of `other`.
+    ///
+    /// See the [`boolean`](crate::boolean) module for details. Open contours
+    /// and components are left alone.
+    pub fn boolean(&mut self, operation: Operation, other: &Glyph) -> Result<(), ErrorKind> {
+        self.apply_boolean(operation, &other.contours)
+    }
+
+    fn apply_boolean(&mut self, operation: Operation, clip: &[Contour]) -> Result<(), ErrorKind> {
+        let closed = apply(operation, &self.contours, clip)?;
+        self.contours.retain(|contour| !contour.is_closed());
+        self.contours.extend(closed);
+        Ok(())
+    }
+}
+
+/// A closed contour taking part in an operation.
+struct Source<'a> {
+    contour: &'a Contour,
+    operand: usize,
+    segments: Vec<Segment>,
+}
+
+/// A straight piece of a source segment, between the parameters `t0` and `t1`.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    from: Point,
+    to: Point,
+    source: usize,
+    segment: usize,
+    t0: f64,
+    t1: f64,
+}
+
+impl Edge {
+    fn t_at(&self, param: f64) -> f64 {
+        self.t0 + (self.t1 - self.t0) * param
+    }
+}
+
+/// What happens to an edge in the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fate {
+    Drop,
+    Keep,
+    Reverse,
+}
+
+#[derive(Default)]
+struct Outline<'a> {
+    sources: Vec<Source<'a>>,
+    edges: Vec<Edge>,
+}
+
+impl<'a> Outline<'a> {
+    fn add_contour(&mut self, operand: usize, contour: &'a Contour) -> Result<(), ErrorKind> {
+        let source = self.sources.len();
+        let segments = contour.segments()?;
+        for (idx, segment) in segments.iter().enumerate() {
+            let steps = segment.flatten_steps(FLATTEN_TOLERANCE);
+            let mut from = segment.start();
+            for step in 1..=steps {
+                let t1 = step as f64 / steps as f64;
+                let to = if step == steps { segment.end() } else { segment.eval(t1) };
+                if from != to {
+                    let t0 = (step - 1) as f64 / steps as f64;
+                    self.edges.push(Edge { from, to, source, segment: idx, t0, t1 });
+                }
+                from = to;
+            }
+        }
+        self.sources.push(Source { contour, operand, segments });
+        Ok(())
+    }
+
+    fn evaluate(&self, operation: Operation) -> Vec<Contour> {
+        // Sources that are cut somewhere cannot be kept whole.
+        let mut touched = vec![false; self.sources.len()];
+        let pieces = self.split_edges(&mut touched);
+
+        // Merge the vertices, and drop duplicate edges where the outlines
+        // coincide.
+        let mut vertices = VertexPool::default();
+        let mut seen = HashMap::new();
+        let mut graph = Vec::new();
+        for (piece, edge) in pieces.iter().enumerate() {
+            let from = vertices.insert(edge.from);
+            let to = vertices.insert(edge.to);
+            if from == to {
+                continue;
+            }
+            let key = (from.min(to), from.max(to));
+            if let Some(&first) = seen.get(&key) {
+                let first: &Edge = &pieces[first];
+                touched[first.source] = true;
+                touched[edge.source] = true;
+                continue;
+            }
+            seen.insert(key, piece);
+            graph.push((from, to, *edge));
+        }
+
+        let fates: Vec<Fate> =
+            graph.iter().map(|(_, _, edge)| self.classify(edge, operation)).collect();
+
+        // Contours that were not cut are either kept or dropped whole.
+        let mut whole_fates: Vec<Option<Fate>> = vec![None; self.sources.len()];
+        let mut mixed = vec![false; self.sources.len()];
+        for ((_, _, edge), &fate) in graph.iter().zip(&fates) {
+            match whole_fates[edge.source] {
+                Some(other) if other != fate => mixed[edge.source] = true,
+                _ => whole_fates[edge.source] = Some(fate),
+            }
+        }
+        let is_whole = |source: usize| !touched[source] && !mixed[source];
+
+        let mut result = Vec::new();
+        for (idx, source) in self.sources.iter().enumerate() {
+            if !is_whole(idx) {
+                continue;
+            }
+            match whole_fates[idx] {
+                Some(Fate::Keep) => result.push(source.contour.clone()),
+                Some(Fate::Reverse) => {
+                    let mut contour = source.contour.clone();
+                    contour.reverse();
+                    result.push(contour);
+                }
+                _ => (),
+            }
+        }
+
+        let kept: Vec<(usize, usize, Edge)> = graph
+            .iter()
+            .zip(&fates)
+            .filter(|((_, _, edge), _)| !is_whole(edge.source))
+            .filter_map(|(&(from, to, edge), fate)| match fate {
+                Fate::Drop => None,
+                Fate::Keep => Some((from, to, edge)),
+                Fate::Reverse => Some((
+                    to,
+                    from,
+                    Edge { from: edge.to, to: edge.from, t0: edge.t1, t1: edge.t0, ..edge },
+                )),
+            })
+            .collect();
+        for path in link_loops(&kept, &vertices) {
+            if let Some(contour) = self.rebuild(&path, &vertices) {
+                result.push(contour);
+            }
+        }
+        result
+    }
+
+    /// Split every edge at its intersections with other edges.
+    fn split_edges(&self, touched: &mut [bool]) -> Vec<Edge> {
+        let mut splits: Vec<Vec<(f64, Point)>> = vec![Vec::new(); self.edges.len()];
+
+        // Sweep from left to right, only testing edges whose horizontal
+        // extents overlap.
+        let min_x = |edge: &Edge| edge.from.x.min(edge.to.x);
+        let max_x = |edge: &Edge| edge.from.x.max(edge.to.x);
+        let mut order: Vec<usize> = (0..self.edges.len()).collect();
+        order.sort_by(|a, b| min_x(&self.edges[*a]).total_cmp(&min_x(&self.edges[*b])));
+        for (pos, &a) in order.iter().enumerate() {
+            let edge = &self.edges[a];
+            let end = max_x(edge) + SNAP_DISTANCE;
+            for &b in order[pos + 1..].iter().take_while(|&&b| min_x(&self.edges[b]) <= end) {
+                let other = &self.edges[b];
+                let (lo, hi) = (edge.from.y.min(edge.to.y), edge.from.y.max(edge.to.y));
+                if other.from.y.max(other.to.y) < lo - SNAP_DISTANCE
+                    || other.from.y.min(other.to.y) > hi + SNAP_DISTANCE
+                {
+                    continue;
+                }
+                for (on_other, param, point) in intersect(edge, other) {
+                    splits[if on_other { b } else { a }].push((param, point));
+                }
+            }
+        }
+
+        let mut pieces = Vec::with_capacity(self.edges.len());
+        for (edge, mut splits) in self.edges.iter().zip(splits) {
+            if !splits.is_empty() {
+                touched[edge.source] = true;
+            }
+            splits.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut from = (0.0, edge.from);
+            for (param, point) in splits.into_iter().chain(Some((1.0, edge.to))) {
+                if point == from.1 {
+                    continue;
+                }
+                let (t0, t1) = (edge.t_at(from.0), edge.t_at(param));
+                pieces.push(Edge { from: from.1, to: point, t0, t1, ..*edge });
+                from = (param, point);
+            }
+        }
+        pieces
+    }
+
+    /// Decide whether the edge is part of the result, by looking at what is
+    /// on either side of it.
+    fn classify(&self, edge: &Edge, operation: Operation) -> Fate {
+        let direction = edge.to - edge.from;
+        let length = direction.hypot();
+        let normal = Point::new(-direction.y, direction.x) * (1.0 / length);
+        let offset = (length * 0.01).min(SAMPLE_DISTANCE);
+        let mid = edge.from.midpoint(edge.to);
+        let inside = |point: Point| {
+            let mut winding = [0, 0];
+            for edge in &self.edges {
+                winding[self.sources[edge.source].operand] +=
+                    winding_contribution(edge.from, edge.to, point);
+            }
+            operation.contains(winding[0] != 0, winding[1] != 0)
+        };
+        match (inside(mid + normal * offset), inside(mid - normal * offset)) {
+            (true, false) => Fate::Keep,
+            (false, true) => Fate::Reverse,
+            _ => Fate::Drop,
+        }
+    }
+
+    /// Turn a loop of edges back into a contour, made of the pieces of the
+    /// source segments they came from.
+    fn rebuild(&self, path: &[Edge], vertices: &VertexPool) -> Option<Contour> {
+        let area: f64 = path.iter().map(|edge| edge.from.cross(edge.to)).sum::<f64>() / 2.0;
+        if area.abs() < MIN_AREA {
+            return None;
+        }
+
+        // Join consecutive edges that come from the same segment.
+        let mut runs: Vec<Edge> = Vec::new();
+        for edge in path {
+            match runs.last_mut() {
+                Some(run) if continues(run, edge) => {
+                    run.to = edge.to;
+                    run.t1 = edge.t1;
+                }
+                _ => runs.push(*edge),
+            }
+        }
+        if runs.len() > 1 && continues(&runs[runs.len() - 1], &runs[0]) {
+            let last = runs.pop().expect("checked above");
+            runs[0].from = last.from;
+            runs[0].t0 = last.t0;
+        }
+
+        let start = vertices.snap(runs[0].from);
+        let mut points = vec![start.to_contour_point(PointType::Move, false)];
+        for run in &runs {
+            let segment = self.sources[run.source].segments[run.segment]
+                .subsegment(run.t0, run.t1)
+                .with_end_points(vertices.snap(run.from), vertices.snap(run.to));
+            match segment {
+                Segment::Line(_, p1) => points.push(p1.to_contour_point(PointType::Line, false)),
+                Segment::Quad(_, p1, p2) => {
+                    points.push(p1.to_contour_point(PointType::OffCurve, false));
+                    points.push(p2.to_contour_point(PointType::QCurve, false));
+                }
+                Segment::Cubic(_, p1, p2, p3) => {
+                    points.push(p1.to_contour_point(PointType::OffCurve, false));
+                    points.push(p2.to_contour_point(PointType::OffCurve, false));
+                    points.push(p3.to_contour_point(PointType::Curve, false));
+                }
+            }
+        }
+        Some(contour_from_path_points(points, true))
+    }
+}
+
+/// Whether `next` picks up exactly where `run` ends on the same segment.
+fn continues(run: &Edge, next: &Edge) -> bool {
+    run.source == next.source
+        && run.segment == next.segment
+        && run.t1 == next.t0
+        && (run.t1 - run.t0).signum() == (next.t1 - next.t0).signum()
+}
+
+/// The points where two edges intersect or overlap. Each intersection is
+/// reported as the edge it splits (`true` for `other`), the parameter along
+/// that edge, and the point.
+fn intersect(edge: &Edge, other: &Edge) -> Vec<(bool, f64, Point)> {
+    let r = edge.to - edge.from;
+    let s = other.to - other.from;
+    let (r_len, s_len) = (r.hypot(), s.hypot());
+    let qp = other.from - edge.from;
+    let denom = r.cross(s);
+    let mut found = Vec::new();
+    let is_inner =
+        |param: f64, len: f64| param * len > SNAP_DISTANCE && (1.0 - param) * len > SNAP_DISTANCE;
+
+    if denom.abs() > 1e-12 * r_len * s_len {
+        let t = qp.cross(s) / denom;
+        let u = qp.cross(r) / denom;
+        let outside = |param: f64, len: f64| {
+            param * len < -SNAP_DISTANCE || (param - 1.0) * len > SNAP_DISTANCE
+        };
+        if outside(t, r_len) || outside(u, s_len) {
+            return found;
+        }
+        // Prefer existing end points over computed positions.
+        let point = if !is_inner(u, s_len) {
+            if u < 0.5 {
+                other.from
+            } else {
+                other.to
+            }
+        } else if !is_inner(t, r_len) {
+            if t < 0.5 {
+                edge.from
+            } else {
+                edge.to
+            }
+        } else {
+            edge.from + r * t
+        };
+        if is_inner(t, r_len) {
+            found.push((false, t, point));
+        }
+        if is_inner(u, s_len) {
+            found.push((true, u, point));
+        }
+    } else if qp.cross(r).abs() <= SNAP_DISTANCE * r_len {
+        // Collinear edges: each splits the other at its end points.
+        for &point in &[other.from, other.to] {
+            let t = (point - edge.from).dot(r) / (r_len * r_len);
+            if is_inner(t, r_len) {
+                found.push((false, t, point));
+            }
+        }
+        for &point in &[edge.from, edge.to] {
+            let u = (point - other.from).dot(s) / (s_len * s_len);
+            if is_inner(u, s_len) {
+                found.push((true, u, point));
+            }
+        }
+    }
+    found
+}
+
+/// Follow the edges into closed loops. Where several edges leave the same
+/// vertex, the loop turns as far left as it can, which keeps loops that
+/// only touch separate.
+fn link_loops(edges: &[(usize, usize, Edge)], vertices: &VertexPool) -> Vec<Vec<Edge>> {
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (idx, (from, _, _)) in edges.iter().enumerate() {
+        outgoing.entry(*from).or_default().push(idx);
+    }
+    let mut used = vec![false; edges.len()];
+    let mut loops = Vec::new();
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let start = edges[first].0;
+        let mut path = vec![edges[first].2];
+        let mut current = first;
+        let closed = loop {
+            let (previous, vertex, _) = edges[current];
+            if vertex == start {
+                break true;
+            }
+            let incoming = vertices.position(vertex) - vertices.position(previous);
+            let turn = |idx: &usize| {
+                let (from, to, _) = edges[*idx];
+                let outgoing = vertices.position(to) - vertices.position(from);
+                incoming.cross(outgoing).atan2(incoming.dot(outgoing))
+            };
+            let next = outgoing
+                .get(&vertex)
+                .into_iter()
+                .flatten()
+                .filter(|idx| !used[**idx])
+                .max_by(|a, b| turn(a).total_cmp(&turn(b)));
+            match next {
+                Some(&next) => {
+                    used[next] = true;
+                    path.push(edges[next].2);
+                    current = next;
+                }
+                None => break false,
+            }
+        };
+        if closed {
+            loops.push(path);
+        }
+    }
+    loops
+}
+
+/// Merges points that are within [`MERGE_DISTANCE`] of each other.
+#[derive(Default)]
+struct VertexPool {
+    positions: Vec<Point>,
+    grid: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl VertexPool {
+    fn cell(point: Point) -> (i64, i64) {
+        ((point.x / MERGE_DISTANCE).floor() as i64, (point.y / MERGE_DISTANCE).floor() as i64)
+    }
+
+    fn find(&self, point: Point) -> Option<usize> {
+        let (x, y) = VertexPool::cell(point);
+        (x - 1..=x + 1)
+            .flat_map(|x| (y - 1..=y + 1).map(move |y| (x, y)))
+            .filter_map(|cell| self.grid.get(&cell))
+            .flatten()
+            .copied()
+            .find(|&idx| (self.positions[idx] - point).hypot() <= MERGE_DISTANCE)
+    }
+
+    fn insert(&mut self, point: Point) -> usize {
+        if let Some(idx) = self.find(point) {
+            return idx;
+        }
+        let idx = self.positions.len();
+        self.positions.push(point);
+        self.grid.entry(VertexPool::cell(point)).or_default().push(idx);
+        idx
+    }
+
+    fn position(&self, idx: usize) -> Point {
+        self.positions[idx]
+    }
+
+    /// The position of the vertex `point` was merged into.
+    fn snap(&self, point: Point) -> Point {
+        self.find(point).map_or(point, |idx| self.positions[idx])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContourPoint;
+
+    fn contour(points: &[(f32, f32, PointType)]) -> Contour {
+        let points = points
+            .iter()
+            .map(|(x, y, typ)| ContourPoint::new(*x, *y, typ.clone(), false, None, None, None))
+            .collect();
+        Contour::new(points, None, None)
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Contour {
+        contour(&[
+            (x, y, PointType::Line),
+            (x + width, y, PointType::Line),
+            (x + width, y + height, PointType::Line),
+            (x, y + height, PointType::Line),
+        ])
+    }
+
+    fn circle(radius: f32) -> Contour {
+        let k = radius * 0.552_284_8;
+        contour(&[
+            (radius, k, PointType::OffCurve),
+            (k, radius, PointType::OffCurve),
+            (0.0, radius, PointType::Curve),
+            (-k, radius, PointType::OffCurve),
+            (-radius, k, PointType::OffCurve),
+            (-radius, 0.0, PointType::Curve),
+            (-radius, -k, PointType::OffCurve),
+            (-k, -radius, PointType::OffCurve),
+            (0.0, -radius, PointType::Curve),
+            (k, -radius, PointType::OffCurve),
+            (radius, -k, PointType::OffCurve),
+            (radius, 0.0, PointType::Curve),
+        ])
+    }
+
+    fn area(contours: &[Contour]) -> f64 {
+        contours.iter().map(|c| c.signed_area().unwrap()).sum()
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let a = [rect(0.0, 0.0, 100.0, 100.0)];
+        let b = [rect(50.0, 50.0, 100.0, 100.0)];
+
+        let union = apply(Operation::Union, &a, &b).unwrap();
+        assert_eq!(union.len(), 1);
+        assert_eq!(union[0].points.len(), 8);
+        assert_eq!(area(&union), 17500.0);
+
+        let difference = apply(Operation::Difference, &a, &b).unwrap();
+        assert_eq!(difference.len(), 1);
+        assert_eq!(difference[0].points.len(), 6);
+        assert_eq!(area(&difference), 7500.0);
+
+        let intersection = apply(Operation::Intersection, &a, &b).unwrap();
+        assert_eq!(intersection.len(), 1);
+        assert_eq!(intersection[0].points.len(), 4);
+        assert_eq!(area(&intersection), 2500.0);
+
+        let xor = apply(Operation::Xor, &a, &b).unwrap();
+        assert_eq!(xor.len(), 2);
+        assert_eq!(area(&xor), 15000.0);
+        assert!(xor.iter().all(|c| !c.is_clockwise().unwrap()));
+    }
+
+    #[test]
+    fn shared_edges() {
+        // Side by side, sharing an edge in opposite directions.
+        let union = apply(
+            Operation::Union,
+            &[rect(0.0, 0.0, 100.0, 100.0), rect(100.0, 0.0, 100.0, 100.0)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(union.len(), 1);
+        assert_eq!(area(&union), 20000.0);
+
+        // Cutting away a part that shares edges with the original.
+        let difference = apply(
+            Operation::Difference,
+            &[rect(0.0, 0.0, 200.0, 100.0)],
+            &[rect(100.0, 0.0, 100.0, 100.0)],
+        )
+        .unwrap();
+        assert_eq!(difference.len(), 1);
+        assert_eq!(area(&difference), 10000.0);
+        assert_eq!(difference[0].points.len(), 4);
+
+        // Touching at a corner only.
+        let union = apply(
+            Operation::Union,
+            &[rect(0.0, 0.0, 100.0, 100.0), rect(100.0, 100.0, 100.0, 100.0)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(union.len(), 2);
+    }
+
+    #[test]
+    fn curves_are_kept() {
+        let union =
+            apply(Operation::Union, &[circle(100.0)], &[rect(0.0, 0.0, 200.0, 200.0)]).unwrap();
+        assert_eq!(union.len(), 1);
+        let expected = std::f64::consts::PI * 10000.0 * 0.75 + 40000.0;
+        assert!((area(&union) - expected).abs() < 10.0, "{}", area(&union));
+        // Three quarters of the circle survive, as three cubics.
+        let curves = union[0].points.iter().filter(|p| p.typ == PointType::Curve).count();
+        assert_eq!(curves, 3);
+        assert_eq!(union[0].points.len(), 7 + 3 * 2);
+        // The on-curves between two arcs are smooth, the ones at the square are not.
+        assert_eq!(union[0].points.iter().filter(|p| p.smooth).count(), 2);
+
+        // A curve cut in the middle.
+        let difference =
+            apply(Operation::Difference, &[circle(100.0)], &[rect(0.0, -200.0, 200.0, 400.0)])
+                .unwrap();
+        assert_eq!(difference.len(), 1);
+        let expected = std::f64::consts::PI * 5000.0;
+        assert!((area(&difference) - expected).abs() < 10.0, "{}", area(&difference));
+    }
+
+    #[test]
+    fn untouched_contours() {
+        // An "o": the counter stays, and nothing is rewritten.
+        let mut outer = rect(0.0, 0.0, 300.0, 300.0);
+        outer.points[0].name = Some("start".into());
+        let mut counter = rect(100.0, 100.0, 100.0, 100.0);
+        counter.reverse();
+        let mut glyph = Glyph::new_named("o");
+        glyph.contours = vec![outer, counter];
+        let open = contour(&[(0.0, 0.0, PointType::Move), (10.0, 10.0, PointType::Line)]);
+        glyph.contours.push(open);
+        let original = glyph.clone();
+        glyph.remove_overlap().unwrap();
+        assert_eq!(glyph.contours.len(), 3);
+        assert_eq!(glyph.contours[0], original.contours[2]);
+        assert_eq!(&glyph.contours[1..], &original.contours[..2]);
+
+        // A contour drawn the wrong way round is reversed.
+        let mut clockwise = rect(0.0, 0.0, 100.0, 100.0);
+        clockwise.reverse();
+        let result = apply(Operation::Union, &[clockwise], &[]).unwrap();
+        assert!(!result[0].is_clockwise().unwrap());
+
+        // Same-direction nested contours merge under the non-zero rule.
+        let result = apply(
+            Operation::Union,
+            &[rect(0.0, 0.0, 300.0, 300.0), rect(100.0, 100.0, 100.0, 100.0)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(area(&result), 90000.0);
+    }
+
+    #[test]
+    fn self_intersection() {
+        let bowtie = contour(&[
+            (0.0, 0.0, PointType::Line),
+            (100.0, 100.0, PointType::Line),
+            (100.0, 0.0, PointType::Line),
+            (0.0, 100.0, PointType::Line),
+        ]);
+        let result = apply(Operation::Union, &[bowtie], &[]).unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|c| c.points.len() == 3 && !c.is_clockwise().unwrap()));
+        assert_eq!(area(&result), 5000.0);
+    }
+
+    #[test]
+    fn glyph_boolean() {
+        let mut glyph = Glyph::new_named("a");
+        glyph.contours.push(rect(0.0, 0.0, 100.0, 100.0));
+        let mut other = Glyph::new_named("b");
+        other.contours.push(rect(50.0, 0.0, 100.0, 100.0));
+        glyph.boolean(Operation::Intersection, &other).unwrap();
+        assert_eq!(glyph.contours.len(), 1);
+        assert_eq!(area(&glyph.contours), 5000.0);
+    }
+}
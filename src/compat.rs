@@ -0,0 +1,332 @@
+//! Checking that masters are compatible for interpolation.
+//!
+//! Glyphs are compatible if they have the same number of contours, with the
+//! same number and types of points, the same components in the same order,
+//! the same anchors in the same order, and guidelines of the same kinds.
+//! Each master is compared against the first one, and every difference found
+//! is reported along with where it occurs.
+//!
+//! # Examples
+//!
+//! ```
+//! use norad::compat::{self, Incompatibility};
+//! use norad::{Anchor, Glyph, PointType};
+//!
+//! let mut regular = Glyph::new_named("a");
+//! regular.anchors.push(Anchor::new(250., 0., Some("bottom".into()), None, None, None));
+//! let mut bold = Glyph::new_named("a");
+//! bold.anchors.push(Anchor::new(270., 0., Some("top".into()), None, None, None));
+//!
+//! let issues = compat::check_glyphs(&[&regular, &bold]);
+//! assert_eq!(
+//!     issues,
+//!     vec![Incompatibility::AnchorName {
+//!         master: 1,
+//!         anchor: 0,
+//!         expected: Some("bottom".into()),
+//!         found: Some("top".into()),
+//!     }]
+//! );
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::{Font, Glyph, GlyphName, Layer, Line, PointType};
+
+/// A difference between masters that prevents them from being interpolated.
+///
+/// `master` is the index of the master that differs from the first one, in
+/// the order the masters were passed in.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Incompatibility {
+    /// The glyph does not exist in this master.
+    MissingGlyph { master: usize },
+    /// The glyph has a different number of contours.
+    ContourCount { master: usize, expected: usize, found: usize },
+    /// A contour has a different number of points.
+    PointCount { master: usize, contour: usize, expected: usize, found: usize },
+    /// A point has a different type.
+    PointType { master: usize, contour: usize, point: usize, expected: PointType, found: PointType },
+    /// The glyph has a different number of components.
+    ComponentCount { master: usize, expected: usize, found: usize },
+    /// A component refers to a different base glyph.
+    ComponentBase { master: usize, component: usize, expected: GlyphName, found: GlyphName },
+    /// The glyph has a different number of anchors.
+    AnchorCount { master: usize, expected: usize, found: usize },
+    /// An anchor has a different name.
+    AnchorName { master: usize, anchor: usize, expected: Option<String>, found: Option<String> },
+    /// The glyph has a different number of guidelines.
+    GuidelineCount { master: usize, expected: usize, found: usize },
+    /// A guideline is of a different kind: vertical, horizontal or angled.
+    GuidelineKind { master: usize, guideline: usize },
+}
+
+/// Compare glyphs, returning every way in which they are incompatible.
+///
+/// The result is empty if the glyphs are compatible. Glyph names are not
+/// compared.
+pub fn check_glyphs(glyphs: &[&Glyph]) -> Vec<Incompatibility> {
+    let mut issues = Vec::new();
+    let (first, others) = match glyphs.split_first() {
+        Some(split) => split,
+        None => return issues,
+    };
+    for (idx, glyph) in others.iter().enumerate() {
+        check_glyph(first, glyph, idx + 1, &mut issues);
+    }
+    issues
+}
+
+/// Compare the glyphs in several layers, returning the incompatibilities of
+/// each glyph that has any.
+///
+/// A glyph that is missing from some of the layers is reported as missing
+/// from those, and is otherwise compared across the layers that have it.
+pub fn check_layers(layers: &[&Layer]) -> BTreeMap<GlyphName, Vec<Incompatibility>> {
+    let names: BTreeSet<&GlyphName> =
+        layers.iter().flat_map(|layer| layer.iter()).map(|glyph| &glyph.name).collect();
+    let mut result = BTreeMap::new();
+    for name in names {
+        let mut issues = Vec::new();
+        let mut first = None;
+        for (master, layer) in layers.iter().enumerate() {
+            match (layer.get_glyph(&**name), first) {
+                (None, _) => issues.push(Incompatibility::MissingGlyph { master }),
+                (Some(glyph), None) => first = Some(glyph),
+                (Some(glyph), Some(first)) => check_glyph(first, glyph, master, &mut issues),
+            }
+        }
+        if !issues.is_empty() {
+            result.insert(name.clone(), issues);
+        }
+    }
+    result
+}
+
+/// Compare the glyphs in the default layers of several fonts.
+///
+/// See [`check_layers`] for details.
+pub fn check_fonts(fonts: &[&Font]) -> BTreeMap<GlyphName, Vec<Incompatibility>> {
+    let layers: Vec<_> = fonts.iter().map(|font| font.default_layer()).collect();
+    check_layers(&layers)
+}
+
+fn check_glyph(first: &Glyph, other: &Glyph, master: usize, issues: &mut Vec<Incompatibility>) {
+    if first.contours.len() != other.contours.len() {
+        issues.push(Incompatibility::ContourCount {
+            master,
+            expected: first.contours.len(),
+            found: other.contours.len(),
+        });
+    } else {
+        for (contour, (a, b)) in first.contours.iter().zip(&other.contours).enumerate() {
+            if a.points.len() != b.points.len() {
+                issues.push(Incompatibility::PointCount {
+                    master,
+                    contour,
+                    expected: a.points.len(),
+                    found: b.points.len(),
+                });
+                continue;
+            }
+            for (point, (a, b)) in a.points.iter().zip(&b.points).enumerate() {
+                if a.typ != b.typ {
+                    issues.push(Incompatibility::PointType {
+                        master,
+                        contour,
+                        point,
+                        expected: a.typ.clone(),
+                        found: b.typ.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    if first.components.len() != other.components.len() {
+        issues.push(Incompatibility::ComponentCount {
+            master,
+            expected: first.components.len(),
+            found: other.components.len(),
+        });
+    } else {
+        for (component, (a, b)) in first.components.iter().zip(&other.components).enumerate() {
+            if a.base != b.base {
+                issues.push(Incompatibility::ComponentBase {
+                    master,
+                    component,
+                    expected: a.base.clone(),
+                    found: b.base.clone(),
+                });
+            }
+        }
+    }
+
+    if first.anchors.len() != other.anchors.len() {
+        issues.push(Incompatibility::AnchorCount {
+            master,
+            expected: first.anchors.len(),
+            found: other.anchors.len(),
+        });
+    } else {
+        for (anchor, (a, b)) in first.anchors.iter().zip(&other.anchors).enumerate() {
+            if a.name != b.name {
+                issues.push(Incompatibility::AnchorName {
+                    master,
+                    anchor,
+                    expected: a.name.clone(),
+                    found: b.name.clone(),
+                });
+            }
+        }
+    }
+
+    if first.guidelines.len() != other.guidelines.len() {
+        issues.push(Incompatibility::GuidelineCount {
+            master,
+            expected: first.guidelines.len(),
+            found: other.guidelines.len(),
+        });
+    } else {
+        for (guideline, (a, b)) in first.guidelines.iter().zip(&other.guidelines).enumerate() {
+            let same_kind = matches!(
+                (&a.line, &b.line),
+                (Line::Vertical(_), Line::Vertical(_))
+                    | (Line::Horizontal(_), Line::Horizontal(_))
+                    | (Line::Angle { .. }, Line::Angle { .. })
+            );
+            if !same_kind {
+                issues.push(Incompatibility::GuidelineKind { master, guideline });
+            }
+        }
+    }
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Incompatibility::MissingGlyph { master } => {
+                write!(f, "master {}: glyph is missing", master)
+            }
+            Incompatibility::ContourCount { master, expected, found } => {
+                write!(f, "master {}: expected {} contours, found {}", master, expected, found)
+            }
+            Incompatibility::PointCount { master, contour, expected, found } => write!(
+                f,
+                "master {}, contour {}: expected {} points, found {}",
+                master, contour, expected, found
+            ),
+            Incompatibility::PointType { master, contour, point, expected, found } => write!(
+                f,
+                "master {}, contour {}, point {}: expected a {} point, found a {} point",
+                master,
+                contour,
+                point,
+                expected.as_str(),
+                found.as_str()
+            ),
+            Incompatibility::ComponentCount { master, expected, found } => {
+                write!(f, "master {}: expected {} components, found {}", master, expected, found)
+            }
+            Incompatibility::ComponentBase { master, component, expected, found } => write!(
+                f,
+                "master {}, component {}: expected base glyph '{}', found '{}'",
+                master, component, expected, found
+            ),
+            Incompatibility::AnchorCount { master, expected, found } => {
+                write!(f, "master {}: expected {} anchors, found {}", master, expected, found)
+            }
+            Incompatibility::AnchorName { master, anchor, expected, found } => write!(
+                f,
+                "master {}, anchor {}: expected name {:?}, found {:?}",
+                master, anchor, expected, found
+            ),
+            Incompatibility::GuidelineCount { master, expected, found } => {
+                write!(f, "master {}: expected {} guidelines, found {}", master, expected, found)
+            }
+            Incompatibility::GuidelineKind { master, guideline } => {
+                write!(f, "master {}, guideline {}: different kind of line", master, guideline)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Component, Contour, ContourPoint};
+
+    fn glyph(name: &str, types: &[&[PointType]]) -> Glyph {
+        let mut glyph = Glyph::new_named(name);
+        for types in types {
+            let points = types
+                .iter()
+                .map(|typ| ContourPoint::new(0., 0., typ.clone(), false, None, None, None))
+                .collect();
+            glyph.contours.push(Contour::new(points, None, None));
+        }
+        glyph
+    }
+
+    #[test]
+    fn point_structure() {
+        use PointType::*;
+        let regular = glyph("a", &[&[Line, Line, Line], &[Curve, OffCurve, OffCurve, Curve]]);
+        let bold = glyph("a", &[&[Line, Line, Line], &[Curve, OffCurve, OffCurve, Line]]);
+        let light = glyph("a", &[&[Line, Line], &[Curve, OffCurve, OffCurve, Curve]]);
+        let black = glyph("a", &[&[Line, Line, Line]]);
+
+        assert!(check_glyphs(&[&regular, &regular.clone()]).is_empty());
+        assert_eq!(
+            check_glyphs(&[&regular, &bold, &light, &black]),
+            vec![
+                Incompatibility::PointType {
+                    master: 1,
+                    contour: 1,
+                    point: 3,
+                    expected: Curve,
+                    found: Line
+                },
+                Incompatibility::PointCount { master: 2, contour: 0, expected: 3, found: 2 },
+                Incompatibility::ContourCount { master: 3, expected: 2, found: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn layers() {
+        let mut regular = Layer::default();
+        let mut bold = Layer::default();
+        regular.insert_glyph(glyph("a", &[]));
+        bold.insert_glyph(glyph("a", &[]));
+        regular.insert_glyph(glyph("b", &[]));
+        let mut aacute = glyph("aacute", &[]);
+        aacute.components.push(Component::new("a".into(), Default::default(), None, None));
+        regular.insert_glyph(aacute.clone());
+        aacute.components[0].base = "b".into();
+        bold.insert_glyph(aacute);
+
+        let report = check_layers(&[&regular, &bold]);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report["b"], vec![Incompatibility::MissingGlyph { master: 1 }]);
+        assert_eq!(
+            report["aacute"],
+            vec![Incompatibility::ComponentBase {
+                master: 1,
+                component: 0,
+                expected: "a".into(),
+                found: "b".into()
+            }]
+        );
+        assert_eq!(
+            report["aacute"][0].to_string(),
+            "master 1, component 0: expected base glyph 'a', found 'b'"
+        );
+
+        // A glyph missing from the first layer is compared across the others.
+        let report = check_layers(&[&bold, &regular]);
+        assert_eq!(report["b"], vec![Incompatibility::MissingGlyph { master: 0 }]);
+    }
+}
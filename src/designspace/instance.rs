@@ -0,0 +1,207 @@
+//! Generating instances from the masters of a designspace.
+
+use std::collections::BTreeMap;
+
+use super::*;
+use crate::compat;
+use crate::error::InterpolationError;
+use crate::fontinfo::StyleMapStyle;
+use crate::varmodel::{normalize_value, NormalizedLocation, VariationModel};
+use crate::{FontInfo, Glyph, Layer};
+
+/// A source with the font and layer it refers to.
+struct Master<'a> {
+    font: &'a Font,
+    layer: &'a Layer,
+    sparse: bool,
+    location: NormalizedLocation,
+}
+
+impl DesignSpaceDocument {
+    /// Normalize a location, mapping each axis to the range -1 to 1 with the
+    /// default at 0.
+    ///
+    /// User space values are mapped to design space first. Axes the location
+    /// does not mention are at their default, and values outside an axis'
+    /// range are clamped to it. Only the `xvalue` of an anisotropic location
+    /// is used.
+    pub fn normalize_location(&self, location: &[Dimension]) -> NormalizedLocation {
+        self.axes
+            .iter()
+            .map(|axis| {
+                let (min, default, max) = axis.design_bounds();
+                let value = location
+                    .iter()
+                    .find(|dimension| dimension.name == axis.name)
+                    .and_then(|dimension| {
+                        dimension
+                            .xvalue
+                            .or_else(|| dimension.uservalue.map(|v| axis.map_forward(v)))
+                    })
+                    .unwrap_or(default);
+                let normalized =
+                    normalize_value(value as f64, min as f64, default as f64, max as f64);
+                (axis.name.clone(), normalized)
+            })
+            .collect()
+    }
+
+    /// Generate the font for an instance from the fonts of the sources.
+    ///
+    /// `fonts` holds the font for each source filename, as returned by
+    /// [`DesignSpaceDocument::load_fonts`]. The font info, kerning and
+    /// glyphs are interpolated from the sources without a `layer`, as
+    /// described in [`Font::weighted_sum`]. Sources with a `layer` are sparse
+    /// masters, which only take part in interpolating the glyphs they
+    /// contain. Glyphs are taken from the default layer of the source at the
+    /// default location, and the names in the font info are set from the
+    /// instance.
+    ///
+    /// A glyph that can't be interpolated is an error, naming the glyph: the
+    /// sources that have it may not form a variation model, or its outlines
+    /// may not be compatible. In the list of incompatibilities, masters are
+    /// counted among the sources that have the glyph, in order.
+    pub fn generate_instance(
+        &self,
+        fonts: &BTreeMap<String, Font>,
+        instance: &Instance,
+    ) -> Result<Font, Error> {
+        let masters = self.masters(fonts)?;
+        let axis_order: Vec<String> = self.axes.iter().map(|axis| axis.name.clone()).collect();
+        let location = self.normalize_location(&instance.location);
+
+        // Interpolate everything but the glyphs from the full masters.
+        let full: Vec<&Master> = masters.iter().filter(|master| !master.sparse).collect();
+        let locations: Vec<_> = full.iter().map(|master| master.location.clone()).collect();
+        let model = VariationModel::new(&locations, &axis_order).map_err(Error::VariationModel)?;
+        let weights = model.master_weights(&location);
+        let base = locations.iter().position(is_default).expect("model has a base master");
+        let fonts: Vec<(&Font, f64)> = std::iter::once((full[base].font, weights[base]))
+            .chain(
+                full.iter()
+                    .zip(&weights)
+                    .enumerate()
+                    .filter(|(idx, (_, weight))| *idx != base && **weight != 0.0)
+                    .map(|(_, (master, weight))| (master.font, *weight)),
+            )
+            .collect();
+        let mut font = Font::weighted_sum(&fonts).expect("there is at least one master");
+
+        // Each glyph is interpolated from the masters that have it.
+        let mut glyph_weights = BTreeMap::new();
+        let base_layer = full[base].layer;
+        let layer = font.default_layer_mut();
+        layer.clear();
+        for glyph in base_layer.iter() {
+            let indices: Vec<usize> = masters
+                .iter()
+                .enumerate()
+                .filter(|(_, master)| master.layer.contains_glyph(&glyph.name))
+                .map(|(idx, _)| idx)
+                .collect();
+            let weights = glyph_weights.entry(indices.clone()).or_insert_with(|| {
+                let locations: Vec<_> =
+                    indices.iter().map(|idx| masters[*idx].location.clone()).collect();
+                VariationModel::new(&locations, &axis_order)
+                    .map(|model| model.master_weights(&location))
+            });
+            let weights = match weights {
+                Ok(weights) => weights,
+                Err(inner) => {
+                    let glyph = glyph.name.to_string();
+                    return Err(Error::GlyphVariationModel { glyph, inner: inner.clone() });
+                }
+            };
+            let masters_glyphs: Vec<&Glyph> = indices
+                .iter()
+                .map(|idx| &**masters[*idx].layer.get_glyph(&*glyph.name).expect("checked above"))
+                .collect();
+            let issues = compat::check_glyphs(&masters_glyphs);
+            if !issues.is_empty() {
+                let inner = InterpolationError::Incompatible(issues);
+                return Err(Error::InterpolateGlyph { glyph: glyph.name.to_string(), inner });
+            }
+            let glyphs: Vec<(&Glyph, f64)> = std::iter::once((&**glyph, 0.0))
+                .chain(
+                    masters_glyphs
+                        .iter()
+                        .zip(weights.iter())
+                        .filter(|(_, weight)| **weight != 0.0)
+                        .map(|(glyph, weight)| (*glyph, *weight)),
+                )
+                .collect();
+            match Glyph::weighted_sum(&glyphs) {
+                Ok(glyph) => layer.insert_glyph(glyph),
+                Err(inner) => {
+                    return Err(Error::InterpolateGlyph { glyph: glyph.name.to_string(), inner })
+                }
+            }
+        }
+
+        let info = font.font_info.get_or_insert_with(FontInfo::default);
+        let mut names = [
+            (&mut info.family_name, &instance.family_name),
+            (&mut info.style_name, &instance.style_name),
+            (&mut info.postscript_font_name, &instance.postscript_font_name),
+            (&mut info.style_map_family_name, &instance.style_map_family_name),
+        ];
+        for (field, name) in names.iter_mut() {
+            if name.is_some() {
+                **field = (*name).clone();
+            }
+        }
+        if let Some(style) = &instance.style_map_style_name {
+            info.style_map_style_name = match style.as_str() {
+                "regular" => Some(StyleMapStyle::Regular),
+                "italic" => Some(StyleMapStyle::Italic),
+                "bold" => Some(StyleMapStyle::Bold),
+                "bold italic" => Some(StyleMapStyle::BoldItalic),
+                _ => info.style_map_style_name.take(),
+            };
+        }
+        Ok(font)
+    }
+
+    /// Generate the fonts for all instances.
+    ///
+    /// See [`DesignSpaceDocument::generate_instance`] for details.
+    pub fn generate_instances(&self, fonts: &BTreeMap<String, Font>) -> Result<Vec<Font>, Error> {
+        self.instances.iter().map(|instance| self.generate_instance(fonts, instance)).collect()
+    }
+
+    fn masters<'a>(&self, fonts: &'a BTreeMap<String, Font>) -> Result<Vec<Master<'a>>, Error> {
+        self.sources
+            .iter()
+            .map(|source| {
+                let font = fonts
+                    .get(&source.filename)
+                    .ok_or_else(|| Error::MissingSource(source.filename.clone()))?;
+                let layer = match &source.layer {
+                    Some(name) => {
+                        font.layers.get(name).ok_or_else(|| Error::MissingLayer(name.clone()))?
+                    }
+                    None => font.default_layer(),
+                };
+                let location = self.normalize_location(&source.location);
+                Ok(Master { font, layer, sparse: source.layer.is_some(), location })
+            })
+            .collect()
+    }
+}
+
+impl Axis {
+    /// The minimum, default and maximum of the axis in design space.
+    fn design_bounds(&self) -> (f32, f32, f32) {
+        let (min, max) = match &self.values {
+            Some(values) => values
+                .iter()
+                .fold((self.default, self.default), |(min, max), v| (min.min(*v), max.max(*v))),
+            None => (self.minimum.unwrap_or(self.default), self.maximum.unwrap_or(self.default)),
+        };
+        (self.map_forward(min), self.map_forward(self.default), self.map_forward(max))
+    }
+}
+
+fn is_default(location: &NormalizedLocation) -> bool {
+    location.values().all(|value| *value == 0.0)
+}
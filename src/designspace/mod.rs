@@ -0,0 +1,299 @@
+//! Reading and writing [designspace] documents.
+//!
+//! A designspace document describes a family of fonts: the axes along which
+//! the design varies, the masters (sources) at various locations on those
+//! axes, the instances to generate from them, and rules for substituting
+//! glyphs at some locations. Both version 4 and version 5 documents can be
+//! read; documents are written as version 5 if they use features only it
+//! supports, such as discrete axes or user space locations, and as version 4
+//! otherwise.
+//!
+//! Elements that this module does not model, such as the axis and location
+//! labels and variable font definitions of version 5, are skipped when
+//! reading, and are not written back out.
+//!
+//! [designspace]: https://fonttools.readthedocs.io/en/latest/designspaceLib/xml.html
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{DesignSpaceError, Error};
+use crate::{Font, Plist};
+
+mod instance;
+mod parse;
+mod serialize;
+
+#[cfg(test)]
+mod tests;
+
+/// A designspace document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DesignSpaceDocument {
+    /// The format version the document was read as, such as `"4.1"` or
+    /// `"5.0"`.
+    ///
+    /// This is informational; the version written is decided by the
+    /// features the document uses.
+    pub format_version: Option<String>,
+    /// The axes of the design space.
+    pub axes: Vec<Axis>,
+    /// The rules for substituting glyphs.
+    pub rules: Rules,
+    /// The masters.
+    pub sources: Vec<Source>,
+    /// The instances.
+    pub instances: Vec<Instance>,
+    /// Arbitrary data.
+    pub lib: Plist,
+}
+
+/// An axis of the design space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    /// The name of the axis, which locations refer to it by.
+    pub name: String,
+    /// The four-letter OpenType tag of the axis.
+    pub tag: String,
+    /// The minimum value of a continuous axis, in user space.
+    pub minimum: Option<f32>,
+    /// The default value, in user space.
+    pub default: f32,
+    /// The maximum value of a continuous axis, in user space.
+    pub maximum: Option<f32>,
+    /// The values of a discrete axis, in user space.
+    pub values: Option<Vec<f32>>,
+    /// Whether the axis should be hidden in user interfaces.
+    pub hidden: bool,
+    /// Localized names of the axis, by language tag.
+    pub label_names: BTreeMap<String, String>,
+    /// The mapping from user space to design space, if they differ.
+    pub map: Vec<AxisMapping>,
+}
+
+/// A point in the mapping of an axis from user space to design space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisMapping {
+    /// The user space value.
+    pub input: f32,
+    /// The design space value.
+    pub output: f32,
+}
+
+/// The coordinate of a location on one axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dimension {
+    /// The name of the axis.
+    pub name: String,
+    /// The design space value.
+    pub xvalue: Option<f32>,
+    /// A second design space value, for anisotropic interpolation.
+    pub yvalue: Option<f32>,
+    /// The user space value, which version 5 documents may give instead of
+    /// the design space value.
+    pub uservalue: Option<f32>,
+}
+
+/// A location in the design space, as a list of axis coordinates.
+///
+/// Axes not mentioned are at their default.
+pub type Location = Vec<Dimension>;
+
+/// A master of the design space.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Source {
+    /// The path to the UFO, relative to the document.
+    pub filename: String,
+    /// A unique name for the source.
+    pub name: Option<String>,
+    /// The family name of the source.
+    pub family_name: Option<String>,
+    /// The style name of the source.
+    pub style_name: Option<String>,
+    /// The layer of the UFO holding the source's glyphs, if it is not the
+    /// default layer. Such a source is a sparse master.
+    pub layer: Option<String>,
+    /// The location of the source.
+    pub location: Location,
+}
+
+/// An instance to generate from the masters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Instance {
+    /// The path the instance's UFO should be written to, relative to the
+    /// document.
+    pub filename: Option<String>,
+    /// A unique name for the instance.
+    pub name: Option<String>,
+    /// The family name of the instance.
+    pub family_name: Option<String>,
+    /// The style name of the instance.
+    pub style_name: Option<String>,
+    /// The PostScript font name of the instance.
+    pub postscript_font_name: Option<String>,
+    /// The style map family name of the instance.
+    pub style_map_family_name: Option<String>,
+    /// The style map style name of the instance.
+    pub style_map_style_name: Option<String>,
+    /// The location of the instance.
+    pub location: Location,
+    /// Arbitrary data.
+    pub lib: Plist,
+}
+
+/// The glyph substitution rules of a document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rules {
+    /// Whether the rules should be applied after other substitutions, rather
+    /// than before.
+    pub processing_last: bool,
+    /// The rules.
+    pub rules: Vec<Rule>,
+}
+
+/// A rule substituting glyphs in some regions of the design space.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rule {
+    /// The name of the rule.
+    pub name: Option<String>,
+    /// The regions where the rule applies. The rule applies where all of the
+    /// conditions of any of the sets are met.
+    pub condition_sets: Vec<Vec<Condition>>,
+    /// The substitutions, as pairs of glyph names.
+    pub substitutions: Vec<(String, String)>,
+}
+
+/// A range on an axis, in design space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    /// The name of the axis.
+    pub name: String,
+    /// The minimum value, if bounded below.
+    pub minimum: Option<f32>,
+    /// The maximum value, if bounded above.
+    pub maximum: Option<f32>,
+}
+
+impl DesignSpaceDocument {
+    /// Load a designspace document from a file.
+    pub fn load(path: impl AsRef<Path>) -> Result<DesignSpaceDocument, Error> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        DesignSpaceDocument::from_xml(&data).map_err(|e| match e {
+            Error::DesignSpace(inner) => {
+                Error::DesignSpace(DesignSpaceError { path: Some(path.to_owned()), ..inner })
+            }
+            other => other,
+        })
+    }
+
+    /// Parse a designspace document from XML.
+    pub fn from_xml(xml: &[u8]) -> Result<DesignSpaceDocument, Error> {
+        parse::parse_document(xml)
+    }
+
+    /// Save the document to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let data = self.encode_xml().map_err(Error::DesignSpaceWrite)?;
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&data)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Returns the axis with the given name.
+    pub fn get_axis(&self, name: &str) -> Option<&Axis> {
+        self.axes.iter().find(|axis| axis.name == name)
+    }
+
+    /// Load the UFOs of all sources, relative to `base_dir`, which is usually
+    /// the directory the document is in.
+    ///
+    /// Each UFO is loaded once, even if several sources refer to its
+    /// layers. The fonts are returned by the filename the sources use.
+    pub fn load_fonts(&self, base_dir: impl AsRef<Path>) -> Result<BTreeMap<String, Font>, Error> {
+        let base_dir = base_dir.as_ref();
+        let mut fonts = BTreeMap::new();
+        for source in &self.sources {
+            if !fonts.contains_key(&source.filename) {
+                let font = Font::load(source.path(base_dir))?;
+                fonts.insert(source.filename.clone(), font);
+            }
+        }
+        Ok(fonts)
+    }
+}
+
+impl Axis {
+    /// Create a continuous axis.
+    pub fn new(
+        name: impl Into<String>,
+        tag: impl Into<String>,
+        min: f32,
+        default: f32,
+        max: f32,
+    ) -> Self {
+        Axis {
+            name: name.into(),
+            tag: tag.into(),
+            minimum: Some(min),
+            default,
+            maximum: Some(max),
+            values: None,
+            hidden: false,
+            label_names: BTreeMap::new(),
+            map: Vec::new(),
+        }
+    }
+
+    /// Map a user space value to design space.
+    pub fn map_forward(&self, value: f32) -> f32 {
+        piecewise_linear(self.map.iter().map(|m| (m.input, m.output)), value)
+    }
+
+    /// Map a design space value to user space.
+    pub fn map_backward(&self, value: f32) -> f32 {
+        piecewise_linear(self.map.iter().map(|m| (m.output, m.input)), value)
+    }
+}
+
+impl Dimension {
+    /// Create a dimension with a design space value.
+    pub fn new(name: impl Into<String>, xvalue: f32) -> Self {
+        Dimension { name: name.into(), xvalue: Some(xvalue), yvalue: None, uservalue: None }
+    }
+}
+
+impl Source {
+    /// The path to the source's UFO.
+    pub fn path(&self, base_dir: &Path) -> PathBuf {
+        base_dir.join(&self.filename)
+    }
+}
+
+/// Map `value` through the piecewise linear function given by the points,
+/// which are sorted by their first coordinate. Values outside the range of
+/// the points are moved along with the nearest one.
+fn piecewise_linear(points: impl Iterator<Item = (f32, f32)>, value: f32) -> f32 {
+    let mut points: Vec<_> = points.collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return value,
+    };
+    if value <= first.0 {
+        return value + first.1 - first.0;
+    }
+    if value >= last.0 {
+        return value + last.1 - last.0;
+    }
+    for pair in points.windows(2) {
+        let ((a_in, a_out), (b_in, b_out)) = (pair[0], pair[1]);
+        if value <= b_in {
+            return a_out + (b_out - a_out) * (value - a_in) / (b_in - a_in);
+        }
+    }
+    value
+}
@@ -0,0 +1,451 @@
+//! Parsing designspace documents.
+
+use std::collections::BTreeMap;
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use super::*;
+use crate::error::{DesignSpaceError, DesignSpaceErrorKind};
+
+macro_rules! err {
+    ($r:expr, $kind:expr) => {
+        Error::DesignSpace(DesignSpaceError {
+            path: None,
+            position: $r.buffer_position(),
+            kind: $kind,
+        })
+    };
+}
+
+type XmlReader<'a> = Reader<&'a [u8]>;
+
+pub(super) fn parse_document(xml: &[u8]) -> Result<DesignSpaceDocument, Error> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    reader.trim_text(true);
+
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Decl(_) | Event::Comment(_) | Event::DocType(_) | Event::PI(_) => (),
+            Event::Start(start) if start.name() == b"designspace" => {
+                let mut attrs = attributes(&reader, &start)?;
+                let format_version = attrs.remove("format");
+                if let Some(version) = &format_version {
+                    let major = version.split('.').next().and_then(|major| major.parse().ok());
+                    if !matches!(major, Some(4) | Some(5)) {
+                        return Err(err!(reader, DesignSpaceErrorKind::UnsupportedVersion));
+                    }
+                }
+                let mut document = DesignSpaceDocument { format_version, ..Default::default() };
+                parse_body(&mut reader, xml, &mut document)?;
+                return Ok(document);
+            }
+            Event::Empty(start) if start.name() == b"designspace" => {
+                let format_version = attributes(&reader, &start)?.remove("format");
+                return Ok(DesignSpaceDocument { format_version, ..Default::default() });
+            }
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::WrongFirstElement)),
+        }
+    }
+}
+
+fn parse_body(
+    reader: &mut XmlReader,
+    raw_xml: &[u8],
+    document: &mut DesignSpaceDocument,
+) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) => match start.name() {
+                b"axes" => parse_axes(reader, &mut document.axes)?,
+                b"rules" => {
+                    let processing = attributes(reader, &start)?.remove("processing");
+                    document.rules.processing_last = processing.as_deref() == Some("last");
+                    parse_rules(reader, &mut document.rules.rules)?;
+                }
+                b"sources" => parse_sources(reader, &mut document.sources)?,
+                b"instances" => parse_instances(reader, raw_xml, &mut document.instances)?,
+                b"lib" => document.lib = parse_lib(reader, raw_xml)?,
+                name => skip(reader, name.to_owned())?,
+            },
+            Event::Empty(start) if start.name() == b"rules" => {
+                let processing = attributes(reader, &start)?.remove("processing");
+                document.rules.processing_last = processing.as_deref() == Some("last");
+            }
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"designspace" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn parse_axes(reader: &mut XmlReader, axes: &mut Vec<Axis>) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == b"axis" => {
+                let mut axis = axis_from_attributes(reader, &start)?;
+                parse_axis_body(reader, &mut axis)?;
+                axes.push(axis);
+            }
+            Event::Empty(start) if start.name() == b"axis" => {
+                axes.push(axis_from_attributes(reader, &start)?);
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"axes" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn axis_from_attributes(reader: &XmlReader, start: &BytesStart) -> Result<Axis, Error> {
+    let mut attrs = attributes(reader, start)?;
+    let name = required(reader, &mut attrs, "name")?;
+    let tag = required(reader, &mut attrs, "tag")?;
+    let default = number(reader, &required(reader, &mut attrs, "default")?)?;
+    let minimum = attrs.remove("minimum").map(|v| number(reader, &v)).transpose()?;
+    let maximum = attrs.remove("maximum").map(|v| number(reader, &v)).transpose()?;
+    let values = attrs
+        .remove("values")
+        .map(|v| v.split_whitespace().map(|v| number(reader, v)).collect::<Result<Vec<_>, _>>())
+        .transpose()?;
+    if values.is_none() && (minimum.is_none() || maximum.is_none()) {
+        return Err(err!(reader, DesignSpaceErrorKind::MissingAttribute));
+    }
+    let hidden = matches!(attrs.remove("hidden").as_deref(), Some("1") | Some("true"));
+    Ok(Axis {
+        name,
+        tag,
+        minimum,
+        default,
+        maximum,
+        values,
+        hidden,
+        label_names: BTreeMap::new(),
+        map: Vec::new(),
+    })
+}
+
+fn parse_axis_body(reader: &mut XmlReader, axis: &mut Axis) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Empty(start) if start.name() == b"map" => {
+                let mut attrs = attributes(reader, &start)?;
+                let input = number(reader, &required(reader, &mut attrs, "input")?)?;
+                let output = number(reader, &required(reader, &mut attrs, "output")?)?;
+                axis.map.push(AxisMapping { input, output });
+            }
+            Event::Start(start) if start.name() == b"labelname" => {
+                let language = required(reader, &mut attributes(reader, &start)?, "xml:lang")?;
+                let name = parse_text(reader, b"labelname")?;
+                axis.label_names.insert(language, name);
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"axis" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn parse_rules(reader: &mut XmlReader, rules: &mut Vec<Rule>) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == b"rule" => {
+                let name = attributes(reader, &start)?.remove("name");
+                let mut rule = Rule { name, ..Default::default() };
+                parse_rule_body(reader, &mut rule)?;
+                rules.push(rule);
+            }
+            Event::Empty(start) if start.name() == b"rule" => {
+                let name = attributes(reader, &start)?.remove("name");
+                rules.push(Rule { name, ..Default::default() });
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"rules" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn parse_rule_body(reader: &mut XmlReader, rule: &mut Rule) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    // Conditions directly in the rule, as older writers put them, form a
+    // single condition set.
+    let mut loose_conditions = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == b"conditionset" => {
+                rule.condition_sets.push(parse_condition_set(reader)?);
+            }
+            Event::Empty(start) if start.name() == b"conditionset" => {
+                rule.condition_sets.push(Vec::new());
+            }
+            Event::Empty(start) if start.name() == b"condition" => {
+                loose_conditions.push(condition_from_attributes(reader, &start)?);
+            }
+            Event::Empty(start) if start.name() == b"sub" => {
+                let mut attrs = attributes(reader, &start)?;
+                let name = required(reader, &mut attrs, "name")?;
+                let with = required(reader, &mut attrs, "with")?;
+                rule.substitutions.push((name, with));
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"rule" => break,
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+    if !loose_conditions.is_empty() {
+        rule.condition_sets.push(loose_conditions);
+    }
+    Ok(())
+}
+
+fn parse_condition_set(reader: &mut XmlReader) -> Result<Vec<Condition>, Error> {
+    let mut buf = Vec::new();
+    let mut conditions = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Empty(start) if start.name() == b"condition" => {
+                conditions.push(condition_from_attributes(reader, &start)?);
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"conditionset" => return Ok(conditions),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn condition_from_attributes(reader: &XmlReader, start: &BytesStart) -> Result<Condition, Error> {
+    let mut attrs = attributes(reader, start)?;
+    let name = required(reader, &mut attrs, "name")?;
+    let minimum = attrs.remove("minimum").map(|v| number(reader, &v)).transpose()?;
+    let maximum = attrs.remove("maximum").map(|v| number(reader, &v)).transpose()?;
+    if minimum.is_none() && maximum.is_none() {
+        return Err(err!(reader, DesignSpaceErrorKind::MissingAttribute));
+    }
+    Ok(Condition { name, minimum, maximum })
+}
+
+fn parse_sources(reader: &mut XmlReader, sources: &mut Vec<Source>) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == b"source" => {
+                let mut source = source_from_attributes(reader, &start)?;
+                parse_source_body(reader, &mut source)?;
+                sources.push(source);
+            }
+            Event::Empty(start) if start.name() == b"source" => {
+                sources.push(source_from_attributes(reader, &start)?);
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"sources" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn source_from_attributes(reader: &XmlReader, start: &BytesStart) -> Result<Source, Error> {
+    let mut attrs = attributes(reader, start)?;
+    Ok(Source {
+        filename: required(reader, &mut attrs, "filename")?,
+        name: attrs.remove("name"),
+        family_name: attrs.remove("familyname"),
+        style_name: attrs.remove("stylename"),
+        layer: attrs.remove("layer"),
+        location: Vec::new(),
+    })
+}
+
+fn parse_source_body(reader: &mut XmlReader, source: &mut Source) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == b"location" => {
+                source.location = parse_location(reader)?;
+            }
+            // The `<info>`, `<kerning>` and similar elements of older
+            // versions are no longer meaningful.
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"source" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn parse_instances(
+    reader: &mut XmlReader,
+    raw_xml: &[u8],
+    instances: &mut Vec<Instance>,
+) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == b"instance" => {
+                let mut instance = instance_from_attributes(reader, &start)?;
+                parse_instance_body(reader, raw_xml, &mut instance)?;
+                instances.push(instance);
+            }
+            Event::Empty(start) if start.name() == b"instance" => {
+                instances.push(instance_from_attributes(reader, &start)?);
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"instances" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn instance_from_attributes(reader: &XmlReader, start: &BytesStart) -> Result<Instance, Error> {
+    let mut attrs = attributes(reader, start)?;
+    Ok(Instance {
+        filename: attrs.remove("filename"),
+        name: attrs.remove("name"),
+        family_name: attrs.remove("familyname"),
+        style_name: attrs.remove("stylename"),
+        postscript_font_name: attrs.remove("postscriptfontname"),
+        style_map_family_name: attrs.remove("stylemapfamilyname"),
+        style_map_style_name: attrs.remove("stylemapstylename"),
+        location: Vec::new(),
+        lib: Plist::new(),
+    })
+}
+
+fn parse_instance_body(
+    reader: &mut XmlReader,
+    raw_xml: &[u8],
+    instance: &mut Instance,
+) -> Result<(), Error> {
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) if start.name() == b"location" => {
+                instance.location = parse_location(reader)?;
+            }
+            Event::Start(start) if start.name() == b"lib" => {
+                instance.lib = parse_lib(reader, raw_xml)?;
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"instance" => return Ok(()),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+fn parse_location(reader: &mut XmlReader) -> Result<Location, Error> {
+    let mut buf = Vec::new();
+    let mut location = Vec::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Empty(start) if start.name() == b"dimension" => {
+                let mut attrs = attributes(reader, &start)?;
+                let name = required(reader, &mut attrs, "name")?;
+                let mut value = |key| attrs.remove(key).map(|v| number(reader, &v)).transpose();
+                let dimension = Dimension {
+                    name,
+                    xvalue: value("xvalue")?,
+                    yvalue: value("yvalue")?,
+                    uservalue: value("uservalue")?,
+                };
+                if dimension.xvalue.is_none() && dimension.uservalue.is_none() {
+                    return Err(err!(reader, DesignSpaceErrorKind::MissingAttribute));
+                }
+                location.push(dimension);
+            }
+            Event::Start(start) => skip(reader, start.name().to_owned())?,
+            Event::Empty(_) | Event::Comment(_) => (),
+            Event::End(ref end) if end.name() == b"location" => return Ok(location),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+/// Parse the plist dictionary inside a `<lib>` element, by handing the raw
+/// XML between the tags to the plist crate.
+fn parse_lib(reader: &mut XmlReader, raw_xml: &[u8]) -> Result<Plist, Error> {
+    let mut buf = Vec::new();
+    let start = reader.buffer_position();
+    let mut end = start;
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::End(ref end) if end.name() == b"lib" => break,
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => end = reader.buffer_position(),
+        }
+    }
+
+    let plist_slice = &raw_xml[start..end];
+    plist::Value::from_reader_xml(plist_slice)
+        .ok()
+        .and_then(plist::Value::into_dictionary)
+        .ok_or_else(|| err!(reader, DesignSpaceErrorKind::BadLib))
+}
+
+fn parse_text(reader: &mut XmlReader, tag: &[u8]) -> Result<String, Error> {
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Text(t) => text.push_str(&t.unescape_and_decode(reader)?),
+            Event::End(ref end) if end.name() == tag => return Ok(text),
+            Event::Eof => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedEof)),
+            _other => return Err(err!(reader, DesignSpaceErrorKind::UnexpectedElement)),
+        }
+    }
+}
+
+/// Skip an element we don't model, along with its contents.
+fn skip(reader: &mut XmlReader, name: Vec<u8>) -> Result<(), Error> {
+    reader.read_to_end(name, &mut Vec::new())?;
+    Ok(())
+}
+
+fn attributes(reader: &XmlReader, start: &BytesStart) -> Result<BTreeMap<String, String>, Error> {
+    start
+        .attributes()
+        .map(|attr| {
+            let attr = attr?;
+            let key = reader.decode(attr.key)?.to_owned();
+            Ok((key, attr.unescape_and_decode_value(reader)?))
+        })
+        .collect()
+}
+
+fn required(
+    reader: &XmlReader,
+    attrs: &mut BTreeMap<String, String>,
+    key: &str,
+) -> Result<String, Error> {
+    attrs.remove(key).ok_or_else(|| err!(reader, DesignSpaceErrorKind::MissingAttribute))
+}
+
+fn number(reader: &XmlReader, value: &str) -> Result<f32, Error> {
+    value.trim().parse().map_err(|_| err!(reader, DesignSpaceErrorKind::BadNumber))
+}
@@ -0,0 +1,234 @@
+//! Writing out designspace documents.
+
+use std::io::{Cursor, Write};
+
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Writer,
+};
+
+use super::*;
+use crate::error::WriteError;
+use crate::glyph::serialize::write_lib_section;
+
+type XmlWriter = Writer<Cursor<Vec<u8>>>;
+
+impl DesignSpaceDocument {
+    /// Serialize the document into an XML byte stream.
+    pub fn encode_xml(&self) -> Result<Vec<u8>, WriteError> {
+        let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b'\t', 1);
+        writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)))?;
+        let mut start = BytesStart::borrowed_name(b"designspace");
+        start.push_attribute(("format", if self.needs_version_5() { "5.0" } else { "4.1" }));
+        writer.write_event(Event::Start(start))?;
+
+        if !self.axes.is_empty() {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"axes")))?;
+            for axis in &self.axes {
+                axis.write_xml(&mut writer)?;
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(b"axes")))?;
+        }
+
+        if !self.rules.rules.is_empty() {
+            let mut start = BytesStart::borrowed_name(b"rules");
+            if self.rules.processing_last {
+                start.push_attribute(("processing", "last"));
+            }
+            writer.write_event(Event::Start(start))?;
+            for rule in &self.rules.rules {
+                rule.write_xml(&mut writer)?;
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(b"rules")))?;
+        }
+
+        if !self.sources.is_empty() {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"sources")))?;
+            for source in &self.sources {
+                source.write_xml(&mut writer)?;
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(b"sources")))?;
+        }
+
+        if !self.instances.is_empty() {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"instances")))?;
+            for instance in &self.instances {
+                instance.write_xml(&mut writer)?;
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(b"instances")))?;
+        }
+
+        if !self.lib.is_empty() {
+            write_lib_section(&self.lib, &mut writer, 1)?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::borrowed(b"designspace")))?;
+        writer.inner().write_all("\n".as_bytes())?;
+        writer.inner().flush()?;
+
+        Ok(writer.into_inner().into_inner())
+    }
+
+    /// Returns `true` if the document uses features that need version 5.
+    fn needs_version_5(&self) -> bool {
+        let user_locations = self
+            .sources
+            .iter()
+            .map(|source| &source.location)
+            .chain(self.instances.iter().map(|instance| &instance.location))
+            .flatten()
+            .any(|dimension| dimension.uservalue.is_some());
+        let instance_libs = self.instances.iter().any(|instance| !instance.lib.is_empty());
+        let axis_features = self.axes.iter().any(|axis| axis.values.is_some() || axis.hidden);
+        user_locations || instance_libs || axis_features
+    }
+}
+
+impl Axis {
+    fn write_xml(&self, writer: &mut XmlWriter) -> Result<(), WriteError> {
+        let mut start = BytesStart::borrowed_name(b"axis");
+        start.push_attribute(("tag", self.tag.as_str()));
+        start.push_attribute(("name", self.name.as_str()));
+        if let Some(values) = &self.values {
+            let values: Vec<_> = values.iter().map(f32::to_string).collect();
+            start.push_attribute(("values", values.join(" ").as_str()));
+        }
+        if let Some(minimum) = self.minimum {
+            start.push_attribute(("minimum", minimum.to_string().as_str()));
+        }
+        if let Some(maximum) = self.maximum {
+            start.push_attribute(("maximum", maximum.to_string().as_str()));
+        }
+        start.push_attribute(("default", self.default.to_string().as_str()));
+        if self.hidden {
+            start.push_attribute(("hidden", "1"));
+        }
+
+        if self.label_names.is_empty() && self.map.is_empty() {
+            writer.write_event(Event::Empty(start))?;
+            return Ok(());
+        }
+        writer.write_event(Event::Start(start))?;
+        for (language, name) in &self.label_names {
+            let mut start = BytesStart::borrowed_name(b"labelname");
+            start.push_attribute(("xml:lang", language.as_str()));
+            writer.write_event(Event::Start(start))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(name)))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"labelname")))?;
+        }
+        for mapping in &self.map {
+            let mut start = BytesStart::borrowed_name(b"map");
+            start.push_attribute(("input", mapping.input.to_string().as_str()));
+            start.push_attribute(("output", mapping.output.to_string().as_str()));
+            writer.write_event(Event::Empty(start))?;
+        }
+        writer.write_event(Event::End(BytesEnd::borrowed(b"axis")))?;
+        Ok(())
+    }
+}
+
+impl Rule {
+    fn write_xml(&self, writer: &mut XmlWriter) -> Result<(), WriteError> {
+        let mut start = BytesStart::borrowed_name(b"rule");
+        if let Some(name) = &self.name {
+            start.push_attribute(("name", name.as_str()));
+        }
+        writer.write_event(Event::Start(start))?;
+        for conditions in &self.condition_sets {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"conditionset")))?;
+            for condition in conditions {
+                let mut start = BytesStart::borrowed_name(b"condition");
+                start.push_attribute(("name", condition.name.as_str()));
+                if let Some(minimum) = condition.minimum {
+                    start.push_attribute(("minimum", minimum.to_string().as_str()));
+                }
+                if let Some(maximum) = condition.maximum {
+                    start.push_attribute(("maximum", maximum.to_string().as_str()));
+                }
+                writer.write_event(Event::Empty(start))?;
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(b"conditionset")))?;
+        }
+        for (name, with) in &self.substitutions {
+            let mut start = BytesStart::borrowed_name(b"sub");
+            start.push_attribute(("name", name.as_str()));
+            start.push_attribute(("with", with.as_str()));
+            writer.write_event(Event::Empty(start))?;
+        }
+        writer.write_event(Event::End(BytesEnd::borrowed(b"rule")))?;
+        Ok(())
+    }
+}
+
+impl Source {
+    fn write_xml(&self, writer: &mut XmlWriter) -> Result<(), WriteError> {
+        let mut start = BytesStart::borrowed_name(b"source");
+        start.push_attribute(("filename", self.filename.as_str()));
+        push_optional(&mut start, "name", &self.name);
+        push_optional(&mut start, "familyname", &self.family_name);
+        push_optional(&mut start, "stylename", &self.style_name);
+        push_optional(&mut start, "layer", &self.layer);
+        if self.location.is_empty() {
+            writer.write_event(Event::Empty(start))?;
+            return Ok(());
+        }
+        writer.write_event(Event::Start(start))?;
+        write_location(&self.location, writer)?;
+        writer.write_event(Event::End(BytesEnd::borrowed(b"source")))?;
+        Ok(())
+    }
+}
+
+impl Instance {
+    fn write_xml(&self, writer: &mut XmlWriter) -> Result<(), WriteError> {
+        let mut start = BytesStart::borrowed_name(b"instance");
+        push_optional(&mut start, "name", &self.name);
+        push_optional(&mut start, "familyname", &self.family_name);
+        push_optional(&mut start, "stylename", &self.style_name);
+        push_optional(&mut start, "filename", &self.filename);
+        push_optional(&mut start, "postscriptfontname", &self.postscript_font_name);
+        push_optional(&mut start, "stylemapfamilyname", &self.style_map_family_name);
+        push_optional(&mut start, "stylemapstylename", &self.style_map_style_name);
+        if self.location.is_empty() && self.lib.is_empty() {
+            writer.write_event(Event::Empty(start))?;
+            return Ok(());
+        }
+        writer.write_event(Event::Start(start))?;
+        write_location(&self.location, writer)?;
+        if !self.lib.is_empty() {
+            write_lib_section(&self.lib, writer, 3)?;
+        }
+        writer.write_event(Event::End(BytesEnd::borrowed(b"instance")))?;
+        Ok(())
+    }
+}
+
+fn write_location(location: &[Dimension], writer: &mut XmlWriter) -> Result<(), WriteError> {
+    if location.is_empty() {
+        return Ok(());
+    }
+    writer.write_event(Event::Start(BytesStart::borrowed_name(b"location")))?;
+    for dimension in location {
+        let mut start = BytesStart::borrowed_name(b"dimension");
+        start.push_attribute(("name", dimension.name.as_str()));
+        let values = [
+            ("uservalue", dimension.uservalue),
+            ("xvalue", dimension.xvalue),
+            ("yvalue", dimension.yvalue),
+        ];
+        for (key, value) in values.iter() {
+            if let Some(value) = value {
+                start.push_attribute((*key, value.to_string().as_str()));
+            }
+        }
+        writer.write_event(Event::Empty(start))?;
+    }
+    writer.write_event(Event::End(BytesEnd::borrowed(b"location")))?;
+    Ok(())
+}
+
+fn push_optional(start: &mut BytesStart, key: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        start.push_attribute((key, value.as_str()));
+    }
+}
@@ -0,0 +1,219 @@
+use super::*;
+use crate::compat::Incompatibility;
+use crate::error::{DesignSpaceErrorKind, InterpolationError, VariationModelError};
+
+#[test]
+fn load_designspace() {
+    let path = "testdata/designspace/TestFamily.designspace";
+    let document = DesignSpaceDocument::load(path).unwrap();
+    assert_eq!(document.format_version.as_deref(), Some("4.1"));
+
+    assert_eq!(document.axes.len(), 2);
+    let weight = &document.axes[0];
+    assert_eq!((weight.name.as_str(), weight.tag.as_str()), ("weight", "wght"));
+    assert_eq!((weight.minimum, weight.default, weight.maximum), (Some(100.), 400., Some(900.)));
+    assert_eq!(weight.label_names["fr"], "Graisse");
+    assert_eq!(weight.map[1], AxisMapping { input: 400., output: 80. });
+    assert_eq!(document.get_axis("width").unwrap().default, 100.);
+
+    assert!(document.rules.processing_last);
+    let rules = &document.rules.rules;
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].condition_sets[0].len(), 2);
+    assert_eq!(
+        rules[0].condition_sets[0][1],
+        Condition { name: "width".into(), minimum: None, maximum: Some(90.) }
+    );
+    assert_eq!(rules[0].substitutions, vec![("dollar".into(), "dollar.nostroke".into())]);
+    assert_eq!(rules[1].condition_sets.len(), 1);
+
+    assert_eq!(document.sources.len(), 3);
+    assert_eq!(document.sources[0].style_name.as_deref(), Some("Light"));
+    assert_eq!(
+        document.sources[0].location,
+        vec![Dimension::new("weight", 20.), Dimension::new("width", 100.)]
+    );
+    assert_eq!(document.sources[1].layer.as_deref(), Some("support"));
+    assert_eq!(document.sources[2].location[0].yvalue, Some(180.));
+
+    let instance = &document.instances[0];
+    assert_eq!(instance.postscript_font_name.as_deref(), Some("TestFamily-Regular"));
+    assert_eq!(instance.style_map_style_name.as_deref(), Some("regular"));
+    assert_eq!(instance.location, vec![Dimension::new("weight", 80.)]);
+
+    assert_eq!(document.lib["com.example.key"].as_string(), Some("value"));
+}
+
+#[test]
+fn designspace_roundtrip() {
+    let path = "testdata/designspace/TestFamily.designspace";
+    let document = DesignSpaceDocument::load(path).unwrap();
+    let xml = document.encode_xml().unwrap();
+    let mut reloaded = DesignSpaceDocument::from_xml(&xml).unwrap();
+    reloaded.format_version = document.format_version.clone();
+    assert_eq!(reloaded, document);
+
+    let mut v5 = document.clone();
+    v5.axes.push(Axis {
+        values: Some(vec![0., 1.]),
+        minimum: None,
+        maximum: None,
+        ..Axis::new("italic", "ital", 0., 0., 1.)
+    });
+    v5.instances[0].location[0].uservalue = Some(400.);
+    v5.instances[0].lib.insert("com.example.instance".into(), 1.into());
+    let xml = v5.encode_xml().unwrap();
+    let text = std::str::from_utf8(&xml).unwrap();
+    assert!(text.contains(r#"<designspace format="5.0">"#));
+    assert!(text.contains(r#"<axis tag="ital" name="italic" values="0 1" default="0"/>"#));
+    let mut reloaded = DesignSpaceDocument::from_xml(&xml).unwrap();
+    reloaded.format_version = None;
+    v5.format_version = None;
+    assert_eq!(reloaded, v5);
+}
+
+#[test]
+fn load_fonts() {
+    let path = Path::new("testdata/designspace/TestFamily.designspace");
+    let document = DesignSpaceDocument::load(path).unwrap();
+    let fonts = document.load_fonts(path.parent().unwrap()).unwrap();
+    assert_eq!(fonts.len(), 2);
+    assert!(fonts["../fontinfotest.ufo"].font_info.is_some());
+}
+
+#[test]
+fn axis_mapping() {
+    let path = "testdata/designspace/TestFamily.designspace";
+    let document = DesignSpaceDocument::load(path).unwrap();
+    let weight = &document.axes[0];
+    assert_eq!(weight.map_forward(400.), 80.);
+    assert_eq!(weight.map_forward(250.), 50.);
+    assert_eq!(weight.map_forward(1000.), 300.);
+    assert_eq!(weight.map_backward(50.), 250.);
+    assert_eq!(document.axes[1].map_forward(80.), 80.);
+}
+
+#[test]
+fn designspace_errors() {
+    let error = |xml: &str| match DesignSpaceDocument::from_xml(xml.as_bytes()) {
+        Err(Error::DesignSpace(e)) => e.kind,
+        other => panic!("unexpected result {:?}", other),
+    };
+    assert_eq!(error("<glyph/>"), DesignSpaceErrorKind::WrongFirstElement);
+    assert_eq!(
+        error(r#"<designspace format="3"></designspace>"#),
+        DesignSpaceErrorKind::UnsupportedVersion
+    );
+    assert_eq!(
+        error(r#"<designspace><axes><axis name="weight" default="1"/></axes></designspace>"#),
+        DesignSpaceErrorKind::MissingAttribute
+    );
+    assert_eq!(
+        error(
+            r#"<designspace><sources><source filename="a.ufo"><location><dimension name="weight" xvalue="bold"/></location></source></sources></designspace>"#
+        ),
+        DesignSpaceErrorKind::BadNumber
+    );
+    assert_eq!(error("<designspace><sources>"), DesignSpaceErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn normalize_location() {
+    let path = "testdata/designspace/TestFamily.designspace";
+    let document = DesignSpaceDocument::load(path).unwrap();
+    let location = document.normalize_location(&[Dimension::new("weight", 50.)]);
+    assert_eq!(location["weight"], -0.5);
+    assert_eq!(location["width"], 0.);
+    let user = Dimension { xvalue: None, uservalue: Some(900.), ..Dimension::new("weight", 0.) };
+    let location = document.normalize_location(&[user, Dimension::new("width", 50.)]);
+    assert_eq!(location["weight"], 1.);
+    assert_eq!(location["width"], -1.);
+}
+
+fn master(widths: &[(&str, f32)]) -> Font {
+    let mut font = Font::new();
+    for (name, width) in widths {
+        let mut glyph = crate::Glyph::new_named(*name);
+        glyph.width = *width;
+        font.default_layer_mut().insert_glyph(glyph);
+    }
+    font
+}
+
+#[test]
+fn generate_instance() {
+    let mut light = master(&[("a", 400.), ("b", 400.)]);
+    let bold = master(&[("a", 600.), ("b", 600.)]);
+    let mut medium = crate::Glyph::new_named("a");
+    medium.width = 700.;
+    light.layers.get_or_create("medium").insert_glyph(medium);
+    let mut fonts = BTreeMap::new();
+    fonts.insert("Light.ufo".to_string(), light);
+    fonts.insert("Bold.ufo".to_string(), bold);
+
+    let source = |filename: &str, layer: Option<&str>, weight| Source {
+        filename: filename.into(),
+        layer: layer.map(Into::into),
+        location: vec![Dimension::new("weight", weight)],
+        ..Default::default()
+    };
+    let mut document = DesignSpaceDocument {
+        axes: vec![Axis::new("weight", "wght", 100., 100., 900.)],
+        sources: vec![
+            source("Light.ufo", None, 100.),
+            source("Light.ufo", Some("medium"), 500.),
+            source("Bold.ufo", None, 900.),
+        ],
+        ..Default::default()
+    };
+    let instance = |weight| Instance {
+        family_name: Some("Test".into()),
+        style_name: Some("Bold".into()),
+        style_map_style_name: Some("bold".into()),
+        location: vec![Dimension::new("weight", weight)],
+        ..Default::default()
+    };
+    document.instances = vec![instance(300.), instance(500.)];
+
+    let instances = document.generate_instances(&fonts).unwrap();
+    let layer = instances[0].default_layer();
+    assert_eq!(layer.get_glyph("a").unwrap().width, 550.);
+    assert_eq!(layer.get_glyph("b").unwrap().width, 450.);
+    let layer = instances[1].default_layer();
+    assert_eq!(layer.get_glyph("a").unwrap().width, 700.);
+    assert_eq!(layer.get_glyph("b").unwrap().width, 500.);
+
+    let info = instances[0].font_info.as_ref().unwrap();
+    assert_eq!(info.family_name.as_deref(), Some("Test"));
+    assert_eq!(info.style_name.as_deref(), Some("Bold"));
+    assert_eq!(info.style_map_style_name, Some(crate::fontinfo::StyleMapStyle::Bold));
+
+    // a sparse glyph incompatible with the others is an error
+    let mut incompatible = crate::Glyph::new_named("a");
+    incompatible.anchors.push(crate::Anchor::new(0., 0., None, None, None, None));
+    let light = fonts.get_mut("Light.ufo").unwrap();
+    light.layers.get_mut("medium").unwrap().insert_glyph(incompatible);
+    match document.generate_instance(&fonts, &document.instances[0]) {
+        Err(Error::InterpolateGlyph { glyph, inner: InterpolationError::Incompatible(issues) }) => {
+            assert_eq!(glyph, "a");
+            assert_eq!(issues, [Incompatibility::AnchorCount { master: 1, expected: 0, found: 1 }]);
+        }
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+
+    // as is a sparse master at the location of another master
+    document.sources[1].location = vec![Dimension::new("weight", 900.)];
+    match document.generate_instance(&fonts, &document.instances[0]) {
+        Err(Error::GlyphVariationModel { glyph, inner }) => {
+            assert_eq!(glyph, "a");
+            assert_eq!(inner, VariationModelError::DuplicateLocation);
+        }
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+
+    fonts.remove("Bold.ufo");
+    match document.generate_instance(&fonts, &document.instances[0]) {
+        Err(Error::MissingSource(name)) => assert_eq!(name, "Bold.ufo"),
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+}
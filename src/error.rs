@@ -6,6 +6,8 @@ use std::path::PathBuf;
 use plist::Error as PlistError;
 use quick_xml::Error as XmlError;
 
+use crate::compat::Incompatibility;
+use crate::kerning::KerningIssue;
 use crate::GlyphName;
 
 /// Errors that occur while working with font objects.
@@ -38,12 +40,87 @@ pub enum Error {
     FontInfoUpconversion,
     InvalidGroups(GroupsValidationError),
     GroupsUpconversionFailure(GroupsValidationError),
+    /// The kerning refers to glyphs or groups that don't exist, or uses
+    /// groups wrongly.
+    InvalidKerning(Vec<KerningIssue>),
+    /// A kerning group could not be changed.
+    KerningGroup(KerningGroupError),
     // the string is the key
     ExpectedPlistDictionary(String),
     ExpectedPlistString,
     ExpectedPositiveValue,
+    /// Converting the curves of a glyph in a layer failed.
+    ConvertCurves {
+        glyph: String,
+        inner: CurveConversionError,
+    },
+    /// No font was given for the source with this filename.
+    MissingSource(String),
+    /// The masters of a designspace can't form a variation model.
+    VariationModel(VariationModelError),
+    /// The sources that have a glyph can't form a variation model.
+    GlyphVariationModel {
+        glyph: String,
+        inner: VariationModelError,
+    },
+    /// A glyph of an instance could not be interpolated.
+    InterpolateGlyph {
+        glyph: String,
+        inner: InterpolationError,
+    },
+    /// An error parsing a designspace document.
+    DesignSpace(DesignSpaceError),
+    /// An error writing a designspace document.
+    DesignSpaceWrite(WriteError),
     #[cfg(feature = "kurbo")]
     ConvertContour(ErrorKind),
+    /// A glyph's components reference each other in a cycle. The string is the
+    /// name of the component base where the cycle was detected.
+    #[cfg(feature = "kurbo")]
+    ComponentCycle(String),
+}
+
+/// An error that occurs while converting outlines between cubic and quadratic curves.
+#[derive(Debug)]
+pub enum CurveConversionError {
+    /// A contour is malformed.
+    Outline(ErrorKind),
+    /// The glyphs being converted together differ in their number of contours
+    /// or in the structure of their points.
+    Incompatible,
+    /// No quadratic spline approximates a curve within the maximum error.
+    ApproximationNotFound,
+}
+
+/// An error that occurs while interpolating glyphs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpolationError {
+    /// There was nothing to interpolate.
+    NoMasters,
+    /// The masters are not compatible; every difference found is listed.
+    Incompatible(Vec<Incompatibility>),
+    /// The number of values given is not the number of masters.
+    MasterCount { expected: usize, found: usize },
+}
+
+/// An error that occurs while changing kerning groups.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KerningGroupError {
+    /// There is no group with this name.
+    MissingGroup(String),
+    /// There is already a group with this name.
+    DuplicateGroup(String),
+    /// The name is not that of a kerning group for the side being changed.
+    WrongSide(String),
+}
+
+/// An error that occurs while building a variation model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariationModelError {
+    /// None of the masters is at the default location.
+    MissingBaseMaster,
+    /// Two masters are at the same location.
+    DuplicateLocation,
 }
 
 /// An error representing a failure to validate UFO groups.
@@ -73,6 +150,72 @@ pub struct GlifError {
     pub kind: ErrorKind,
 }
 
+/// An error that occurs while parsing a designspace document.
+#[derive(Debug)]
+pub struct DesignSpaceError {
+    pub path: Option<PathBuf>,
+    pub position: usize,
+    pub kind: DesignSpaceErrorKind,
+}
+
+/// The reason for a designspace parse failure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DesignSpaceErrorKind {
+    WrongFirstElement,
+    UnsupportedVersion,
+    UnexpectedElement,
+    UnexpectedEof,
+    MissingAttribute,
+    BadNumber,
+    BadLib,
+}
+
+/// An error that occurs while parsing a feature file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureParseError {
+    /// The line of the error, starting at one.
+    pub line: usize,
+    /// The column of the error in characters, starting at one.
+    pub column: usize,
+    /// What went wrong.
+    pub kind: FeatureErrorKind,
+}
+
+/// The reason for a feature file parse failure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum FeatureErrorKind {
+    /// A character that cannot start a token.
+    UnexpectedCharacter,
+    /// A string with no closing quote.
+    UnterminatedString,
+    /// An `include` with no closing paren on the same line.
+    UnterminatedInclude,
+    /// A glyph class with no closing bracket.
+    UnterminatedGlyphClass,
+    /// A block with no closing brace.
+    UnterminatedBlock,
+    /// A statement, or the label after a block, with no semicolon.
+    UnterminatedStatement,
+    /// A brace, bracket or semicolon out of place.
+    UnexpectedToken,
+}
+
+/// An error that occurs while resolving the includes of a feature file.
+#[derive(Debug)]
+pub enum IncludeError {
+    /// An included file could not be read.
+    Io { path: PathBuf, inner: IoError },
+    /// A file could not be parsed.
+    Parse { path: PathBuf, inner: FeatureParseError },
+    /// A file includes itself, directly or through other files.
+    Cycle(PathBuf),
+    /// The files read are larger than the limit, in bytes.
+    TooLarge(usize),
+    /// Includes are nested deeper than the limit.
+    TooDeep(usize),
+}
+
 /// An error when attempting to write a .glif file
 #[derive(Debug)]
 pub struct GlifWriteError {
@@ -135,6 +278,8 @@ pub enum ErrorKind {
     TooManyOffCurves,
     PenPathNotStarted,
     TrailingOffCurves,
+    /// A path with more than one subpath was converted to a single contour.
+    MultipleSubpaths,
     DuplicateIdentifier,
     UnexpectedDrawing,
     UnfinishedDrawing,
@@ -185,6 +330,14 @@ impl std::fmt::Display for Error {
             Error::GroupsUpconversionFailure(ge) => {
                 write!(f, "Upconverting UFO v1 or v2 kerning data to v3 failed: {}", ge)
             }
+            Error::InvalidKerning(issues) => {
+                write!(f, "Invalid kerning")?;
+                for (idx, issue) in issues.iter().enumerate() {
+                    write!(f, "{} {}", if idx == 0 { ":" } else { ";" }, issue)?;
+                }
+                Ok(())
+            }
+            Error::KerningGroup(e) => e.fmt(f),
             Error::ExpectedPlistDictionary(key) => {
                 write!(f, "Expected a Plist dictionary at '{}'", key)
             }
@@ -192,8 +345,25 @@ impl std::fmt::Display for Error {
             Error::ExpectedPositiveValue => {
                 write!(f, "PositiveIntegerOrFloat expects a positive value.")
             }
+            Error::ConvertCurves { glyph, inner } => {
+                write!(f, "Failed to convert curves of glyph '{}': {}", glyph, inner)
+            }
+            Error::MissingSource(name) => write!(f, "No font given for source '{}'", name),
+            Error::VariationModel(e) => write!(f, "Invalid variation model: {}", e),
+            Error::GlyphVariationModel { glyph, inner } => {
+                write!(f, "Invalid variation model for glyph '{}': {}", glyph, inner)
+            }
+            Error::InterpolateGlyph { glyph, inner } => {
+                write!(f, "Failed to interpolate glyph '{}': {}", glyph, inner)
+            }
+            Error::DesignSpace(e) => e.fmt(f),
+            Error::DesignSpaceWrite(e) => write!(f, "Failed to write designspace: {}", e),
             #[cfg(feature = "kurbo")]
             Error::ConvertContour(cause) => write!(f, "Failed to convert contour: '{}'", cause),
+            #[cfg(feature = "kurbo")]
+            Error::ComponentCycle(name) => {
+                write!(f, "Component '{}' references itself, directly or indirectly.", name)
+            }
         }
     }
 }
@@ -207,6 +377,73 @@ impl std::fmt::Display for GroupsValidationError {
     }
 }
 
+impl std::fmt::Display for CurveConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CurveConversionError::Outline(kind) => write!(f, "Malformed contour: {}", kind),
+            CurveConversionError::Incompatible => {
+                write!(f, "Outlines are not compatible for conversion")
+            }
+            CurveConversionError::ApproximationNotFound => {
+                write!(f, "No approximation within the maximum error was found")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CurveConversionError {}
+
+impl std::fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            InterpolationError::NoMasters => write!(f, "No masters to interpolate"),
+            InterpolationError::Incompatible(issues) => {
+                write!(f, "Masters are not compatible")?;
+                for (idx, issue) in issues.iter().enumerate() {
+                    write!(f, "{} {}", if idx == 0 { ":" } else { ";" }, issue)?;
+                }
+                Ok(())
+            }
+            InterpolationError::MasterCount { expected, found } => {
+                write!(f, "Expected a value for each of {} masters, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InterpolationError {}
+
+impl std::fmt::Display for VariationModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VariationModelError::MissingBaseMaster => {
+                write!(f, "No master is at the default location")
+            }
+            VariationModelError::DuplicateLocation => {
+                write!(f, "More than one master is at the same location")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VariationModelError {}
+
+impl std::fmt::Display for KerningGroupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KerningGroupError::MissingGroup(name) => write!(f, "No group named '{}'", name),
+            KerningGroupError::DuplicateGroup(name) => {
+                write!(f, "A group named '{}' already exists", name)
+            }
+            KerningGroupError::WrongSide(name) => {
+                write!(f, "'{}' is not a kerning group for this side", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KerningGroupError {}
+
 impl std::fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -247,6 +484,7 @@ impl std::fmt::Display for ErrorKind {
             ErrorKind::TrailingOffCurves => {
                 write!(f, "Open contours must not have trailing off-curves")
             }
+            ErrorKind::MultipleSubpaths => write!(f, "A contour must have a single subpath"),
             ErrorKind::DuplicateIdentifier => write!(f, "Duplicate identifier"),
             ErrorKind::UnexpectedDrawing => write!(f, "Unexpected drawing without an outline"),
             ErrorKind::UnfinishedDrawing => write!(f, "Unfinished drawing, you must call end_path"),
@@ -272,6 +510,80 @@ impl std::fmt::Display for WriteError {
     }
 }
 
+impl std::fmt::Display for DesignSpaceErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DesignSpaceErrorKind::WrongFirstElement => write!(f, "Wrong first element"),
+            DesignSpaceErrorKind::UnsupportedVersion => write!(f, "Unsupported format version"),
+            DesignSpaceErrorKind::UnexpectedElement => write!(f, "Unexpected element"),
+            DesignSpaceErrorKind::UnexpectedEof => write!(f, "Unexpected EOF"),
+            DesignSpaceErrorKind::MissingAttribute => write!(f, "Missing required attribute"),
+            DesignSpaceErrorKind::BadNumber => write!(f, "Bad number"),
+            DesignSpaceErrorKind::BadLib => write!(f, "Bad lib"),
+        }
+    }
+}
+
+impl std::fmt::Display for FeatureErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FeatureErrorKind::UnexpectedCharacter => write!(f, "Unexpected character"),
+            FeatureErrorKind::UnterminatedString => write!(f, "Unterminated string"),
+            FeatureErrorKind::UnterminatedInclude => write!(f, "Unterminated include"),
+            FeatureErrorKind::UnterminatedGlyphClass => write!(f, "Missing ']' after glyph class"),
+            FeatureErrorKind::UnterminatedBlock => write!(f, "Missing '}}' at end of block"),
+            FeatureErrorKind::UnterminatedStatement => write!(f, "Missing ';' after statement"),
+            FeatureErrorKind::UnexpectedToken => write!(f, "Unexpected token"),
+        }
+    }
+}
+
+impl std::fmt::Display for FeatureParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} at line {}, column {}", self.kind, self.line, self.column)
+    }
+}
+
+impl std::error::Error for FeatureParseError {}
+
+impl std::fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IncludeError::Io { path, inner } => {
+                write!(f, "Failed to read '{}': {}", path.display(), inner)
+            }
+            IncludeError::Parse { path, inner } => {
+                write!(f, "Failed to parse '{}': {}", path.display(), inner)
+            }
+            IncludeError::Cycle(path) => write!(f, "'{}' includes itself", path.display()),
+            IncludeError::TooLarge(limit) => {
+                write!(f, "The included features are larger than {} bytes", limit)
+            }
+            IncludeError::TooDeep(limit) => {
+                write!(f, "Includes are nested more than {} deep", limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IncludeError::Io { inner, .. } => Some(inner),
+            IncludeError::Parse { inner, .. } => Some(inner),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DesignSpaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Designspace error in {:?} index {}: '{}'", self.path, self.position, self.kind)
+    }
+}
+
+impl std::error::Error for DesignSpaceError {}
+
 impl std::fmt::Display for GlifWriteError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "Failed to write glyph '{}': {}", self.name, self.inner)
@@ -301,6 +613,13 @@ impl std::error::Error for Error {
             Error::IoError(inner) => Some(inner),
             Error::PlistError(inner) => Some(inner),
             Error::GlifWrite(inner) => Some(&inner.inner),
+            Error::ConvertCurves { inner, .. } => Some(inner),
+            Error::DesignSpace(inner) => Some(inner),
+            Error::DesignSpaceWrite(inner) => Some(inner),
+            Error::VariationModel(inner) => Some(inner),
+            Error::GlyphVariationModel { inner, .. } => Some(inner),
+            Error::InterpolateGlyph { inner, .. } => Some(inner),
+            Error::KerningGroup(inner) => Some(inner),
             _ => None,
         }
     }
@@ -341,6 +660,13 @@ impl From<(ErrorKind, usize)> for GlifErrorInternal {
     }
 }
 
+#[doc(hidden)]
+impl From<ErrorKind> for CurveConversionError {
+    fn from(src: ErrorKind) -> CurveConversionError {
+        CurveConversionError::Outline(src)
+    }
+}
+
 #[doc(hidden)]
 impl From<XmlError> for Error {
     fn from(src: XmlError) -> Error {
@@ -0,0 +1,359 @@
+//! Resolving `include` statements in feature files.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::parse::parse_features;
+use super::syntax::{Element, Node, NodeKind, Position, TokenKind};
+use crate::error::IncludeError;
+use crate::ufo::FEATURES_FILE;
+use crate::Font;
+
+/// Options for resolving the includes of a feature file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct IncludeOptions {
+    /// The most text to read, in bytes, counting a file each time it is
+    /// included.
+    pub max_size: usize,
+    /// The deepest nesting of includes, a file included by the file being
+    /// resolved being at depth one.
+    pub max_depth: usize,
+}
+
+impl IncludeOptions {
+    /// Set the most text to read, in bytes.
+    pub fn max_size(&mut self, size: usize) -> &mut Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Set the deepest nesting of includes.
+    pub fn max_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_depth = depth;
+        self
+    }
+}
+
+impl Default for IncludeOptions {
+    fn default() -> Self {
+        IncludeOptions { max_size: 16 * 1024 * 1024, max_depth: 32 }
+    }
+}
+
+/// Features with their includes expanded, and a map from the merged text
+/// back to the files it came from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedFeatures {
+    text: String,
+    /// The path and text of each file.
+    files: Vec<(PathBuf, String)>,
+    /// Where each run of the merged text comes from: its start in the
+    /// merged text, the file, and its start in that file. Sorted by start.
+    segments: Vec<(usize, usize, usize)>,
+}
+
+/// A location in one of the files making up resolved features.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SourceLocation<'a> {
+    /// The file.
+    pub path: &'a Path,
+    /// Where in the file.
+    pub position: Position,
+}
+
+/// Expand the `include` statements in the text of a feature file.
+///
+/// `path` is the file the text comes from, used in the source map and to
+/// detect files including themselves, and need not exist. The files
+/// included are found relative to `include_dir`, as are the files they
+/// include in turn, unless their paths are absolute.
+///
+/// Each include statement, with its semicolon if it has one, is replaced
+/// by the contents of the file it names. Including a file from itself,
+/// directly or through other files, is an error, as is going over the
+/// limits of `options`. The size of each file is checked against them
+/// before it is read.
+pub fn resolve_includes(
+    text: &str,
+    path: &Path,
+    include_dir: &Path,
+    options: &IncludeOptions,
+) -> Result<ResolvedFeatures, IncludeError> {
+    if text.len() > options.max_size {
+        return Err(IncludeError::TooLarge(options.max_size));
+    }
+    let mut resolver = Resolver {
+        include_dir,
+        options,
+        remaining: options.max_size - text.len(),
+        resolved: ResolvedFeatures { text: String::new(), files: Vec::new(), segments: Vec::new() },
+        file_indices: HashMap::new(),
+        stack: Vec::new(),
+    };
+    resolver.expand(path.to_owned(), text.to_owned())?;
+    Ok(resolver.resolved)
+}
+
+impl Font {
+    /// Expand the `include` statements in the font's features, if it has
+    /// any.
+    ///
+    /// `ufo_path` is where the font is stored. Included files are found
+    /// relative to the directory containing the UFO, as feature compilers
+    /// do. See [`resolve_includes`] for details.
+    pub fn resolve_features(
+        &self,
+        ufo_path: impl AsRef<Path>,
+        options: &IncludeOptions,
+    ) -> Result<Option<ResolvedFeatures>, IncludeError> {
+        let ufo_path = ufo_path.as_ref();
+        let features = match &self.features {
+            Some(features) => features,
+            None => return Ok(None),
+        };
+        let include_dir = ufo_path.parent().unwrap_or_else(|| Path::new(""));
+        resolve_includes(features, &ufo_path.join(FEATURES_FILE), include_dir, options).map(Some)
+    }
+}
+
+impl ResolvedFeatures {
+    /// The merged text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The paths of the files making up the merged text, starting with the
+    /// file that was resolved.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Returns where a byte offset into the merged text comes from.
+    ///
+    /// Returns `None` if the offset is past the end of the text.
+    pub fn source(&self, offset: usize) -> Option<SourceLocation<'_>> {
+        if offset >= self.text.len() {
+            return None;
+        }
+        let idx = self.segments.partition_point(|(start, _, _)| *start <= offset) - 1;
+        let (start, file, file_start) = self.segments[idx];
+        let (path, text) = &self.files[file];
+        let mut position = Position { offset: 0, line: 1, column: 1 };
+        position.advance(&text[..file_start + offset - start]);
+        Some(SourceLocation { path, position })
+    }
+}
+
+struct Resolver<'a> {
+    include_dir: &'a Path,
+    options: &'a IncludeOptions,
+    /// How much more text may be read, in bytes.
+    remaining: usize,
+    resolved: ResolvedFeatures,
+    /// The index of each file in `resolved.files`, by canonical path.
+    file_indices: HashMap<PathBuf, usize>,
+    /// The canonical paths of the files being expanded.
+    stack: Vec<PathBuf>,
+}
+
+impl Resolver<'_> {
+    fn expand(&mut self, path: PathBuf, text: String) -> Result<(), IncludeError> {
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if self.stack.contains(&canonical) {
+            return Err(IncludeError::Cycle(path));
+        }
+        let tree = match parse_features(&text) {
+            Ok(tree) => tree,
+            Err(inner) => return Err(IncludeError::Parse { path, inner }),
+        };
+        let mut includes = Vec::new();
+        find_includes(&tree, &mut 0, &mut includes);
+
+        let files = &mut self.resolved.files;
+        let file = *self.file_indices.entry(canonical.clone()).or_insert_with(|| {
+            files.push((path, text));
+            files.len() - 1
+        });
+        self.stack.push(canonical);
+        let mut cursor = 0;
+        for (start, end, include) in includes {
+            self.push_text(file, cursor, start);
+            if self.stack.len() > self.options.max_depth {
+                return Err(IncludeError::TooDeep(self.options.max_depth));
+            }
+            let include_path = self.include_dir.join(include.trim());
+            let text = match self.read(&include_path) {
+                Ok(Some(text)) => text,
+                Ok(None) => return Err(IncludeError::TooLarge(self.options.max_size)),
+                Err(inner) => return Err(IncludeError::Io { path: include_path, inner }),
+            };
+            self.expand(include_path, text)?;
+            cursor = end;
+        }
+        let len = self.resolved.files[file].1.len();
+        self.push_text(file, cursor, len);
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Read a file, charging its size against the remaining budget.
+    ///
+    /// Returns `None` if the file is larger than the remaining budget.
+    fn read(&mut self, path: &Path) -> Result<Option<String>, std::io::Error> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() > self.remaining as u64 {
+            return Ok(None);
+        }
+        // the file may have grown since
+        let mut text = String::new();
+        file.take(self.remaining as u64 + 1).read_to_string(&mut text)?;
+        if text.len() > self.remaining {
+            return Ok(None);
+        }
+        self.remaining -= text.len();
+        Ok(Some(text))
+    }
+
+    /// Append a range of the text of a file to the merged text.
+    fn push_text(&mut self, file: usize, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        let resolved = &mut self.resolved;
+        resolved.segments.push((resolved.text.len(), file, start));
+        resolved.text.push_str(&resolved.files[file].1[start..end]);
+    }
+}
+
+/// Collect the byte range and path of each include statement.
+fn find_includes(node: &Node, offset: &mut usize, includes: &mut Vec<(usize, usize, String)>) {
+    for child in &node.children {
+        match child {
+            Element::Token(token) => *offset += token.text.len(),
+            Element::Node(node) if node.kind == NodeKind::Include => {
+                let start = *offset;
+                let path = node.tokens().find(|token| token.kind == TokenKind::Path);
+                let path = path.map(|token| token.text.clone()).unwrap_or_default();
+                *offset += node.tokens().map(|token| token.text.len()).sum::<usize>();
+                includes.push((start, *offset, path));
+            }
+            Element::Node(node) => find_includes(node, offset, includes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        let dir = tempdir::TempDir::new("Includes").unwrap();
+        let shared = dir.path().join("shared");
+        fs::create_dir(&shared).unwrap();
+        fs::write(shared.join("classes.fea"), "@lc = [a b];\ninclude(shared/more.fea);\n").unwrap();
+        fs::write(shared.join("more.fea"), "@uc = [A B];\n").unwrap();
+
+        let mut font = Font::new();
+        font.features = Some(
+            "languagesystem DFLT dflt;\ninclude( shared/classes.fea )\nfeature liga {\n    \
+             include(shared/more.fea);\n} liga;\n"
+                .into(),
+        );
+        let ufo_path = dir.path().join("Font.ufo");
+        let resolved =
+            font.resolve_features(&ufo_path, &IncludeOptions::default()).unwrap().unwrap();
+        assert_eq!(
+            resolved.text(),
+            "languagesystem DFLT dflt;\n@lc = [a b];\n@uc = [A B];\n\n\nfeature liga {\n    \
+             @uc = [A B];\n\n} liga;\n"
+        );
+        let files: Vec<_> = resolved.files().collect();
+        assert_eq!(
+            files,
+            [ufo_path.join("features.fea"), shared.join("classes.fea"), shared.join("more.fea")]
+        );
+
+        let source = |needle: &str, nth: usize| {
+            let offset = resolved.text().match_indices(needle).nth(nth).unwrap().0;
+            let location = resolved.source(offset).unwrap();
+            (
+                location.path.file_name().unwrap().to_owned(),
+                location.position.line,
+                location.position.column,
+            )
+        };
+        assert_eq!(source("languagesystem", 0), ("features.fea".into(), 1, 1));
+        assert_eq!(source("[a b]", 0), ("classes.fea".into(), 1, 7));
+        assert_eq!(source("@uc", 0), ("more.fea".into(), 1, 1));
+        assert_eq!(source("\n\nfeature", 0), ("classes.fea".into(), 2, 26));
+        assert_eq!(source("feature", 0), ("features.fea".into(), 3, 1));
+        assert_eq!(source("@uc", 1), ("more.fea".into(), 1, 1));
+        assert_eq!(source("} liga", 0), ("features.fea".into(), 5, 1));
+        assert_eq!(resolved.source(resolved.text().len()), None);
+
+        font.features = None;
+        assert_eq!(font.resolve_features(&ufo_path, &IncludeOptions::default()).unwrap(), None);
+    }
+
+    #[test]
+    fn resolve_errors() {
+        let dir = tempdir::TempDir::new("Includes").unwrap();
+        let path = dir.path().join("features.fea");
+        fs::write(dir.path().join("a.fea"), "include(b.fea);\n").unwrap();
+        fs::write(dir.path().join("b.fea"), "include(a.fea);\n").unwrap();
+        fs::write(dir.path().join("bad.fea"), "sub a by b\n").unwrap();
+        fs::write(dir.path().join("c.fea"), "@c = [a b c d e f g h i j k];\n").unwrap();
+        let options = IncludeOptions::default();
+        let resolve = |text: &str, options: &IncludeOptions| {
+            resolve_includes(text, &path, dir.path(), options).unwrap_err()
+        };
+
+        match resolve("include(a.fea);", &options) {
+            IncludeError::Cycle(path) => assert_eq!(path, dir.path().join("a.fea")),
+            err => panic!("unexpected error {:?}", err),
+        }
+        match resolve("include(missing.fea);", &options) {
+            IncludeError::Io { path, .. } => assert_eq!(path, dir.path().join("missing.fea")),
+            err => panic!("unexpected error {:?}", err),
+        }
+        match resolve("\ninclude(bad.fea);", &options) {
+            IncludeError::Parse { path, inner } => {
+                assert_eq!(path, dir.path().join("bad.fea"));
+                assert_eq!((inner.line, inner.column), (1, 11));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        match resolve("include(bad.fea;", &options) {
+            IncludeError::Parse { path: err_path, .. } => assert_eq!(err_path, path),
+            err => panic!("unexpected error {:?}", err),
+        }
+        let mut small = IncludeOptions::default();
+        small.max_size(20);
+        assert!(matches!(resolve("include(c.fea);", &small), IncludeError::TooLarge(20)));
+        assert!(matches!(resolve(&"#".repeat(21), &small), IncludeError::TooLarge(20)));
+
+        // each inclusion of a file counts against the limit
+        fs::write(dir.path().join("d.fea"), "@d = [d];\n").unwrap();
+        fs::write(dir.path().join("e.fea"), "include(d.fea);".repeat(100)).unwrap();
+        fs::write(dir.path().join("f.fea"), "include(e.fea);".repeat(100)).unwrap();
+        small.max_size(10_000);
+        assert!(matches!(resolve("include(f.fea);", &small), IncludeError::TooLarge(10_000)));
+
+        let mut shallow = IncludeOptions::default();
+        shallow.max_depth(1);
+        assert!(matches!(resolve("include(e.fea);", &shallow), IncludeError::TooDeep(1)));
+        assert!(resolve_includes("include(d.fea);", &path, dir.path(), &shallow).is_ok());
+
+        // including the same file twice is fine
+        fs::write(dir.path().join("a.fea"), "@a = [a];\n").unwrap();
+        let resolved =
+            resolve_includes("include(a.fea);include(a.fea);", &path, dir.path(), &options)
+                .unwrap();
+        assert_eq!(resolved.text(), "@a = [a];\n@a = [a];\n");
+        assert_eq!(resolved.files().count(), 2);
+    }
+}
@@ -0,0 +1,428 @@
+//! Generating the `kern` feature.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+use super::scripts::{script, Script};
+use crate::kerning::{KERN1_PREFIX, KERN2_PREFIX};
+use crate::{Font, Layer};
+
+/// Options for generating the `kern` feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct KernFeatureOptions {
+    /// Put the pairs of each script in a lookup of its own, registered for
+    /// that script. Pairs of right-to-left scripts get right-to-left values.
+    pub split_scripts: bool,
+    /// Skip over marks between the glyphs of a pair.
+    pub ignore_marks: bool,
+}
+
+impl KernFeatureOptions {
+    /// Request that the pairs be split into lookups by script.
+    pub fn split_scripts(&mut self, b: bool) -> &mut Self {
+        self.split_scripts = b;
+        self
+    }
+
+    /// Request that marks between the glyphs of a pair be skipped.
+    pub fn ignore_marks(&mut self, b: bool) -> &mut Self {
+        self.ignore_marks = b;
+        self
+    }
+}
+
+impl Default for KernFeatureOptions {
+    fn default() -> Self {
+        KernFeatureOptions { split_scripts: true, ignore_marks: true }
+    }
+}
+
+/// A side of a pair, once resolved against the font.
+#[derive(Clone, Copy)]
+enum Side<'a> {
+    Glyph(&'a str),
+    Class(&'a str),
+}
+
+/// A pair, with the sort order of its kind, which follows the precedence
+/// of pairs in a UFO: glyph pairs, then glyph-class pairs, then class-glyph
+/// pairs, then class pairs.
+#[derive(Clone, Copy)]
+struct Pair<'a> {
+    kind: u8,
+    first: Side<'a>,
+    second: Side<'a>,
+    value: i32,
+}
+
+/// Generate the `kern` feature from the font's kerning.
+///
+/// The result holds a class definition for each kerning group that is used,
+/// named `@kern1.` or `@kern2.` followed by the group name, the lookups,
+/// and the feature itself, and can be appended to the font's features. It
+/// is empty if there is nothing to kern.
+///
+/// Pairs between glyphs come first, then pairs of a glyph and a class, then
+/// pairs of a class and a glyph, and then pairs between classes, so that
+/// the first rule for two glyphs is the pair that applies to them in the
+/// UFO. Pairs between a glyph and a class are written as `enum pos` rules
+/// so that they act as exceptions. Pairs and group members referring to
+/// glyphs that are not in the default layer are left out, and values are
+/// rounded to integers.
+///
+/// When splitting by script, the script of a glyph is that of its first
+/// codepoint that belongs to a script. A pair goes in the lookup of each
+/// script its glyphs have, and pairs whose glyphs have no script go in the
+/// lookups of all scripts, as well as in a lookup for the `DFLT` script.
+/// Each lookup thus holds all the pairs that apply in its script, so that
+/// exceptions and the pairs they override always end up together. The
+/// scripts used should be declared with `languagesystem` statements in the
+/// rest of the font's features.
+pub fn kern_feature(font: &Font, options: &KernFeatureOptions) -> String {
+    let kerning = match &font.kerning {
+        Some(kerning) => kerning,
+        None => return String::new(),
+    };
+    let layer = font.default_layer();
+    let classes = Classes::new(font, layer);
+
+    let mut lookups: BTreeMap<Option<Script>, Vec<Pair>> = BTreeMap::new();
+    let mut common = Vec::new();
+    for (first, row) in kerning {
+        for (second, value) in row {
+            let (first, second) = match (classes.side(first, layer), classes.side(second, layer)) {
+                (Some(first), Some(second)) => (first, second),
+                _ => continue,
+            };
+            let kind = match (&first, &second) {
+                (Side::Glyph(_), Side::Glyph(_)) => 0,
+                (Side::Glyph(_), Side::Class(_)) => 1,
+                (Side::Class(_), Side::Glyph(_)) => 2,
+                (Side::Class(_), Side::Class(_)) => 3,
+            };
+            let scripts: BTreeSet<Script> = if options.split_scripts {
+                let glyphs = classes.glyphs(&first).into_iter().chain(classes.glyphs(&second));
+                glyphs.filter_map(|glyph| glyph_script(layer, glyph)).collect()
+            } else {
+                BTreeSet::new()
+            };
+            let pair = Pair { kind, first, second, value: value.round() as i32 };
+            if scripts.is_empty() {
+                common.push(pair);
+            }
+            for script in scripts {
+                lookups.entry(Some(script)).or_default().push(pair);
+            }
+        }
+    }
+    for pairs in lookups.values_mut() {
+        pairs.extend(common.iter().copied());
+    }
+    // Without any script lookups, the common lookup is registered for all
+    // scripts, and otherwise only for `DFLT`.
+    let only_common = lookups.is_empty();
+    if !common.is_empty() {
+        lookups.insert(None, common);
+    }
+    if lookups.is_empty() {
+        return String::new();
+    }
+
+    let mut fea = String::new();
+    for (name, glyphs) in classes.used(&lookups) {
+        writeln!(fea, "@{} = [{}];", name, glyphs.join(" ")).unwrap();
+    }
+    if !fea.is_empty() {
+        fea.push('\n');
+    }
+    for (script, pairs) in lookups.iter_mut() {
+        pairs.sort_by_key(|pair| pair.kind);
+        writeln!(fea, "lookup {} {{", lookup_name(*script)).unwrap();
+        if options.ignore_marks {
+            fea.push_str("    lookupflag IgnoreMarks;\n");
+        }
+        for pair in pairs.iter() {
+            let enumerate = if pair.kind == 1 || pair.kind == 2 { "enum " } else { "" };
+            let value = match script {
+                Some(script) if script.rtl => {
+                    format!("<{} 0 {} 0>", pair.value, pair.value)
+                }
+                _ => pair.value.to_string(),
+            };
+            writeln!(
+                fea,
+                "    {}pos {} {} {};",
+                enumerate,
+                classes.fea_name(&pair.first),
+                classes.fea_name(&pair.second),
+                value
+            )
+            .unwrap();
+        }
+        writeln!(fea, "}} {};\n", lookup_name(*script)).unwrap();
+    }
+    fea.push_str("feature kern {\n");
+    for script in lookups.keys() {
+        if !only_common {
+            let tag = script.map(|script| script.tag.trim_end()).unwrap_or("DFLT");
+            writeln!(fea, "    script {};\n    language dflt;", tag).unwrap();
+        }
+        writeln!(fea, "    lookup {};", lookup_name(*script)).unwrap();
+    }
+    fea.push_str("} kern;\n");
+    fea
+}
+
+impl Font {
+    /// Generate the `kern` feature from the font's kerning.
+    ///
+    /// See [`kern_feature`] for details.
+    pub fn kern_feature(&self, options: &KernFeatureOptions) -> String {
+        kern_feature(self, options)
+    }
+}
+
+/// The kerning groups of a font, as FEA classes.
+struct Classes<'a> {
+    /// The class name and glyphs in the font of each group.
+    groups: HashMap<&'a str, (String, Vec<&'a str>)>,
+}
+
+impl<'a> Classes<'a> {
+    fn new(font: &'a Font, layer: &Layer) -> Self {
+        let mut names = BTreeSet::new();
+        let mut groups = HashMap::new();
+        for (group, glyphs) in font.groups.iter().flatten() {
+            let (prefix, name) = if let Some(name) = group.strip_prefix(KERN1_PREFIX) {
+                ("kern1.", name)
+            } else if let Some(name) = group.strip_prefix(KERN2_PREFIX) {
+                ("kern2.", name)
+            } else {
+                continue;
+            };
+            let name: String = name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '_' { c } else { '_' })
+                .collect();
+            let mut class = format!("{}{}", prefix, name);
+            let mut suffix = 1;
+            while !names.insert(class.clone()) {
+                class = format!("{}{}.{}", prefix, name, suffix);
+                suffix += 1;
+            }
+            let glyphs: Vec<&str> = glyphs
+                .iter()
+                .filter(|glyph| layer.contains_glyph(glyph))
+                .map(|glyph| &**glyph)
+                .collect();
+            groups.insert(group.as_str(), (class, glyphs));
+        }
+        Classes { groups }
+    }
+
+    /// Resolve a side of a pair, if it refers to a glyph in the font or a
+    /// group with glyphs in the font.
+    fn side<'b>(&self, name: &'b str, layer: &Layer) -> Option<Side<'b>> {
+        if name.starts_with(KERN1_PREFIX) || name.starts_with(KERN2_PREFIX) {
+            match self.groups.get(name) {
+                Some((_, glyphs)) if !glyphs.is_empty() => Some(Side::Class(name)),
+                _ => None,
+            }
+        } else if layer.contains_glyph(name) {
+            Some(Side::Glyph(name))
+        } else {
+            None
+        }
+    }
+
+    fn glyphs<'b>(&'b self, side: &Side<'b>) -> Vec<&'b str> {
+        match side {
+            Side::Glyph(name) => vec![name],
+            Side::Class(name) => self.groups[name].1.clone(),
+        }
+    }
+
+    fn fea_name(&self, side: &Side) -> String {
+        match side {
+            Side::Glyph(name) => name.to_string(),
+            Side::Class(name) => format!("@{}", self.groups[name].0),
+        }
+    }
+
+    /// The class names and glyphs of the groups used by the pairs, sorted by
+    /// class name.
+    fn used<'b>(
+        &'b self,
+        lookups: &BTreeMap<Option<Script>, Vec<Pair>>,
+    ) -> BTreeMap<&'b str, &'b [&'a str]> {
+        let mut used = BTreeMap::new();
+        for pair in lookups.values().flatten() {
+            for side in [&pair.first, &pair.second].iter() {
+                if let Side::Class(name) = side {
+                    let (class, glyphs) = &self.groups[*name];
+                    used.insert(class.as_str(), glyphs.as_slice());
+                }
+            }
+        }
+        used
+    }
+}
+
+fn glyph_script(layer: &Layer, name: &str) -> Option<Script> {
+    layer.get_glyph(name)?.codepoints.iter().find_map(|c| script(*c))
+}
+
+fn lookup_name(script: Option<Script>) -> String {
+    match script {
+        Some(script) => format!("kern_{}", script.tag.trim_end()),
+        None => "kern_DFLT".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Glyph;
+    use maplit::btreemap;
+
+    fn glyph(name: &str, codepoint: Option<char>) -> Glyph {
+        let mut glyph = Glyph::new_named(name);
+        glyph.codepoints.extend(codepoint);
+        glyph
+    }
+
+    #[test]
+    fn kern() {
+        let mut font = Font::new();
+        let glyphs = [
+            ("T", Some('T')),
+            ("o", Some('o')),
+            ("oacute", Some('ó')),
+            ("period", Some('.')),
+            ("alef-ar", Some('\u{627}')),
+            ("beh-ar", Some('\u{628}')),
+            ("T.alt", None),
+            ("v", Some('v')),
+        ];
+        for (name, codepoint) in glyphs.iter() {
+            font.default_layer_mut().insert_glyph(glyph(name, *codepoint));
+        }
+        font.groups = Some(btreemap! {
+            "public.kern1.T".into() => vec!["T".into(), "T.alt".into(), "Tcommaaccent".into()],
+            "public.kern2.o round".into() => vec!["o".into(), "oacute".into()],
+            "public.kern2.empty".into() => vec!["missing".into()],
+        });
+        font.kerning = Some(btreemap! {
+            "public.kern1.T".into() => btreemap! {
+                "public.kern2.o round".into() => -80.,
+                "oacute".into() => -40.4,
+                "public.kern2.empty".into() => -10.,
+                "period".into() => -60.,
+            },
+            "T".into() => btreemap! { "o".into() => -70. },
+            "alef-ar".into() => btreemap! { "beh-ar".into() => 20. },
+            "missing".into() => btreemap! { "o".into() => -5. },
+            "period".into() => btreemap! { "period".into() => -10. },
+            "v".into() => btreemap! { "public.kern2.o round".into() => -15. },
+        });
+
+        let expected = "\
+@kern1.T = [T T.alt];
+@kern2.o_round = [o oacute];
+
+lookup kern_DFLT {
+    lookupflag IgnoreMarks;
+    pos period period -10;
+} kern_DFLT;
+
+lookup kern_arab {
+    lookupflag IgnoreMarks;
+    pos alef-ar beh-ar <20 0 20 0>;
+    pos period period <-10 0 -10 0>;
+} kern_arab;
+
+lookup kern_latn {
+    lookupflag IgnoreMarks;
+    pos T o -70;
+    pos period period -10;
+    enum pos v @kern2.o_round -15;
+    enum pos @kern1.T oacute -40;
+    enum pos @kern1.T period -60;
+    pos @kern1.T @kern2.o_round -80;
+} kern_latn;
+
+feature kern {
+    script DFLT;
+    language dflt;
+    lookup kern_DFLT;
+    script arab;
+    language dflt;
+    lookup kern_arab;
+    script latn;
+    language dflt;
+    lookup kern_latn;
+} kern;
+";
+        assert_eq!(font.kern_feature(&KernFeatureOptions::default()), expected);
+
+        let mut options = KernFeatureOptions::default();
+        options.split_scripts(false).ignore_marks(false);
+        let fea = font.kern_feature(&options);
+        assert!(fea.contains("lookup kern_DFLT {\n    pos T o -70;\n    pos alef-ar beh-ar 20;\n"));
+        assert!(fea.contains(
+            "    pos period period -10;\n    enum pos v @kern2.o_round -15;\n    \
+             enum pos @kern1.T oacute -40;\n"
+        ));
+        assert!(fea.ends_with("feature kern {\n    lookup kern_DFLT;\n} kern;\n"));
+
+        font.kerning = None;
+        assert_eq!(font.kern_feature(&options), "");
+    }
+
+    #[test]
+    fn mixed_scripts() {
+        let mut font = Font::new();
+        let glyphs = [("v", 'v'), ("o", 'o'), ("oacute", 'ó'), ("omicron", 'ο')];
+        for (name, codepoint) in glyphs.iter() {
+            font.default_layer_mut().insert_glyph(glyph(name, Some(*codepoint)));
+        }
+        font.groups = Some(btreemap! {
+            "public.kern1.v".into() => vec!["v".into()],
+            "public.kern2.o".into() => vec!["o".into(), "oacute".into(), "omicron".into()],
+        });
+        font.kerning = Some(btreemap! {
+            "v".into() => btreemap! { "public.kern2.o".into() => -10., "o".into() => -70. },
+            "public.kern1.v".into() => btreemap! { "oacute".into() => -40. },
+        });
+
+        // The exceptions for Latin glyphs are in the same lookup as the
+        // pair they override, and ahead of it.
+        let expected = "\
+@kern1.v = [v];
+@kern2.o = [o oacute omicron];
+
+lookup kern_grek {
+    lookupflag IgnoreMarks;
+    enum pos v @kern2.o -10;
+} kern_grek;
+
+lookup kern_latn {
+    lookupflag IgnoreMarks;
+    pos v o -70;
+    enum pos v @kern2.o -10;
+    enum pos @kern1.v oacute -40;
+} kern_latn;
+
+feature kern {
+    script grek;
+    language dflt;
+    lookup kern_grek;
+    script latn;
+    language dflt;
+    lookup kern_latn;
+} kern;
+";
+        assert_eq!(font.kern_feature(&KernFeatureOptions::default()), expected);
+    }
+}
@@ -0,0 +1,396 @@
+//! Generating the `mark`, `mkmk` and `curs` features from anchors.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::{Anchor, Font, Glyph};
+
+/// The lib key holding the OpenType category of each glyph.
+static CATEGORIES_KEY: &str = "public.openTypeCategories";
+
+/// The category of a glyph, for attaching marks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Category {
+    Base,
+    Mark,
+    Ligature,
+}
+
+/// Glyphs, with the anchor they attach by.
+type Attached<'a> = Vec<(&'a str, &'a Anchor)>;
+
+/// The anchors of a ligature for a mark class, by component.
+type ComponentAnchors<'a> = Vec<Option<&'a Anchor>>;
+
+/// The anchors of the glyphs, sorted out by what they attach.
+#[derive(Default)]
+struct Attachments<'a> {
+    /// The marks of each mark class, with the anchor they attach by.
+    mark_classes: BTreeMap<&'a str, Attached<'a>>,
+    /// The bases of each mark class.
+    bases: BTreeMap<&'a str, Attached<'a>>,
+    /// The marks that other marks of each mark class attach to.
+    mark_bases: BTreeMap<&'a str, Attached<'a>>,
+    /// The ligatures of each mark class, with the anchor of each component.
+    ligatures: BTreeMap<&'a str, Vec<(&'a str, ComponentAnchors<'a>)>>,
+    /// The glyphs with `entry` or `exit` anchors.
+    cursive: Vec<(&'a str, Option<&'a Anchor>, Option<&'a Anchor>)>,
+}
+
+/// Generate the `mark`, `mkmk` and `curs` features from the anchors of the
+/// glyphs in the default layer.
+///
+/// The result holds a mark class for each anchor name used by marks, the
+/// lookups, and the features, and can be appended to the font's features.
+/// It is empty if there is nothing to attach.
+///
+/// Anchors are matched by name, following the usual conventions:
+///
+/// - a mark with an anchor named `_top` attaches to the `top` anchor of
+///   bases and of other marks (in the `mkmk` feature);
+/// - a ligature has an anchor for each of its components, numbered from
+///   one, such as `top_1` and `top_2`;
+/// - anchors named `entry` and `exit` connect glyphs in the `curs` feature.
+///
+/// Whether a glyph is a base, a mark or a ligature is taken from the
+/// `public.openTypeCategories` key of the font's lib, where glyphs not
+/// listed, or listed as components or unassigned, are left out. Without the
+/// key, glyphs with anchors whose names start with an underscore are marks,
+/// those with numbered anchors matching a mark are ligatures, and all others
+/// are bases. Anchor coordinates are rounded to integers.
+pub fn mark_feature(font: &Font) -> String {
+    let attachments = Attachments::new(font);
+    let mut fea = String::new();
+
+    for (class, marks) in &attachments.mark_classes {
+        for (glyph, anchor) in marks {
+            writeln!(fea, "markClass {} {} @MC_{};", glyph, anchor_fea(Some(anchor)), class)
+                .unwrap();
+        }
+    }
+    if !fea.is_empty() {
+        fea.push('\n');
+    }
+
+    let mut mark_lookups = Vec::new();
+    for (class, bases) in &attachments.bases {
+        let name = format!("mark2base_{}", class);
+        writeln!(fea, "lookup {} {{", name).unwrap();
+        for (glyph, anchor) in bases {
+            let anchor = anchor_fea(Some(anchor));
+            writeln!(fea, "    pos base {} {} mark @MC_{};", glyph, anchor, class).unwrap();
+        }
+        writeln!(fea, "}} {};\n", name).unwrap();
+        mark_lookups.push(name);
+    }
+    for (class, ligatures) in &attachments.ligatures {
+        let name = format!("mark2liga_{}", class);
+        writeln!(fea, "lookup {} {{", name).unwrap();
+        for (glyph, components) in ligatures {
+            let components: Vec<String> = components
+                .iter()
+                .map(|anchor| match anchor {
+                    Some(_) => format!("{} mark @MC_{}", anchor_fea(*anchor), class),
+                    None => anchor_fea(None),
+                })
+                .collect();
+            writeln!(fea, "    pos ligature {} {};", glyph, components.join(" ligComponent "))
+                .unwrap();
+        }
+        writeln!(fea, "}} {};\n", name).unwrap();
+        mark_lookups.push(name);
+    }
+
+    let mut mkmk_lookups = Vec::new();
+    for (class, bases) in &attachments.mark_bases {
+        let name = format!("mark2mark_{}", class);
+        let filter: BTreeSet<&str> =
+            attachments.mark_classes[class].iter().chain(bases).map(|(glyph, _)| *glyph).collect();
+        let filter: Vec<&str> = filter.into_iter().collect();
+        writeln!(fea, "@MFS_{} = [{}];", name, filter.join(" ")).unwrap();
+        writeln!(fea, "lookup {} {{", name).unwrap();
+        writeln!(fea, "    lookupflag UseMarkFilteringSet @MFS_{};", name).unwrap();
+        for (glyph, anchor) in bases {
+            let anchor = anchor_fea(Some(anchor));
+            writeln!(fea, "    pos mark {} {} mark @MC_{};", glyph, anchor, class).unwrap();
+        }
+        writeln!(fea, "}} {};\n", name).unwrap();
+        mkmk_lookups.push(name);
+    }
+
+    let mut curs_lookups = Vec::new();
+    if !attachments.cursive.is_empty() {
+        fea.push_str("lookup curs {\n    lookupflag IgnoreMarks;\n");
+        for (glyph, entry, exit) in &attachments.cursive {
+            writeln!(
+                fea,
+                "    pos cursive {} {} {};",
+                glyph,
+                anchor_fea(*entry),
+                anchor_fea(*exit)
+            )
+            .unwrap();
+        }
+        fea.push_str("} curs;\n\n");
+        curs_lookups.push("curs".to_string());
+    }
+
+    for (feature, lookups) in
+        [("mark", mark_lookups), ("mkmk", mkmk_lookups), ("curs", curs_lookups)].iter()
+    {
+        if lookups.is_empty() {
+            continue;
+        }
+        writeln!(fea, "feature {} {{", feature).unwrap();
+        for lookup in lookups {
+            writeln!(fea, "    lookup {};", lookup).unwrap();
+        }
+        writeln!(fea, "}} {};\n", feature).unwrap();
+    }
+    // Only a single newline at the end.
+    while fea.ends_with("\n\n") {
+        fea.pop();
+    }
+    fea
+}
+
+impl Font {
+    /// Generate the `mark`, `mkmk` and `curs` features from the anchors of
+    /// the glyphs in the default layer.
+    ///
+    /// See [`mark_feature`] for details.
+    pub fn mark_feature(&self) -> String {
+        mark_feature(self)
+    }
+}
+
+impl<'a> Attachments<'a> {
+    fn new(font: &'a Font) -> Self {
+        let categories = font.lib.get(CATEGORIES_KEY).and_then(|value| value.as_dictionary());
+        let layer = font.default_layer();
+        let mut result = Attachments::default();
+
+        // The mark classes have to be known before the bases and
+        // ligatures can be found.
+        let mut glyphs = Vec::new();
+        for glyph in layer.iter() {
+            let category = match categories {
+                Some(categories) => {
+                    match categories.get(&glyph.name).and_then(|value| value.as_string()) {
+                        Some("base") => Category::Base,
+                        Some("mark") => Category::Mark,
+                        Some("ligature") => Category::Ligature,
+                        _ => continue,
+                    }
+                }
+                None if glyph.anchors.iter().any(|a| anchor_name(a).starts_with('_')) => {
+                    Category::Mark
+                }
+                None => Category::Base,
+            };
+            if category == Category::Mark {
+                for anchor in &glyph.anchors {
+                    if let Some(class) = anchor_name(anchor).strip_prefix('_') {
+                        let marks = result.mark_classes.entry(class).or_default();
+                        marks.push((&*glyph.name, anchor));
+                    }
+                }
+            }
+            glyphs.push((&**glyph, category));
+        }
+
+        for (glyph, category) in glyphs {
+            let ligature = ligature_anchors(glyph, &result.mark_classes);
+            let category = match category {
+                Category::Base if categories.is_none() && !ligature.is_empty() => {
+                    Category::Ligature
+                }
+                category => category,
+            };
+            match category {
+                Category::Ligature => {
+                    for (class, components) in ligature {
+                        let ligatures = result.ligatures.entry(class).or_default();
+                        ligatures.push((&*glyph.name, components));
+                    }
+                }
+                Category::Base | Category::Mark => {
+                    let bases = match category {
+                        Category::Base => &mut result.bases,
+                        _ => &mut result.mark_bases,
+                    };
+                    for anchor in &glyph.anchors {
+                        let name = anchor_name(anchor);
+                        if let Some((class, _)) = result.mark_classes.get_key_value(name) {
+                            bases.entry(class).or_default().push((&*glyph.name, anchor));
+                        }
+                    }
+                }
+            }
+
+            let find = |name: &str| glyph.anchors.iter().find(|a| anchor_name(a) == name);
+            let (entry, exit) = (find("entry"), find("exit"));
+            if entry.is_some() || exit.is_some() {
+                result.cursive.push((&*glyph.name, entry, exit));
+            }
+        }
+        result
+    }
+}
+
+/// The anchors of a ligature for each mark class, by component.
+///
+/// Every class gets an entry for each component of the ligature, the
+/// components without an anchor for the class being `None`.
+fn ligature_anchors<'a>(
+    glyph: &'a Glyph,
+    mark_classes: &BTreeMap<&'a str, Attached<'a>>,
+) -> BTreeMap<&'a str, ComponentAnchors<'a>> {
+    let mut result: BTreeMap<&str, ComponentAnchors> = BTreeMap::new();
+    for anchor in &glyph.anchors {
+        let name = anchor_name(anchor);
+        let (class, index) = match name.rfind('_') {
+            Some(idx) => (&name[..idx], name[idx + 1..].parse::<usize>()),
+            None => continue,
+        };
+        let (class, index) = match (mark_classes.get_key_value(class), index) {
+            (Some((class, _)), Ok(index)) if index > 0 => (*class, index),
+            _ => continue,
+        };
+        let components = result.entry(class).or_default();
+        if components.len() < index {
+            components.resize(index, None);
+        }
+        components[index - 1] = Some(anchor);
+    }
+    let count = result.values().map(Vec::len).max().unwrap_or(0);
+    for components in result.values_mut() {
+        components.resize(count, None);
+    }
+    result
+}
+
+fn anchor_name(anchor: &Anchor) -> &str {
+    anchor.name.as_deref().unwrap_or("")
+}
+
+fn anchor_fea(anchor: Option<&Anchor>) -> String {
+    match anchor {
+        Some(anchor) => format!("<anchor {} {}>", anchor.x.round() as i32, anchor.y.round() as i32),
+        None => "<anchor NULL>".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(name: &str, anchors: &[(&str, f32, f32)]) -> Glyph {
+        let mut glyph = Glyph::new_named(name);
+        for (name, x, y) in anchors {
+            glyph.anchors.push(Anchor::new(*x, *y, Some(name.to_string()), None, None, None));
+        }
+        glyph
+    }
+
+    fn font() -> Font {
+        let mut font = Font::new();
+        let glyphs = [
+            glyph("a", &[("top", 250., 500.), ("bottom", 250., 0.)]),
+            glyph("f_i", &[("top_1", 150., 700.), ("top_3", 400.4, 700.)]),
+            glyph("acutecomb", &[("_top", 100., 500.), ("top", 100., 700.)]),
+            glyph("dotbelowcomb", &[("_bottom", 100., 0.)]),
+            glyph("alef-ar", &[("entry", 0., 100.), ("exit", 300., 100.)]),
+            glyph("space", &[]),
+        ];
+        for glyph in glyphs.iter() {
+            font.default_layer_mut().insert_glyph(glyph.clone());
+        }
+        font
+    }
+
+    #[test]
+    fn mark_features() {
+        let expected = "\
+markClass dotbelowcomb <anchor 100 0> @MC_bottom;
+markClass acutecomb <anchor 100 500> @MC_top;
+
+lookup mark2base_bottom {
+    pos base a <anchor 250 0> mark @MC_bottom;
+} mark2base_bottom;
+
+lookup mark2base_top {
+    pos base a <anchor 250 500> mark @MC_top;
+} mark2base_top;
+
+lookup mark2liga_top {
+    pos ligature f_i <anchor 150 700> mark @MC_top ligComponent <anchor NULL> ligComponent <anchor 400 700> mark @MC_top;
+} mark2liga_top;
+
+@MFS_mark2mark_top = [acutecomb];
+lookup mark2mark_top {
+    lookupflag UseMarkFilteringSet @MFS_mark2mark_top;
+    pos mark acutecomb <anchor 100 700> mark @MC_top;
+} mark2mark_top;
+
+lookup curs {
+    lookupflag IgnoreMarks;
+    pos cursive alef-ar <anchor 0 100> <anchor 300 100>;
+} curs;
+
+feature mark {
+    lookup mark2base_bottom;
+    lookup mark2base_top;
+    lookup mark2liga_top;
+} mark;
+
+feature mkmk {
+    lookup mark2mark_top;
+} mkmk;
+
+feature curs {
+    lookup curs;
+} curs;
+";
+        assert_eq!(font().mark_feature(), expected);
+        assert_eq!(Font::new().mark_feature(), "");
+    }
+
+    #[test]
+    fn categories() {
+        let mut font = font();
+        let categories: plist::Dictionary = vec![
+            ("a".to_string(), plist::Value::from("base")),
+            ("acutecomb".to_string(), plist::Value::from("mark")),
+            ("f_i".to_string(), plist::Value::from("base")),
+        ]
+        .into_iter()
+        .collect();
+        font.lib.insert(CATEGORIES_KEY.into(), categories.into());
+        let fea = font.mark_feature();
+        assert!(fea.starts_with("markClass acutecomb <anchor 100 500> @MC_top;\n\n"));
+        assert!(!fea.contains("mark2liga"));
+        assert!(!fea.contains("@MC_bottom"));
+        assert!(!fea.contains("curs"));
+    }
+
+    #[test]
+    fn ligature_components() {
+        let mut font = font();
+        let ffi = glyph(
+            "f_f_i",
+            &[
+                ("top_1", 100., 700.),
+                ("top_2", 300., 700.),
+                ("top_3", 500., 700.),
+                ("bottom_1", 100., 0.),
+            ],
+        );
+        font.default_layer_mut().insert_glyph(ffi);
+        let fea = font.mark_feature();
+        assert!(fea.contains(
+            "    pos ligature f_f_i <anchor 100 0> mark @MC_bottom ligComponent <anchor NULL> \
+             ligComponent <anchor NULL>;\n"
+        ));
+    }
+}
@@ -0,0 +1,34 @@
+//! Generating OpenType feature code in the [Adobe FEA syntax][fea].
+//!
+//! The generators here turn the data in a [`Font`] into feature code that
+//! can be appended to the font's [`features`]:
+//!
+//! - [`kern_feature`] writes the `kern` feature from the kerning and kerning
+//!   groups.
+//! - [`mark_feature`] writes the `mark`, `mkmk` and `curs` features from the
+//!   anchors of the glyphs.
+//!
+//! The features can also be parsed with [`parse_features`] into a lossless
+//! syntax tree, for inspecting or rewriting them, and the files they
+//! include merged in with [`resolve_includes`]. The glyphs they refer to
+//! can be found with [`glyph_references`] and renamed with
+//! [`rename_glyph_references`].
+//!
+//! [fea]: https://adobe-type-tools.github.io/afdko/OpenTypeFeatureFileSpecification.html
+//! [`Font`]: crate::Font
+//! [`features`]: crate::Font::features
+
+mod include;
+mod kern;
+mod mark;
+mod parse;
+mod references;
+mod scripts;
+mod syntax;
+
+pub use include::{resolve_includes, IncludeOptions, ResolvedFeatures, SourceLocation};
+pub use kern::{kern_feature, KernFeatureOptions};
+pub use mark::mark_feature;
+pub use parse::parse_features;
+pub use references::{glyph_references, rename_glyph_references, GlyphReference, ReferenceKind};
+pub use syntax::{Element, Node, NodeKind, Position, Token, TokenKind};
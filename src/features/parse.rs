@@ -0,0 +1,570 @@
+//! Parsing feature files into a lossless syntax tree.
+
+use super::syntax::{Element, Node, NodeKind, Token, TokenKind};
+use crate::error::{FeatureErrorKind, FeatureParseError};
+use crate::Font;
+
+/// Parse the text of a feature file into a syntax tree.
+///
+/// The parser knows the overall structure of the [Adobe FEA syntax][fea]:
+/// statements, blocks and glyph classes, and what kind of statement or
+/// block each one is. It does not check the contents of statements, so it
+/// accepts any text the full syntax does, and some that it does not. The
+/// contents of `anonymous` blocks are kept as a single [`TokenKind::Raw`]
+/// token, and the file name of an `include` statement as a
+/// [`TokenKind::Path`] token.
+///
+/// The text can be recovered from the tree by formatting it with
+/// [`Display`](std::fmt::Display); it is identical to the input.
+///
+/// # Examples
+///
+/// ```
+/// use norad::features::{parse_features, NodeKind};
+///
+/// let fea = "languagesystem DFLT dflt; # the default\nfeature liga { sub f i by f_i; } liga;\n";
+/// let tree = parse_features(fea).unwrap();
+/// let kinds: Vec<_> = tree.child_nodes().map(|node| node.kind).collect();
+/// assert_eq!(kinds, [NodeKind::LanguageSystem, NodeKind::FeatureBlock]);
+/// assert_eq!(tree.to_string(), fea);
+/// ```
+///
+/// [fea]: https://adobe-type-tools.github.io/afdko/OpenTypeFeatureFileSpecification.html
+pub fn parse_features(text: &str) -> Result<Node, FeatureParseError> {
+    let tokens = Lexer::new(text).lex()?;
+    Parser { text, tokens, pos: 0, end: 0 }.parse_file()
+}
+
+impl Font {
+    /// Parse the font's features into a syntax tree, if it has any.
+    ///
+    /// See [`parse_features`] for details.
+    pub fn parse_features(&self) -> Result<Option<Node>, FeatureParseError> {
+        self.features.as_deref().map(parse_features).transpose()
+    }
+}
+
+/// Build an error at a byte offset into `text`.
+fn error(text: &str, offset: usize, kind: FeatureErrorKind) -> FeatureParseError {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+    FeatureParseError { line, column, kind }
+}
+
+fn is_name_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || c == b'.'
+}
+
+fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"_.-+*:^|~".contains(&c)
+}
+
+/// Splits a feature file into tokens, each with its byte offset.
+struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    tokens: Vec<(usize, Token)>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Lexer { text, pos: 0, tokens: Vec::new() }
+    }
+
+    fn lex(mut self) -> Result<Vec<(usize, Token)>, FeatureParseError> {
+        let bytes = self.text.as_bytes();
+        while self.pos < bytes.len() {
+            let start = self.pos;
+            let rest = &self.text[start..];
+            let c = bytes[start];
+            let next = bytes.get(start + 1).copied();
+            let (kind, len) = match c {
+                b'#' => (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len())),
+                b'"' => match rest[1..].find('"') {
+                    Some(end) => (TokenKind::String, end + 2),
+                    None => return Err(self.error(start, FeatureErrorKind::UnterminatedString)),
+                },
+                b'@' if next.map(is_name_char).unwrap_or(false) => {
+                    (TokenKind::ClassName, 1 + self.run(start + 1, is_name_char))
+                }
+                b'\\' if next.map(|c| c.is_ascii_digit()).unwrap_or(false) => {
+                    (TokenKind::Cid, 1 + self.run(start + 1, |c| c.is_ascii_digit()))
+                }
+                b'\\' if next.map(is_name_start).unwrap_or(false) => {
+                    (TokenKind::Name, 1 + self.run(start + 1, is_name_char))
+                }
+                b'-' if next.map(|c| c.is_ascii_digit()).unwrap_or(false)
+                    && !matches!(self.tokens.last(), Some((_, token)) if token.kind == TokenKind::Cid) =>
+                {
+                    (TokenKind::Number, 1 + self.number(start + 1))
+                }
+                b'0'..=b'9' => (TokenKind::Number, self.number(start)),
+                c if is_name_start(c) => (TokenKind::Name, self.run(start, is_name_char)),
+                b';' => (TokenKind::Semicolon, 1),
+                b',' => (TokenKind::Comma, 1),
+                b'{' => (TokenKind::LBrace, 1),
+                b'}' => (TokenKind::RBrace, 1),
+                b'[' => (TokenKind::LBracket, 1),
+                b']' => (TokenKind::RBracket, 1),
+                b'(' => (TokenKind::LParen, 1),
+                b')' => (TokenKind::RParen, 1),
+                b'<' => (TokenKind::LAngle, 1),
+                b'>' => (TokenKind::RAngle, 1),
+                b'=' => (TokenKind::Equals, 1),
+                b'\'' => (TokenKind::Quote, 1),
+                b'-' => (TokenKind::Hyphen, 1),
+                _ => {
+                    let len = rest
+                        .char_indices()
+                        .find(|(_, c)| !(c.is_whitespace() || *c == '\u{feff}'))
+                        .map(|(idx, _)| idx)
+                        .unwrap_or(rest.len());
+                    if len == 0 {
+                        return Err(self.error(start, FeatureErrorKind::UnexpectedCharacter));
+                    }
+                    (TokenKind::Whitespace, len)
+                }
+            };
+            self.push(kind, start + len);
+
+            if kind == TokenKind::LParen && self.previous_names(2) == ["include"] {
+                self.include_path()?;
+            } else if kind == TokenKind::LBrace {
+                if let [keyword, tag] = self.previous_names(3).as_slice() {
+                    if *keyword == "anon" || *keyword == "anonymous" {
+                        let tag = tag.to_string();
+                        self.anonymous_block(start, &tag)?;
+                    }
+                }
+            }
+        }
+        Ok(self.tokens)
+    }
+
+    fn push(&mut self, kind: TokenKind, end: usize) {
+        let text = self.text[self.pos..end].to_string();
+        self.tokens.push((self.pos, Token { kind, text }));
+        self.pos = end;
+    }
+
+    fn error(&self, offset: usize, kind: FeatureErrorKind) -> FeatureParseError {
+        error(self.text, offset, kind)
+    }
+
+    /// The length of the run of bytes matching `f` starting at `start`.
+    fn run(&self, start: usize, f: impl Fn(u8) -> bool) -> usize {
+        self.text.as_bytes()[start..].iter().take_while(|c| f(**c)).count()
+    }
+
+    /// The length of the number starting at `start`, without its sign.
+    fn number(&self, start: usize) -> usize {
+        let bytes = self.text.as_bytes();
+        if bytes[start] == b'0' && matches!(bytes.get(start + 1), Some(b'x') | Some(b'X')) {
+            return 2 + self.run(start + 2, |c| c.is_ascii_hexdigit());
+        }
+        let mut len = self.run(start, |c| c.is_ascii_digit());
+        if bytes.get(start + len) == Some(&b'.')
+            && bytes.get(start + len + 1).map(|c| c.is_ascii_digit()).unwrap_or(false)
+        {
+            len += 1 + self.run(start + len + 1, |c| c.is_ascii_digit());
+        }
+        len
+    }
+
+    /// The text of the names just before the token just pushed, up to
+    /// `n - 1` of them, stopping at any other significant token.
+    fn previous_names(&self, n: usize) -> Vec<&str> {
+        let mut tokens =
+            self.tokens.iter().rev().map(|(_, token)| token).filter(|t| !t.is_trivia());
+        tokens.next();
+        let names: Vec<&str> = tokens
+            .take(n - 1)
+            .take_while(|token| token.kind == TokenKind::Name)
+            .map(|token| token.text.as_str())
+            .collect();
+        names.into_iter().rev().collect()
+    }
+
+    /// Lex the file name of an include statement, up to the closing paren.
+    fn include_path(&mut self) -> Result<(), FeatureParseError> {
+        let rest = &self.text[self.pos..];
+        match rest.find([')', '\n']) {
+            Some(len) if rest.as_bytes()[len] == b')' => {
+                if len > 0 {
+                    self.push(TokenKind::Path, self.pos + len);
+                }
+                Ok(())
+            }
+            _ => Err(self.error(self.pos, FeatureErrorKind::UnterminatedInclude)),
+        }
+    }
+
+    /// Lex the contents of an anonymous block, up to the line holding the
+    /// closing brace and tag.
+    fn anonymous_block(&mut self, brace: usize, tag: &str) -> Result<(), FeatureParseError> {
+        let mut line_start = self.pos;
+        loop {
+            let line = &self.text[line_start..];
+            let line = &line[..line.find('\n').unwrap_or(line.len())];
+            let trimmed = line.trim_start();
+            let is_end = trimmed
+                .strip_prefix('}')
+                .map(|rest| rest.trim_start())
+                .and_then(|rest| rest.strip_prefix(tag))
+                .map(|rest| rest.trim_start().starts_with(';'))
+                .unwrap_or(false);
+            if is_end {
+                let end = line_start + line.len() - trimmed.len();
+                if end > self.pos {
+                    self.push(TokenKind::Raw, end);
+                }
+                return Ok(());
+            }
+            line_start += line.len() + 1;
+            if line_start > self.text.len() {
+                return Err(self.error(brace, FeatureErrorKind::UnterminatedBlock));
+            }
+        }
+    }
+}
+
+/// Builds the tree from the tokens.
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// The end of the last significant token, where a missing semicolon
+    /// is reported.
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn parse_file(mut self) -> Result<Node, FeatureParseError> {
+        let children = self.parse_items(false)?;
+        Ok(Node { kind: NodeKind::File, children })
+    }
+
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn bump(&mut self) -> Element {
+        let (offset, token) = std::mem::replace(
+            &mut self.tokens[self.pos],
+            (0, Token { kind: TokenKind::Whitespace, text: String::new() }),
+        );
+        if !token.is_trivia() {
+            self.end = offset + token.text.len();
+        }
+        self.pos += 1;
+        Element::Token(token)
+    }
+
+    fn error(&self, offset: usize, kind: FeatureErrorKind) -> FeatureParseError {
+        error(self.text, offset, kind)
+    }
+
+    /// Parse statements and blocks up to the end of the text, or the closing
+    /// brace of the enclosing block.
+    fn parse_items(&mut self, in_block: bool) -> Result<Vec<Element>, FeatureParseError> {
+        let mut children = Vec::new();
+        while let Some((offset, token)) = self.peek() {
+            match token.kind {
+                TokenKind::RBrace if in_block => break,
+                TokenKind::RBrace | TokenKind::RBracket | TokenKind::Semicolon => {
+                    return Err(self.error(*offset, FeatureErrorKind::UnexpectedToken));
+                }
+                TokenKind::Whitespace | TokenKind::Comment | TokenKind::Raw => {
+                    children.push(self.bump())
+                }
+                _ => children.push(Element::Node(self.parse_item()?)),
+            }
+        }
+        Ok(children)
+    }
+
+    /// Parse a statement or a block.
+    fn parse_item(&mut self) -> Result<Node, FeatureParseError> {
+        let mut children = Vec::new();
+        loop {
+            let (offset, token) = match self.peek() {
+                Some((offset, token)) => (*offset, token),
+                None => return Err(self.error(self.end, FeatureErrorKind::UnterminatedStatement)),
+            };
+            match token.kind {
+                TokenKind::Semicolon => {
+                    children.push(self.bump());
+                    return Ok(Node { kind: statement_kind(&children), children });
+                }
+                TokenKind::LBrace => return self.parse_block(children),
+                TokenKind::LBracket => children.push(Element::Node(self.parse_class()?)),
+                TokenKind::RBrace => {
+                    return Err(self.error(self.end, FeatureErrorKind::UnterminatedStatement));
+                }
+                TokenKind::RBracket => {
+                    return Err(self.error(offset, FeatureErrorKind::UnexpectedToken));
+                }
+                TokenKind::RParen if statement_kind(&children) == NodeKind::Include => {
+                    children.push(self.bump());
+                    // the semicolon after an include is optional
+                    let next = self.tokens[self.pos..].iter().find(|(_, token)| !token.is_trivia());
+                    if !matches!(next, Some((_, token)) if token.kind == TokenKind::Semicolon) {
+                        return Ok(Node { kind: NodeKind::Include, children });
+                    }
+                }
+                _ => children.push(self.bump()),
+            }
+        }
+    }
+
+    /// Parse a block, from its opening brace to the semicolon after its
+    /// label.
+    fn parse_block(&mut self, mut children: Vec<Element>) -> Result<Node, FeatureParseError> {
+        let kind = block_kind(&children);
+        let brace = self.peek().unwrap().0;
+        children.push(self.bump());
+        children.extend(self.parse_items(true)?);
+        match self.peek() {
+            Some((_, token)) if token.kind == TokenKind::RBrace => children.push(self.bump()),
+            _ => return Err(self.error(brace, FeatureErrorKind::UnterminatedBlock)),
+        }
+        loop {
+            match self.peek() {
+                Some((_, token)) if token.kind == TokenKind::Semicolon => {
+                    children.push(self.bump());
+                    return Ok(Node { kind, children });
+                }
+                Some((offset, token))
+                    if matches!(
+                        token.kind,
+                        TokenKind::LBrace
+                            | TokenKind::RBrace
+                            | TokenKind::LBracket
+                            | TokenKind::RBracket
+                    ) =>
+                {
+                    return Err(self.error(*offset, FeatureErrorKind::UnexpectedToken));
+                }
+                Some(_) => children.push(self.bump()),
+                None => return Err(self.error(self.end, FeatureErrorKind::UnterminatedStatement)),
+            }
+        }
+    }
+
+    /// Parse a glyph class in brackets.
+    fn parse_class(&mut self) -> Result<Node, FeatureParseError> {
+        let bracket = self.peek().unwrap().0;
+        let mut children = vec![self.bump()];
+        loop {
+            match self.peek().map(|(_, token)| token.kind) {
+                Some(TokenKind::RBracket) => {
+                    children.push(self.bump());
+                    return Ok(Node { kind: NodeKind::GlyphClass, children });
+                }
+                Some(TokenKind::LBracket)
+                | Some(TokenKind::LBrace)
+                | Some(TokenKind::RBrace)
+                | Some(TokenKind::Semicolon)
+                | None => {
+                    return Err(self.error(bracket, FeatureErrorKind::UnterminatedGlyphClass));
+                }
+                Some(_) => children.push(self.bump()),
+            }
+        }
+    }
+}
+
+/// The first two significant tokens of a statement.
+fn head(children: &[Element]) -> (Option<&Token>, Option<&Token>) {
+    let mut tokens = children.iter().filter_map(|child| match child {
+        Element::Token(token) if !token.is_trivia() => Some(token),
+        _ => None,
+    });
+    (tokens.next(), tokens.next())
+}
+
+fn statement_kind(children: &[Element]) -> NodeKind {
+    let (first, second) = head(children);
+    let (first, second) = match (first, second) {
+        (Some(first), second) => (first, second.map(|token| token.text.as_str())),
+        (None, _) => return NodeKind::Statement,
+    };
+    if first.kind == TokenKind::ClassName {
+        return match second {
+            Some("=") => NodeKind::GlyphClassDefinition,
+            _ => NodeKind::Statement,
+        };
+    }
+    match (first.text.as_str(), second) {
+        ("languagesystem", _) => NodeKind::LanguageSystem,
+        ("script", _) => NodeKind::Script,
+        ("language", _) => NodeKind::Language,
+        ("lookupflag", _) => NodeKind::LookupFlag,
+        ("lookup", _) => NodeKind::LookupReference,
+        ("include", _) => NodeKind::Include,
+        ("markClass", _) => NodeKind::MarkClass,
+        ("pos", _) | ("position", _) | ("enum", _) | ("enumerate", _) => NodeKind::Position,
+        ("ignore", Some("pos")) | ("ignore", Some("position")) => NodeKind::Position,
+        ("sub", _) | ("substitute", _) | ("rsub", _) | ("reversesub", _) => NodeKind::Substitution,
+        ("ignore", Some("sub")) | ("ignore", Some("substitute")) => NodeKind::Substitution,
+        _ => NodeKind::Statement,
+    }
+}
+
+fn block_kind(children: &[Element]) -> NodeKind {
+    match head(children).0.map(|token| token.text.as_str()) {
+        Some("feature") => NodeKind::FeatureBlock,
+        Some("lookup") => NodeKind::LookupBlock,
+        Some("table") => NodeKind::TableBlock,
+        Some("anon") | Some("anonymous") => NodeKind::AnonymousBlock,
+        _ => NodeKind::Block,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEA: &str = r#"# Prefix: Languagesystems
+languagesystem DFLT dflt;
+languagesystem latn dflt ;
+
+@lc = [a-z \ae \100-\110 a.sc] ; # lowercase
+markClass [acutecomb gravecomb] <anchor 250 -10.5> @TOP;
+include( ../shared/classes.fea )
+
+table GDEF {
+    GlyphClassDef @lc, , [acutecomb], ;
+} GDEF;
+
+lookup liga_f useExtension {
+    lookupflag IgnoreMarks;
+    sub f i by f_i;
+    ignore sub f' i;
+    rsub [a b]' c by [A B];
+} liga_f;
+
+anon sbit {
+    /* anything { goes } here; */
+} sbit;
+
+feature kern {
+	script latn;
+	language TRK exclude_dflt;
+	pos T [o a] -80;
+	enum pos @lc <0 0 -10 0>;
+	featureNames { name "Kerning"; };
+	lookup liga_f;
+} kern;
+"#;
+
+    #[test]
+    fn round_trip() {
+        let tree = parse_features(FEA).unwrap();
+        assert_eq!(tree.to_string(), FEA);
+
+        let kinds: Vec<_> = tree.child_nodes().map(|node| node.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                NodeKind::LanguageSystem,
+                NodeKind::LanguageSystem,
+                NodeKind::GlyphClassDefinition,
+                NodeKind::MarkClass,
+                NodeKind::Include,
+                NodeKind::TableBlock,
+                NodeKind::LookupBlock,
+                NodeKind::AnonymousBlock,
+                NodeKind::FeatureBlock,
+            ]
+        );
+        let nodes: Vec<&Node> = tree.child_nodes().collect();
+        assert_eq!(nodes[6].label(), Some("liga_f"));
+        let lookup: Vec<_> = nodes[6].child_nodes().map(|node| node.kind).collect();
+        assert_eq!(
+            lookup,
+            [
+                NodeKind::LookupFlag,
+                NodeKind::Substitution,
+                NodeKind::Substitution,
+                NodeKind::Substitution
+            ]
+        );
+        let feature: Vec<_> = nodes[8].child_nodes().map(|node| node.kind).collect();
+        assert_eq!(
+            feature,
+            [
+                NodeKind::Script,
+                NodeKind::Language,
+                NodeKind::Position,
+                NodeKind::Position,
+                NodeKind::Block,
+                NodeKind::LookupReference
+            ]
+        );
+
+        let path = tree.tokens().find(|token| token.kind == TokenKind::Path).unwrap();
+        assert_eq!(path.text, " ../shared/classes.fea ");
+        let raw = tree.tokens().find(|token| token.kind == TokenKind::Raw).unwrap();
+        assert_eq!(raw.text, "\n    /* anything { goes } here; */\n");
+        let class: Vec<_> = nodes[2]
+            .descendants()
+            .find(|node| node.kind == NodeKind::GlyphClass)
+            .unwrap()
+            .significant_tokens()
+            .map(|token| (token.kind, token.text.as_str()))
+            .collect();
+        assert_eq!(
+            class,
+            [
+                (TokenKind::LBracket, "["),
+                (TokenKind::Name, "a-z"),
+                (TokenKind::Name, "\\ae"),
+                (TokenKind::Cid, "\\100"),
+                (TokenKind::Hyphen, "-"),
+                (TokenKind::Cid, "\\110"),
+                (TokenKind::Name, "a.sc"),
+                (TokenKind::RBracket, "]"),
+            ]
+        );
+        let (position, _) =
+            tree.tokens_with_position().find(|(_, token)| token.text == "-10.5").unwrap();
+        assert_eq!((position.line, position.column), (6, 45));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let fail = |fea: &str| {
+            let err = parse_features(fea).unwrap_err();
+            (err.line, err.column, err.kind)
+        };
+        assert_eq!(
+            fail("feature liga {\n  sub f i by f_i;\n"),
+            (1, 14, FeatureErrorKind::UnterminatedBlock)
+        );
+        assert_eq!(
+            fail("languagesystem DFLT dflt\n"),
+            (1, 25, FeatureErrorKind::UnterminatedStatement)
+        );
+        assert_eq!(
+            fail("feature liga {\n  sub f i by f_i\n} liga;"),
+            (2, 17, FeatureErrorKind::UnterminatedStatement)
+        );
+        assert_eq!(fail("@a = [a b;\n"), (1, 6, FeatureErrorKind::UnterminatedGlyphClass));
+        assert_eq!(fail("name \"abc;\n"), (1, 6, FeatureErrorKind::UnterminatedString));
+        assert_eq!(fail("sub a by b;\n}\n"), (2, 1, FeatureErrorKind::UnexpectedToken));
+        assert_eq!(
+            fail("sub a by b;\n  sub é by e;"),
+            (2, 7, FeatureErrorKind::UnexpectedCharacter)
+        );
+        assert_eq!(fail("include(foo.fea\n"), (1, 9, FeatureErrorKind::UnterminatedInclude));
+        assert_eq!(fail("anon sbit {\n} other;\n"), (1, 11, FeatureErrorKind::UnterminatedBlock));
+        assert_eq!(fail("feature liga { } liga"), (1, 22, FeatureErrorKind::UnterminatedStatement));
+
+        assert_eq!(parse_features("").unwrap().children, []);
+        let font = crate::Font::new();
+        assert_eq!(font.parse_features().unwrap(), None);
+    }
+}
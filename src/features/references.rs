@@ -0,0 +1,481 @@
+//! Finding and renaming the glyphs referred to by feature files.
+
+use std::collections::BTreeMap;
+
+use super::syntax::{Element, Node, NodeKind, Position, TokenKind};
+use crate::error::FeatureParseError;
+use crate::{Font, GlyphName, Layer};
+
+/// Names that are keywords, not glyphs, in rules.
+static KEYWORDS: &[&str] = &[
+    "pos",
+    "position",
+    "enum",
+    "enumerate",
+    "ignore",
+    "sub",
+    "substitute",
+    "rsub",
+    "reversesub",
+    "by",
+    "from",
+    "lookup",
+    "markClass",
+    "mark",
+    "base",
+    "ligature",
+    "ligComponent",
+    "cursive",
+    "NULL",
+];
+
+/// Statements whose first argument is a glyph.
+static GLYPH_STATEMENTS: &[&str] = &["Attach", "LigatureCaretByPos", "LigatureCaretByIndex"];
+
+/// What a reference refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReferenceKind {
+    /// A glyph, by name.
+    Glyph,
+    /// A glyph class, such as `@lowercase`. The name does not include the
+    /// `@`.
+    Class,
+}
+
+/// A reference to a glyph or glyph class in a feature file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphReference {
+    pub name: String,
+    pub kind: ReferenceKind,
+    /// Where the reference is in the text, including any `\` or `@`.
+    pub position: Position,
+}
+
+/// Find the references to glyphs and glyph classes in a feature file.
+///
+/// Glyphs are found in glyph classes, class definitions, `markClass`
+/// statements, substitution and positioning rules, and the `Attach` and
+/// `LigatureCaret` statements of the `GDEF` table. Glyph classes are found
+/// wherever they are referred to or defined. The contents of `anonymous`
+/// blocks and included files are not searched.
+///
+/// Each glyph of a range in a glyph class, like `a - c` or `a01-a10`, is
+/// returned, at the position of the range. A name with no spaces like
+/// `a-c` is only a range if `glyphs` is given, the name is not a glyph in
+/// it, and its ends are. The references are in the order they appear in
+/// the text.
+pub fn glyph_references(tree: &Node, glyphs: Option<&Layer>) -> Vec<GlyphReference> {
+    let is_glyph = |name: &str| glyphs.map(|layer| layer.contains_glyph(name)).unwrap_or(true);
+    let tokens: Vec<_> = tree.tokens_with_position().collect();
+    let mut references = Vec::new();
+    for found in classify(tree) {
+        let (position, token) = tokens[found.start];
+        let kind = found.kind;
+        let name = token.text.trim_start_matches(['\\', '@']);
+        let last = tokens[found.end].1.text.trim_start_matches('\\');
+        match range(&found, name, last, is_glyph) {
+            Some(members) => references.extend(members.into_iter().map(|name| GlyphReference {
+                name,
+                kind,
+                position,
+            })),
+            None => references.push(GlyphReference { name: name.into(), kind, position }),
+        }
+    }
+    references
+}
+
+/// Rename the glyphs referred to by a feature file, leaving the rest of the
+/// text as it was.
+///
+/// Each glyph reference found as by [`glyph_references`] whose name is a
+/// key of `renames` is changed to the corresponding value. A range with a
+/// glyph that is renamed is replaced by the names of its glyphs, so that it
+/// keeps referring to the same glyphs. Returns the number of references
+/// renamed.
+pub fn rename_glyph_references(
+    tree: &mut Node,
+    renames: &BTreeMap<GlyphName, GlyphName>,
+    glyphs: Option<&Layer>,
+) -> usize {
+    let is_glyph = |name: &str| {
+        renames.contains_key(name) || glyphs.map(|layer| layer.contains_glyph(name)).unwrap_or(true)
+    };
+    let found = classify(tree);
+    let mut tokens: Vec<_> = tree.tokens_mut().collect();
+    let mut renamed = 0;
+    for found in found.iter().filter(|found| found.kind == ReferenceKind::Glyph) {
+        let text = &tokens[found.start].text;
+        let name = text.trim_start_matches('\\');
+        let prefix = &text[..text.len() - name.len()];
+        let last = tokens[found.end].text.trim_start_matches('\\');
+        let new_text = match range(found, name, last, is_glyph) {
+            Some(members) if members.iter().any(|name| renames.contains_key(name.as_str())) => {
+                let names: Vec<_> = members
+                    .into_iter()
+                    .map(|name| match renames.get(name.as_str()) {
+                        Some(new) => {
+                            renamed += 1;
+                            new.to_string()
+                        }
+                        None => name,
+                    })
+                    .collect();
+                format!("{}{}", prefix, names.join(" "))
+            }
+            Some(_) => continue,
+            None => match renames.get(name) {
+                Some(new) => {
+                    renamed += 1;
+                    format!("{}{}", prefix, new)
+                }
+                None => continue,
+            },
+        };
+        tokens[found.start].text = new_text;
+        for token in &mut tokens[found.start + 1..=found.end] {
+            token.text.clear();
+        }
+    }
+    renamed
+}
+
+impl Font {
+    /// Find the references to glyphs and glyph classes in the font's
+    /// features.
+    ///
+    /// See [`glyph_references`] for details; ranges are resolved against the
+    /// default layer.
+    pub fn feature_glyph_references(&self) -> Result<Vec<GlyphReference>, FeatureParseError> {
+        Ok(match self.parse_features()? {
+            Some(tree) => glyph_references(&tree, Some(self.default_layer())),
+            None => Vec::new(),
+        })
+    }
+
+    /// Find the references in the font's features to glyphs that are not in
+    /// the default layer.
+    pub fn missing_feature_glyphs(&self) -> Result<Vec<GlyphReference>, FeatureParseError> {
+        let layer = self.default_layer();
+        let mut references = self.feature_glyph_references()?;
+        references.retain(|reference| {
+            reference.kind == ReferenceKind::Glyph && !layer.contains_glyph(&reference.name)
+        });
+        Ok(references)
+    }
+
+    /// Rename the glyphs referred to by the font's features.
+    ///
+    /// See [`rename_glyph_references`] for details; ranges are resolved
+    /// against the default layer. Returns the number of references renamed.
+    pub fn rename_feature_glyphs(
+        &mut self,
+        renames: &BTreeMap<GlyphName, GlyphName>,
+    ) -> Result<usize, FeatureParseError> {
+        let mut tree = match self.parse_features()? {
+            Some(tree) => tree,
+            None => return Ok(0),
+        };
+        let renamed = rename_glyph_references(&mut tree, renames, Some(self.default_layer()));
+        if renamed > 0 {
+            self.features = Some(tree.to_string());
+        }
+        Ok(renamed)
+    }
+}
+
+/// A reference among the tokens of a tree.
+struct Found {
+    /// The index of its first token.
+    start: usize,
+    /// The index of its last token, after `start` for a range written with
+    /// spaces, like `a - c`.
+    end: usize,
+    kind: ReferenceKind,
+    /// Whether the reference is in a glyph class, where it may be a range.
+    in_class: bool,
+}
+
+/// Returns the glyphs of a reference, if it is a range.
+///
+/// `name` and `last` are the names of the first and last tokens of the
+/// reference. A range written with spaces whose glyphs can't be listed is
+/// taken to be its two ends.
+fn range(
+    found: &Found,
+    name: &str,
+    last: &str,
+    is_glyph: impl Fn(&str) -> bool,
+) -> Option<Vec<String>> {
+    if found.kind != ReferenceKind::Glyph || !found.in_class {
+        None
+    } else if found.start != found.end {
+        Some(range_members(name, last).unwrap_or_else(|| vec![name.into(), last.into()]))
+    } else if is_glyph(name) {
+        None
+    } else {
+        name.match_indices('-')
+            .map(|(idx, _)| (&name[..idx], &name[idx + 1..]))
+            .filter(|(first, last)| !first.is_empty() && is_glyph(first) && is_glyph(last))
+            .find_map(|(first, last)| range_members(first, last))
+    }
+}
+
+/// List the glyphs from `first` to `last`, as feature compilers do.
+///
+/// The names must be the same but for a single letter, `a` to `z` or `A` to
+/// `Z`, or a number, whose digits are kept in the names listed.
+fn range_members(first: &str, last: &str) -> Option<Vec<String>> {
+    if first.len() != last.len() || !first.is_ascii() || !last.is_ascii() {
+        return None;
+    }
+    let prefix = first.bytes().zip(last.bytes()).take_while(|(a, b)| a == b).count();
+    let suffix = first[prefix..]
+        .bytes()
+        .rev()
+        .zip(last[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (start, end) = (&first[prefix..first.len() - suffix], &last[prefix..last.len() - suffix]);
+    let (head, tail) = (&first[..prefix], &first[first.len() - suffix..]);
+    let start_byte = start.as_bytes().first().copied();
+    let end_byte = end.as_bytes().first().copied();
+    match (start_byte, end_byte) {
+        // the ends are the same
+        (None, None) => Some(vec![first.into()]),
+        (Some(a), Some(b))
+            if start.len() == 1
+                && a <= b
+                && (a.is_ascii_lowercase() && b.is_ascii_lowercase()
+                    || a.is_ascii_uppercase() && b.is_ascii_uppercase()) =>
+        {
+            Some((a..=b).map(|c| format!("{}{}{}", head, c as char, tail)).collect())
+        }
+        _ => {
+            // extend the number to all of its digits
+            let digits = |text: &str| text.bytes().rev().take_while(u8::is_ascii_digit).count();
+            let head_digits = digits(head);
+            let tail_digits = tail.bytes().take_while(u8::is_ascii_digit).count();
+            let from = prefix - head_digits;
+            let to = first.len() - suffix + tail_digits;
+            let (a, b) = (&first[from..to], &last[from..to]);
+            if !a.bytes().all(|c| c.is_ascii_digit()) || !b.bytes().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let (a, b) = (a.parse::<u32>().ok()?, b.parse::<u32>().ok()?);
+            if a > b {
+                return None;
+            }
+            let (head, tail, width) = (&first[..from], &first[to..], to - from);
+            Some((a..=b).map(|n| format!("{}{:0width$}{}", head, n, tail, width = width)).collect())
+        }
+    }
+}
+
+/// Find the references among the tokens of the tree, in order.
+fn classify(tree: &Node) -> Vec<Found> {
+    let mut found = Vec::new();
+    classify_node(tree, &mut 0, &mut found);
+    found
+}
+
+fn classify_node(node: &Node, idx: &mut usize, found: &mut Vec<Found>) {
+    let rule = matches!(
+        node.kind,
+        NodeKind::Position
+            | NodeKind::Substitution
+            | NodeKind::MarkClass
+            | NodeKind::GlyphClassDefinition
+    );
+    let in_class = node.kind == NodeKind::GlyphClass;
+    let glyph_statement = node.kind == NodeKind::Statement
+        && node.keyword().map(|keyword| GLYPH_STATEMENTS.contains(&keyword)).unwrap_or(false);
+    let mut angle_depth = 0usize;
+    let mut after_lookup = false;
+    let mut significant = 0;
+    // whether the last significant token was a glyph, or a hyphen after one
+    let mut after_glyph = false;
+    let mut in_range = false;
+    for child in &node.children {
+        let token = match child {
+            Element::Node(child) => {
+                classify_node(child, idx, found);
+                after_glyph = false;
+                in_range = false;
+                continue;
+            }
+            Element::Token(token) => token,
+        };
+        let mut glyph = false;
+        match token.kind {
+            TokenKind::ClassName => {
+                found.push(Found { start: *idx, end: *idx, kind: ReferenceKind::Class, in_class })
+            }
+            TokenKind::LAngle => angle_depth += 1,
+            TokenKind::RAngle => angle_depth = angle_depth.saturating_sub(1),
+            TokenKind::Hyphen if in_class && after_glyph => in_range = true,
+            TokenKind::Name if in_range => found.last_mut().unwrap().end = *idx,
+            TokenKind::Name => {
+                let text = token.text.as_str();
+                let is_glyph = match node.kind {
+                    NodeKind::GlyphClass => true,
+                    _ if glyph_statement => significant == 1,
+                    _ => rule && angle_depth == 0 && !after_lookup && !KEYWORDS.contains(&text),
+                };
+                if is_glyph {
+                    found.push(Found {
+                        start: *idx,
+                        end: *idx,
+                        kind: ReferenceKind::Glyph,
+                        in_class,
+                    });
+                    glyph = true;
+                }
+                after_lookup = rule && text == "lookup";
+            }
+            _ => (),
+        }
+        if !token.is_trivia() {
+            significant += 1;
+            after_glyph = glyph || (in_range && token.kind == TokenKind::Hyphen);
+            in_range = in_range && token.kind == TokenKind::Hyphen;
+        }
+        *idx += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Glyph;
+    use maplit::btreemap;
+
+    const FEA: &str = r#"@lc = [a-c \d e - f];
+markClass [acute grave] <anchor 0 500> @TOP;
+table GDEF {
+    Attach a 1;
+    LigatureCaretByPos f_i 300;
+    GlyphClassDef [a], [f_i], @TOP, ;
+} GDEF;
+lookup ss01 { sub a by a.ss01; } ss01;
+feature liga {
+    lookupflag UseMarkFilteringSet [acute];
+    sub f i by f_i; # f i
+    sub [a b]' lookup ss01 c by NULL;
+    ignore sub x' y;
+    pos base @lc <anchor 250 450> mark @TOP;
+    pos f_i <anchor NULL> i 10;
+} liga;
+anon foo {
+    a b c
+} foo;
+"#;
+
+    fn font() -> Font {
+        let mut font = Font::new();
+        for name in ["a", "b", "c", "d", "e", "f", "i", "acute", "grave", "f_i"].iter() {
+            font.default_layer_mut().insert_glyph(Glyph::new_named(*name));
+        }
+        font.features = Some(FEA.into());
+        font
+    }
+
+    #[test]
+    fn references() {
+        let font = font();
+        let references = font.feature_glyph_references().unwrap();
+        let names: Vec<_> = references
+            .iter()
+            .map(|reference| match reference.kind {
+                ReferenceKind::Glyph => reference.name.clone(),
+                ReferenceKind::Class => format!("@{}", reference.name),
+            })
+            .collect();
+        #[rustfmt::skip]
+        assert_eq!(
+            names,
+            [
+                "@lc", "a", "b", "c", "d", "e", "f",
+                "acute", "grave", "@TOP",
+                "a",
+                "f_i",
+                "a", "f_i", "@TOP",
+                "a", "a.ss01",
+                "acute",
+                "f", "i", "f_i",
+                "a", "b", "c",
+                "x", "y",
+                "@lc", "@TOP",
+                "f_i", "i",
+            ]
+        );
+        let position =
+            |idx: usize| (references[idx].position.line, references[idx].position.column);
+        assert_eq!(position(1), (1, 8));
+        assert_eq!(position(3), (1, 8));
+        assert_eq!(position(4), (1, 12));
+        assert_eq!(position(5), (1, 15));
+        assert_eq!(position(26), (14, 14));
+
+        let missing: Vec<_> = font
+            .missing_feature_glyphs()
+            .unwrap()
+            .into_iter()
+            .map(|reference| (reference.name, reference.position.line))
+            .collect();
+        assert_eq!(missing, [("a.ss01".to_string(), 8), ("x".into(), 13), ("y".into(), 13)]);
+
+        // without glyphs to resolve ranges against, a range is one name
+        let tree = font.parse_features().unwrap().unwrap();
+        assert_eq!(glyph_references(&tree, None)[1].name, "a-c");
+
+        // glyphs missing from the middle of ranges are found
+        let mut font = font;
+        font.default_layer_mut().remove_glyph("b");
+        font.default_layer_mut().insert_glyph(Glyph::new_named("a01"));
+        font.default_layer_mut().insert_glyph(Glyph::new_named("a03"));
+        font.features = Some("@x = [a-c];\n@y = [d - f];\n@z = [a01-a03];\n".into());
+        let missing: Vec<_> = font
+            .missing_feature_glyphs()
+            .unwrap()
+            .into_iter()
+            .map(|reference| (reference.name, reference.position.line))
+            .collect();
+        assert_eq!(missing, [("b".to_string(), 1), ("a02".into(), 3)]);
+    }
+
+    #[test]
+    fn rename() {
+        let mut font = font();
+        let renames = btreemap! {
+            "a".into() => "a.alt".into(),
+            "c".into() => "c.alt".into(),
+            "d".into() => "d.alt".into(),
+            "f_i".into() => "fi".into(),
+        };
+        assert_eq!(font.rename_feature_glyphs(&renames).unwrap(), 12);
+        let features = font.features.as_deref().unwrap();
+        assert!(features.starts_with("@lc = [a.alt b c.alt \\d.alt e - f];\n"));
+        assert!(features.contains("    Attach a.alt 1;\n    LigatureCaretByPos fi 300;\n"));
+        assert!(features.contains("    GlyphClassDef [a.alt], [fi], @TOP, ;\n"));
+        assert!(features.contains("lookup ss01 { sub a.alt by a.ss01; } ss01;\n"));
+        assert!(features.contains("    sub f i by fi; # f i\n"));
+        assert!(features.contains("    sub [a.alt b]' lookup ss01 c.alt by NULL;\n"));
+        assert!(features.contains("    pos fi <anchor NULL> i 10;\n"));
+        assert!(features.ends_with("anon foo {\n    a b c\n} foo;\n"));
+        assert_eq!(font.features.as_deref().unwrap().len(), FEA.len() + 30);
+
+        let renames = btreemap! { "missing".into() => "other".into() };
+        assert_eq!(font.rename_feature_glyphs(&renames).unwrap(), 0);
+
+        // renaming a glyph in a range lists the glyphs of the range
+        let mut font = self::font();
+        font.features = Some("@x = [a-c];\n@y = [d - f g];\n@z = [b - c];\n".into());
+        let renames = btreemap! { "e".into() => "e.alt".into(), "i".into() => "i.alt".into() };
+        assert_eq!(font.rename_feature_glyphs(&renames).unwrap(), 1);
+        assert_eq!(
+            font.features.as_deref().unwrap(),
+            "@x = [a-c];\n@y = [d e.alt f g];\n@z = [b - c];\n"
+        );
+    }
+}
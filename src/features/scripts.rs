@@ -0,0 +1,95 @@
+//! Finding the script of a character, for splitting features by script.
+//!
+//! This covers the blocks of the scripts most commonly found in fonts;
+//! characters outside them, including digits, punctuation and combining
+//! marks shared between scripts, have no script.
+
+/// A script, as its OpenType tag and whether it is written right to left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Script {
+    pub(crate) tag: &'static str,
+    pub(crate) rtl: bool,
+}
+
+const fn ltr(tag: &'static str) -> Script {
+    Script { tag, rtl: false }
+}
+
+const fn rtl(tag: &'static str) -> Script {
+    Script { tag, rtl: true }
+}
+
+/// Ranges of characters and their scripts, sorted by the start of the range.
+#[rustfmt::skip]
+static RANGES: &[(u32, u32, Script)] = &[
+    (0x0041, 0x005A, ltr("latn")),
+    (0x0061, 0x007A, ltr("latn")),
+    (0x00AA, 0x00AA, ltr("latn")),
+    (0x00BA, 0x00BA, ltr("latn")),
+    (0x00C0, 0x00D6, ltr("latn")),
+    (0x00D8, 0x00F6, ltr("latn")),
+    (0x00F8, 0x02AF, ltr("latn")),
+    (0x0370, 0x03FF, ltr("grek")),
+    (0x0400, 0x052F, ltr("cyrl")),
+    (0x0531, 0x058F, ltr("armn")),
+    (0x0591, 0x05FF, rtl("hebr")),
+    (0x0600, 0x06FF, rtl("arab")),
+    (0x0700, 0x074F, rtl("syrc")),
+    (0x0750, 0x077F, rtl("arab")),
+    (0x0780, 0x07BF, rtl("thaa")),
+    (0x07C0, 0x07FF, rtl("nko ")),
+    (0x08A0, 0x08FF, rtl("arab")),
+    (0x0900, 0x097F, ltr("dev2")),
+    (0x0980, 0x09FF, ltr("bng2")),
+    (0x0A00, 0x0A7F, ltr("gur2")),
+    (0x0A80, 0x0AFF, ltr("gjr2")),
+    (0x0B00, 0x0B7F, ltr("ory2")),
+    (0x0B80, 0x0BFF, ltr("tml2")),
+    (0x0C00, 0x0C7F, ltr("tel2")),
+    (0x0C80, 0x0CFF, ltr("knd2")),
+    (0x0D00, 0x0D7F, ltr("mlm2")),
+    (0x0D80, 0x0DFF, ltr("sinh")),
+    (0x0E00, 0x0E7F, ltr("thai")),
+    (0x0E80, 0x0EFF, ltr("lao ")),
+    (0x0F00, 0x0FFF, ltr("tibt")),
+    (0x1000, 0x109F, ltr("mym2")),
+    (0x10A0, 0x10FF, ltr("geor")),
+    (0x1100, 0x11FF, ltr("hang")),
+    (0x1200, 0x139F, ltr("ethi")),
+    (0x13A0, 0x13FF, ltr("cher")),
+    (0x1780, 0x17FF, ltr("khmr")),
+    (0x1800, 0x18AF, ltr("mong")),
+    (0x1C80, 0x1C8F, ltr("cyrl")),
+    (0x1D00, 0x1D7F, ltr("latn")),
+    (0x1E00, 0x1EFF, ltr("latn")),
+    (0x1F00, 0x1FFF, ltr("grek")),
+    (0x2C60, 0x2C7F, ltr("latn")),
+    (0x2DE0, 0x2DFF, ltr("cyrl")),
+    (0x3040, 0x30FF, ltr("kana")),
+    (0x3130, 0x318F, ltr("hang")),
+    (0x3400, 0x4DBF, ltr("hani")),
+    (0x4E00, 0x9FFF, ltr("hani")),
+    (0xA640, 0xA69F, ltr("cyrl")),
+    (0xA720, 0xA7FF, ltr("latn")),
+    (0xAB30, 0xAB6F, ltr("latn")),
+    (0xAC00, 0xD7AF, ltr("hang")),
+    (0xFB00, 0xFB06, ltr("latn")),
+    (0xFB13, 0xFB17, ltr("armn")),
+    (0xFB1D, 0xFB4F, rtl("hebr")),
+    (0xFB50, 0xFDFF, rtl("arab")),
+    (0xFE70, 0xFEFF, rtl("arab")),
+    (0xFF21, 0xFF3A, ltr("latn")),
+    (0xFF41, 0xFF5A, ltr("latn")),
+];
+
+/// Returns the script of a character, if it belongs to one.
+pub(crate) fn script(c: char) -> Option<Script> {
+    let c = c as u32;
+    let idx = RANGES.partition_point(|(start, _, _)| *start <= c).checked_sub(1)?;
+    let (_, end, script) = RANGES[idx];
+    if c <= end {
+        Some(script)
+    } else {
+        None
+    }
+}
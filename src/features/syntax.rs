@@ -0,0 +1,245 @@
+//! The syntax tree of a feature file.
+
+use std::fmt;
+
+/// The kind of a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Spaces, tabs and line breaks.
+    Whitespace,
+    /// A comment, from `#` to the end of the line.
+    Comment,
+    /// A glyph name or keyword, such as `a.sc` or `feature`. Glyph names
+    /// escaped with a backslash keep it.
+    Name,
+    /// A glyph class name, such as `@lowercase`.
+    ClassName,
+    /// A CID, such as `\101`.
+    Cid,
+    /// A number, possibly negative or with a fraction.
+    Number,
+    /// A quoted string, including the quotes.
+    String,
+    /// The file name in an `include` statement.
+    Path,
+    /// The contents of an `anonymous` block, which are not parsed.
+    Raw,
+    Semicolon,
+    Comma,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    LAngle,
+    RAngle,
+    Equals,
+    /// The `'` marking glyphs in contextual rules.
+    Quote,
+    /// The `-` of a glyph range.
+    Hyphen,
+}
+
+/// A token of a feature file, with its text exactly as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+/// The kind of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NodeKind {
+    /// A whole feature file.
+    File,
+    /// A `feature` block.
+    FeatureBlock,
+    /// A `lookup` block.
+    LookupBlock,
+    /// A `table` block.
+    TableBlock,
+    /// An `anonymous` block.
+    AnonymousBlock,
+    /// Any other block, such as `featureNames` or `cvParameters`.
+    Block,
+    /// A `languagesystem` statement.
+    LanguageSystem,
+    /// A `script` statement.
+    Script,
+    /// A `language` statement.
+    Language,
+    /// A `lookupflag` statement.
+    LookupFlag,
+    /// A reference to a lookup defined elsewhere, such as `lookup kern1;`.
+    LookupReference,
+    /// An `include` statement.
+    Include,
+    /// A glyph class definition, such as `@lc = [a b c];`.
+    GlyphClassDefinition,
+    /// A `markClass` statement.
+    MarkClass,
+    /// A positioning rule, including `enum` and `ignore pos` rules.
+    Position,
+    /// A substitution rule, including `rsub` and `ignore sub` rules.
+    Substitution,
+    /// Any other statement.
+    Statement,
+    /// A glyph class written out in brackets, such as `[a b c]`.
+    GlyphClass,
+}
+
+/// A child of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    Token(Token),
+}
+
+/// A node of the syntax tree.
+///
+/// The tree is lossless: it holds every token of the text it was parsed
+/// from, including whitespace and comments, so that writing it out with
+/// [`Display`](fmt::Display) gives back the text exactly. Whitespace and
+/// comments between statements belong to the enclosing node; those inside
+/// a statement belong to the statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+/// Where a token starts in the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    /// The byte offset.
+    pub offset: usize,
+    /// The line, starting at one.
+    pub line: usize,
+    /// The column in characters, starting at one.
+    pub column: usize,
+}
+
+impl Node {
+    /// Iterate over the tokens of the node and its descendants, in order.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens.into_iter()
+    }
+
+    /// Iterate over the tokens, with where they start in the text of the
+    /// node.
+    pub fn tokens_with_position(&self) -> impl Iterator<Item = (Position, &Token)> {
+        let mut position = Position { offset: 0, line: 1, column: 1 };
+        self.tokens().map(move |token| {
+            let start = position;
+            position.advance(&token.text);
+            (start, token)
+        })
+    }
+
+    /// Iterate over the mutable tokens of the node and its descendants, in
+    /// order.
+    pub fn tokens_mut(&mut self) -> impl Iterator<Item = &mut Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens_mut(&mut tokens);
+        tokens.into_iter()
+    }
+
+    /// Iterate over the child nodes.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// Iterate over the node and all its descendant nodes, in order.
+    pub fn descendants(&self) -> impl Iterator<Item = &Node> {
+        let mut nodes = Vec::new();
+        self.collect_nodes(&mut nodes);
+        nodes.into_iter()
+    }
+
+    /// Iterate over the tokens that are not whitespace or comments.
+    pub fn significant_tokens(&self) -> impl Iterator<Item = &Token> {
+        self.tokens().filter(|token| !token.is_trivia())
+    }
+
+    /// Returns the name of a block or the keyword of a statement: the first
+    /// name in the node.
+    pub fn keyword(&self) -> Option<&str> {
+        self.significant_tokens().next().map(|token| token.text.as_str())
+    }
+
+    /// Returns the label of a block, such as the tag of a feature or the
+    /// name of a lookup: the second name in the node.
+    pub fn label(&self) -> Option<&str> {
+        match self.kind {
+            NodeKind::FeatureBlock
+            | NodeKind::LookupBlock
+            | NodeKind::TableBlock
+            | NodeKind::AnonymousBlock
+            | NodeKind::Block => self.significant_tokens().nth(1).map(|token| token.text.as_str()),
+            _ => None,
+        }
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        for child in &self.children {
+            match child {
+                Element::Node(node) => node.collect_tokens(tokens),
+                Element::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    fn collect_tokens_mut<'a>(&'a mut self, tokens: &mut Vec<&'a mut Token>) {
+        for child in &mut self.children {
+            match child {
+                Element::Node(node) => node.collect_tokens_mut(tokens),
+                Element::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    fn collect_nodes<'a>(&'a self, nodes: &mut Vec<&'a Node>) {
+        nodes.push(self);
+        for node in self.child_nodes() {
+            node.collect_nodes(nodes);
+        }
+    }
+}
+
+impl Token {
+    /// Returns `true` for whitespace and comments.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+impl Position {
+    /// Move the position past `text`.
+    pub(crate) fn advance(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset += text.len();
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.tokens() {
+            f.write_str(&token.text)?;
+        }
+        Ok(())
+    }
+}
@@ -287,8 +287,8 @@ pub(crate) struct OutlineBuilder {
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub(crate) struct Outline {
-    components: Vec<Component>,
-    contours: Vec<Contour>,
+    pub(crate) components: Vec<Component>,
+    pub(crate) contours: Vec<Contour>,
 }
 
 #[derive(Debug)]
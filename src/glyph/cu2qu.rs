@@ -0,0 +1,293 @@
+//! Converting cubic curves to quadratic splines.
+//!
+//! This is a port of the [cu2qu] algorithm from fontTools.
+//!
+//! [cu2qu]: https://github.com/fonttools/fonttools/tree/main/Lib/fontTools/cu2qu
+
+use super::segment::{Point, PointRuns};
+use crate::error::{CurveConversionError, ErrorKind};
+use crate::{Contour, ContourPoint, Glyph, PointType};
+
+/// The maximum number of quadratic segments a cubic is approximated with.
+const MAX_SEGMENTS: usize = 100;
+
+impl Contour {
+    /// Convert cubic curves to quadratic splines.
+    ///
+    /// Each cubic segment is replaced by the quadratic spline with the fewest
+    /// off-curves that stays within `max_error` font units of it. A value of
+    /// one thousandth of the em is a common choice. Points other than the
+    /// replaced off-curves keep their attributes, and the contour keeps its
+    /// direction; see [`Glyph::correct_direction`] for preparing TrueType
+    /// outlines.
+    pub fn convert_to_quadratic(&mut self, max_error: f64) -> Result<(), CurveConversionError> {
+        let mut converted = convert_contours(&[&*self], max_error)?;
+        self.points = converted.remove(0);
+        Ok(())
+    }
+}
+
+impl Glyph {
+    /// Convert the cubic curves in all contours to quadratic splines.
+    ///
+    /// See [`Contour::convert_to_quadratic`] for details.
+    pub fn convert_to_quadratic(&mut self, max_error: f64) -> Result<(), CurveConversionError> {
+        Glyph::convert_to_quadratic_compatible(&mut [self], max_error)
+    }
+
+    /// Convert the cubic curves of several masters of a glyph to quadratic
+    /// splines, keeping them compatible for interpolation.
+    ///
+    /// Corresponding curves are approximated with the same number of
+    /// off-curves in every glyph, which means some may use more than they
+    /// would if converted on their own. The glyphs must have the same number
+    /// of contours with the same point structure. Nothing is changed if an
+    /// error occurs.
+    pub fn convert_to_quadratic_compatible(
+        glyphs: &mut [&mut Glyph],
+        max_error: f64,
+    ) -> Result<(), CurveConversionError> {
+        let contour_count = glyphs.first().map_or(0, |glyph| glyph.contours.len());
+        if glyphs.iter().any(|glyph| glyph.contours.len() != contour_count) {
+            return Err(CurveConversionError::Incompatible);
+        }
+        let converted = (0..contour_count)
+            .map(|idx| {
+                let contours: Vec<_> = glyphs.iter().map(|glyph| &glyph.contours[idx]).collect();
+                convert_contours(&contours, max_error)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (idx, points) in converted.into_iter().enumerate() {
+            for (glyph, points) in glyphs.iter_mut().zip(points) {
+                glyph.contours[idx].points = points;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Convert corresponding contours together, returning their new points.
+fn convert_contours(
+    contours: &[&Contour],
+    max_error: f64,
+) -> Result<Vec<Vec<ContourPoint>>, CurveConversionError> {
+    let mut masters =
+        contours.iter().map(|contour| contour.point_runs()).collect::<Result<Vec<_>, _>>()?;
+    let first = match masters.first() {
+        Some(first) => first,
+        None => return Ok(Vec::new()),
+    };
+    if masters.iter().any(|master| !same_structure(first, master)) {
+        return Err(CurveConversionError::Incompatible);
+    }
+
+    for run in 0..first.runs.len() {
+        let segment = &masters[0].runs[run];
+        let end_type = segment.last().map(|point| point.typ.clone());
+        match (end_type, segment.len() - 1) {
+            (Some(PointType::Curve), 0) => set_end_type(&mut masters, run, PointType::Line),
+            (Some(PointType::Curve), 1) => set_end_type(&mut masters, run, PointType::QCurve),
+            (Some(PointType::Curve), 2) => {
+                let cubics = masters
+                    .iter()
+                    .map(|master| {
+                        let start = master.segment_start(run).expect("curves start on-curve");
+                        let points = &master.runs[run];
+                        [
+                            start.into(),
+                            (&points[0]).into(),
+                            (&points[1]).into(),
+                            (&points[2]).into(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+                let splines = curves_to_quadratic(&cubics, max_error)
+                    .ok_or(CurveConversionError::ApproximationNotFound)?;
+                for (master, spline) in masters.iter_mut().zip(splines) {
+                    let points = &mut master.runs[run];
+                    let mut end = points.pop().expect("checked above");
+                    end.typ = PointType::QCurve;
+                    points.clear();
+                    points.extend(
+                        spline[1..spline.len() - 1]
+                            .iter()
+                            .map(|pt| pt.to_contour_point(PointType::OffCurve, false)),
+                    );
+                    points.push(end);
+                }
+            }
+            (Some(PointType::Curve), _) => return Err(ErrorKind::TooManyOffCurves.into()),
+            _ => (),
+        }
+    }
+    Ok(masters.into_iter().map(PointRuns::into_points).collect())
+}
+
+fn same_structure(first: &PointRuns, other: &PointRuns) -> bool {
+    first.start.is_some() == other.start.is_some()
+        && first.runs.len() == other.runs.len()
+        && first.runs.iter().zip(&other.runs).all(|(a, b)| {
+            a.len() == b.len() && a.last().map(|pt| &pt.typ) == b.last().map(|pt| &pt.typ)
+        })
+}
+
+fn set_end_type(masters: &mut [PointRuns], run: usize, typ: PointType) {
+    for master in masters {
+        if let Some(end) = master.runs[run].last_mut() {
+            end.typ = typ.clone();
+        }
+    }
+}
+
+/// Approximate the cubics with quadratic splines that all have the same
+/// number of segments. Each spline includes the cubic's end points.
+fn curves_to_quadratic(curves: &[[Point; 4]], max_error: f64) -> Option<Vec<Vec<Point>>> {
+    if curves.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut splines = vec![None; curves.len()];
+    let mut segments = 1;
+    let mut idx = 0;
+    let mut last_idx = 0;
+    loop {
+        match cubic_approx_spline(&curves[idx], segments, max_error) {
+            Some(spline) => {
+                splines[idx] = Some(spline);
+                idx = (idx + 1) % curves.len();
+                if idx == last_idx {
+                    return splines.into_iter().collect();
+                }
+            }
+            None if segments == MAX_SEGMENTS => return None,
+            None => {
+                // Start over with one more segment, and check every curve
+                // again until we get back to this one.
+                segments += 1;
+                last_idx = idx;
+            }
+        }
+    }
+}
+
+/// Approximate a cubic with a quadratic spline of `n` segments, if that is
+/// possible within `tolerance`.
+fn cubic_approx_spline(cubic: &[Point; 4], n: usize, tolerance: f64) -> Option<Vec<Point>> {
+    if n == 1 {
+        return cubic_approx_quadratic(cubic, tolerance);
+    }
+
+    let cubics = split_cubic_into_n(cubic, n);
+    let mut next_q1 = cubic_approx_control(0.0, &cubics[0]);
+    let mut q2 = cubic[0];
+    let mut d1 = Point::new(0.0, 0.0);
+    let mut spline = vec![cubic[0], next_q1];
+    for i in 1..=n {
+        let [_, c1, c2, c3] = cubics[i - 1];
+        let q0 = q2;
+        let q1 = next_q1;
+        if i < n {
+            next_q1 = cubic_approx_control(i as f64 / (n - 1) as f64, &cubics[i]);
+            spline.push(next_q1);
+            q2 = q1.midpoint(next_q1);
+        } else {
+            q2 = c3;
+        }
+
+        // The error at the end points, then between them.
+        let d0 = d1;
+        d1 = q2 - c3;
+        if d1.hypot() > tolerance
+            || !cubic_farthest_fit_inside(
+                d0,
+                q0 + (q1 - q0) * (2.0 / 3.0) - c1,
+                q2 + (q1 - q2) * (2.0 / 3.0) - c2,
+                d1,
+                tolerance,
+            )
+        {
+            return None;
+        }
+    }
+    spline.push(cubic[3]);
+    Some(spline)
+}
+
+/// Approximate a cubic with a single quadratic, whose control point is the
+/// intersection of the cubic's handles.
+fn cubic_approx_quadratic(cubic: &[Point; 4], tolerance: f64) -> Option<Vec<Point>> {
+    let [c0, _, _, c3] = *cubic;
+    let q1 = calc_intersect(cubic)?;
+    let c1 = c0 + (q1 - c0) * (2.0 / 3.0);
+    let c2 = c3 + (q1 - c3) * (2.0 / 3.0);
+    let zero = Point::new(0.0, 0.0);
+    if !cubic_farthest_fit_inside(zero, c1 - cubic[1], c2 - cubic[2], zero, tolerance) {
+        return None;
+    }
+    Some(vec![c0, q1, c3])
+}
+
+/// The intersection of the lines through the first and the last two points.
+fn calc_intersect([a, b, c, d]: &[Point; 4]) -> Option<Point> {
+    let ab = *b - *a;
+    let cd = *d - *c;
+    let perpendicular = Point::new(-ab.y, ab.x);
+    let h = perpendicular.dot(*a - *c) / perpendicular.dot(cd);
+    if !h.is_finite() {
+        return None;
+    }
+    Some(*c + cd * h)
+}
+
+/// The control point of a quadratic approximating the cubic, interpolated
+/// between the handle extensions by `t`.
+fn cubic_approx_control(t: f64, [p0, p1, p2, p3]: &[Point; 4]) -> Point {
+    let p1 = *p0 + (*p1 - *p0) * 1.5;
+    let p2 = *p3 + (*p2 - *p3) * 1.5;
+    p1.lerp(p2, t)
+}
+
+/// Returns `true` if the cubic described by the given deltas stays within
+/// `tolerance` of the origin.
+///
+/// The cubic is subdivided until its control points are all close enough,
+/// or a point on the curve is too far away.
+fn cubic_farthest_fit_inside(p0: Point, p1: Point, p2: Point, p3: Point, tolerance: f64) -> bool {
+    if p2.hypot() <= tolerance && p1.hypot() <= tolerance {
+        return true;
+    }
+
+    let mid = (p0 + (p1 + p2) * 3.0 + p3) * 0.125;
+    // NaN must fail the check, rather than recursing forever.
+    if mid.hypot() > tolerance || mid.hypot().is_nan() {
+        return false;
+    }
+    let deriv3 = (p3 + p2 - p1 - p0) * 0.125;
+    cubic_farthest_fit_inside(p0, p0.midpoint(p1), mid - deriv3, mid, tolerance)
+        && cubic_farthest_fit_inside(mid, mid + deriv3, p2.midpoint(p3), p3, tolerance)
+}
+
+/// Split a cubic into `n` cubics of equal parameter length.
+fn split_cubic_into_n(&[p0, p1, p2, p3]: &[Point; 4], n: usize) -> Vec<[Point; 4]> {
+    let c = (p1 - p0) * 3.0;
+    let b = (p2 - p1) * 3.0 - c;
+    let d = p0;
+    let a = p3 - d - c - b;
+
+    let dt = 1.0 / n as f64;
+    let delta_2 = dt * dt;
+    let delta_3 = dt * delta_2;
+    (0..n)
+        .map(|i| {
+            let t1 = i as f64 * dt;
+            let t1_2 = t1 * t1;
+            let a1 = a * delta_3;
+            let b1 = (a * (3.0 * t1) + b) * delta_2;
+            let c1 = (b * (2.0 * t1) + c + a * (3.0 * t1_2)) * dt;
+            let d1 = a * (t1 * t1_2) + b * t1_2 + c * t1 + d;
+
+            let x2 = c1 * (1.0 / 3.0) + d1;
+            let x3 = (b1 + c1) * (1.0 / 3.0) + x2;
+            [d1, x2, x3, a1 + d1 + c1 + b1]
+        })
+        .collect()
+}
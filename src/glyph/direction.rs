@@ -0,0 +1,113 @@
+//! Detecting and correcting the direction of contours.
+
+use super::segment::{winding_number, Segment};
+use crate::error::ErrorKind;
+use crate::{Contour, Glyph, PointType};
+
+impl Contour {
+    /// Returns the signed area enclosed by the contour.
+    ///
+    /// The area is positive for a counter-clockwise contour and negative for
+    /// a clockwise one, in the y-up coordinate system of a glyph. An open
+    /// contour is measured as if it were closed by a straight line.
+    pub fn signed_area(&self) -> Result<f64, ErrorKind> {
+        let segments = self.segments()?;
+        let mut area: f64 = segments.iter().map(Segment::area).sum();
+        if let (Some(first), Some(last)) = (segments.first(), segments.last()) {
+            area += Segment::Line(last.end(), first.start()).area();
+        }
+        Ok(area)
+    }
+
+    /// Returns `true` if the contour runs clockwise.
+    ///
+    /// A contour enclosing no area is not considered clockwise.
+    pub fn is_clockwise(&self) -> Result<bool, ErrorKind> {
+        Ok(self.signed_area()? < 0.0)
+    }
+
+    /// Reverse the direction of the contour.
+    ///
+    /// Segment types move along with their segments, so the outline is
+    /// unchanged apart from its direction. A closed contour keeps its first
+    /// point; an open contour starts at its former end point, which becomes
+    /// the [`PointType::Move`].
+    pub fn reverse(&mut self) {
+        if self.points.is_empty() {
+            return;
+        }
+        let closed = self.is_closed();
+        let mut segment_type = if closed {
+            // Move the first point to the end, so that it is first again
+            // after reversing.
+            self.points.rotate_left(1);
+            self.points
+                .iter()
+                .find(|point| point.typ != PointType::OffCurve)
+                .map_or(PointType::OffCurve, |point| point.typ.clone())
+        } else {
+            PointType::Move
+        };
+        self.points.reverse();
+        if !closed {
+            // An open contour must start on-curve; off-curves leading up to
+            // the old end point are invalid and are dropped.
+            let first_on_curve = self
+                .points
+                .iter()
+                .position(|point| point.typ != PointType::OffCurve)
+                .unwrap_or(self.points.len());
+            self.points.drain(..first_on_curve);
+        }
+        // The type of an on-curve point describes the segment ending at it,
+        // so each one takes the type of the segment that used to start there.
+        for point in &mut self.points {
+            if point.typ != PointType::OffCurve {
+                std::mem::swap(&mut point.typ, &mut segment_type);
+            }
+        }
+    }
+}
+
+impl Glyph {
+    /// Reverse contours as needed so that they follow a consistent direction.
+    ///
+    /// Outer contours run counter-clockwise, as expected in PostScript
+    /// outlines, or clockwise if `true_type` is set. Contours nested inside
+    /// an odd number of other contours are counters, and run the opposite
+    /// way. Open contours and contours without area are left alone.
+    pub fn correct_direction(&mut self, true_type: bool) -> Result<(), ErrorKind> {
+        let polygons = self
+            .contours
+            .iter()
+            .map(|contour| if contour.is_closed() { contour.flatten().map(Some) } else { Ok(None) })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (idx, polygon) in polygons.iter().enumerate() {
+            let polygon = match polygon {
+                Some(polygon) if polygon.len() > 1 => polygon,
+                _ => continue,
+            };
+            let area = self.contours[idx].signed_area()?;
+            if area == 0.0 {
+                continue;
+            }
+            // Sample off the vertices, which are more likely to be shared
+            // with other contours.
+            let sample = polygon[0].midpoint(polygon[1]);
+            let depth = polygons
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != idx)
+                .filter_map(|(_, other)| other.as_ref())
+                .filter(|other| winding_number(other, sample) != 0)
+                .count();
+            let is_outer = depth % 2 == 0;
+            let should_be_clockwise = is_outer == true_type;
+            if (area < 0.0) != should_be_clockwise {
+                self.contours[idx].reverse();
+            }
+        }
+        Ok(())
+    }
+}
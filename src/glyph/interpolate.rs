@@ -0,0 +1,78 @@
+//! Interpolating between compatible glyphs.
+
+use crate::compat;
+use crate::error::InterpolationError;
+use crate::{Glyph, Line};
+
+impl Glyph {
+    /// Interpolate between this glyph and `other`.
+    ///
+    /// A `factor` of `0.0` gives this glyph and `1.0` gives `other`; values
+    /// outside that range extrapolate. See [`Glyph::weighted_sum`] for what
+    /// is interpolated.
+    pub fn interpolate(&self, other: &Glyph, factor: f64) -> Result<Glyph, InterpolationError> {
+        Glyph::weighted_sum(&[(self, 1.0 - factor), (other, factor)])
+    }
+
+    /// Returns the sum of several glyphs, each multiplied by its weight.
+    ///
+    /// The coordinates of points, anchors and guidelines, the transforms of
+    /// components, and the width and height are summed; everything else is
+    /// taken from the first glyph. The weights usually add up to one, but
+    /// need not, which allows for adding deltas to a default master.
+    ///
+    /// The glyphs must be compatible, as checked by
+    /// [`compat::check_glyphs`].
+    pub fn weighted_sum(masters: &[(&Glyph, f64)]) -> Result<Glyph, InterpolationError> {
+        let glyphs: Vec<&Glyph> = masters.iter().map(|(glyph, _)| *glyph).collect();
+        let first = glyphs.first().ok_or(InterpolationError::NoMasters)?;
+        let issues = compat::check_glyphs(&glyphs);
+        if !issues.is_empty() {
+            return Err(InterpolationError::Incompatible(issues));
+        }
+        let blend = |value: &dyn Fn(&Glyph) -> f32| -> f32 {
+            masters.iter().map(|(glyph, weight)| value(glyph) as f64 * weight).sum::<f64>() as f32
+        };
+
+        let mut result = (*first).clone();
+        result.width = blend(&|g| g.width);
+        result.height = blend(&|g| g.height);
+        for (c, contour) in result.contours.iter_mut().enumerate() {
+            for (p, point) in contour.points.iter_mut().enumerate() {
+                point.x = blend(&|g| g.contours[c].points[p].x);
+                point.y = blend(&|g| g.contours[c].points[p].y);
+            }
+        }
+        for (idx, component) in result.components.iter_mut().enumerate() {
+            let transform = &mut component.transform;
+            transform.x_scale = blend(&|g| g.components[idx].transform.x_scale);
+            transform.xy_scale = blend(&|g| g.components[idx].transform.xy_scale);
+            transform.yx_scale = blend(&|g| g.components[idx].transform.yx_scale);
+            transform.y_scale = blend(&|g| g.components[idx].transform.y_scale);
+            transform.x_offset = blend(&|g| g.components[idx].transform.x_offset);
+            transform.y_offset = blend(&|g| g.components[idx].transform.y_offset);
+        }
+        for (idx, anchor) in result.anchors.iter_mut().enumerate() {
+            anchor.x = blend(&|g| g.anchors[idx].x);
+            anchor.y = blend(&|g| g.anchors[idx].y);
+        }
+        for (idx, guideline) in result.guidelines.iter_mut().enumerate() {
+            let value = |n: usize| blend(&|g| line_values(&g.guidelines[idx].line)[n]);
+            guideline.line = match guideline.line {
+                Line::Vertical(_) => Line::Vertical(value(0)),
+                Line::Horizontal(_) => Line::Horizontal(value(1)),
+                Line::Angle { .. } => Line::Angle { x: value(0), y: value(1), degrees: value(2) },
+            };
+        }
+        Ok(result)
+    }
+}
+
+/// The `x`, `y` and angle of a line, with zero for those it does not have.
+fn line_values(line: &Line) -> [f32; 3] {
+    match *line {
+        Line::Vertical(x) => [x, 0.0, 0.0],
+        Line::Horizontal(y) => [0.0, y, 0.0],
+        Line::Angle { x, y, degrees } => [x, y, degrees],
+    }
+}
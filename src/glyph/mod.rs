@@ -1,8 +1,14 @@
 //! Data related to individual glyphs.
 
 pub mod builder;
+mod cu2qu;
+mod direction;
+mod interpolate;
 mod parse;
-mod serialize;
+mod qu2cu;
+mod round;
+pub(crate) mod segment;
+pub(crate) mod serialize;
 #[cfg(test)]
 mod tests;
 
@@ -14,8 +20,12 @@ use druid::{Data, Lens};
 
 use crate::error::{Error, ErrorKind, GlifError, GlifErrorInternal};
 use crate::names::NameList;
+use crate::pen::{Pen, PointPen, PointToSegmentPen};
 use crate::shared_types::PUBLIC_OBJECT_LIBS_KEY;
+#[cfg(feature = "kurbo")]
+use crate::Layer;
 use crate::{Color, Guideline, Identifier, Line, Plist};
+pub use round::RoundingOptions;
 
 /// The name of a glyph.
 pub type GlyphName = Arc<str>;
@@ -97,6 +107,121 @@ impl Glyph {
         }
     }
 
+    /// Draw the glyph's contours and components into a [`PointPen`].
+    ///
+    /// Contours are drawn first, followed by components. Point and contour
+    /// libs are not passed to the pen.
+    pub fn draw_points(&self, mut pen: impl PointPen) -> Result<(), ErrorKind> {
+        for contour in &self.contours {
+            contour.draw_points(&mut pen)?;
+        }
+        for component in &self.components {
+            pen.add_component(
+                component.base.clone(),
+                component.transform,
+                component.identifier.clone(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draw the glyph's contours and components into a segment [`Pen`].
+    pub fn draw(&self, pen: impl Pen) -> Result<(), ErrorKind> {
+        self.draw_points(PointToSegmentPen::new(pen))
+    }
+
+    /// Converts the glyph's whole outline to a [`kurbo::BezPath`].
+    ///
+    /// The path contains all contours, followed by the outlines of all components,
+    /// transformed into place. Components are resolved (recursively) in `layer`.
+    ///
+    /// Errors if a component's base glyph is missing from the layer, or if
+    /// components reference each other in a cycle.
+    #[cfg(feature = "kurbo")]
+    pub fn to_kurbo(&self, layer: &Layer) -> Result<kurbo::BezPath, Error> {
+        let mut path = kurbo::BezPath::new();
+        let mut visiting = vec![self.name.clone()];
+        self.append_to_kurbo(layer, kurbo::Affine::IDENTITY, &mut visiting, &mut path)?;
+        Ok(path)
+    }
+
+    #[cfg(feature = "kurbo")]
+    fn append_to_kurbo(
+        &self,
+        layer: &Layer,
+        transform: kurbo::Affine,
+        visiting: &mut Vec<GlyphName>,
+        path: &mut kurbo::BezPath,
+    ) -> Result<(), Error> {
+        for contour in &self.contours {
+            let mut contour_path = contour.to_kurbo()?;
+            contour_path.apply_affine(transform);
+            path.extend(contour_path);
+        }
+        for component in &self.components {
+            if visiting.contains(&component.base) {
+                return Err(Error::ComponentCycle(component.base.to_string()));
+            }
+            let base = layer.get_glyph(&*component.base).ok_or_else(|| Error::MissingGlyph {
+                layer: layer.name().to_string(),
+                glyph: component.base.to_string(),
+            })?;
+            visiting.push(component.base.clone());
+            let transform = transform * kurbo::Affine::from(component.transform);
+            base.append_to_kurbo(layer, transform, visiting, path)?;
+            visiting.pop();
+        }
+        Ok(())
+    }
+
+    /// Replaces the glyph's contours with those of a [`kurbo::BezPath`].
+    ///
+    /// Each subpath becomes one contour, as described in [`Contour::from_kurbo`].
+    /// Components, anchors and all other glyph data are left untouched.
+    #[cfg(feature = "kurbo")]
+    pub fn replace_contours_from_kurbo(&mut self, path: &kurbo::BezPath) -> Result<(), Error> {
+        self.contours = contours_from_kurbo(path)?;
+        Ok(())
+    }
+
+    /// Applies an affine transformation to the glyph.
+    ///
+    /// This transforms contour points, anchors, guidelines, and the image. The
+    /// transformation is also prepended to each component's transform, so that
+    /// components end up where they would if the glyph were decomposed first; if
+    /// the base glyphs are transformed as well, you probably only want to move
+    /// the component offsets instead.
+    ///
+    /// If `adjust_advance` is true, the advance width and height are scaled by
+    /// the horizontal and vertical scale factors of the transformation.
+    pub fn transform(&mut self, transform: AffineTransform, adjust_advance: bool) {
+        for contour in &mut self.contours {
+            for point in &mut contour.points {
+                let (x, y) = transform.transform_point((point.x, point.y));
+                point.x = x;
+                point.y = y;
+            }
+        }
+        for anchor in &mut self.anchors {
+            let (x, y) = transform.transform_point((anchor.x, anchor.y));
+            anchor.x = x;
+            anchor.y = y;
+        }
+        for guideline in &mut self.guidelines {
+            guideline.line = guideline.line.transformed(transform);
+        }
+        for component in &mut self.components {
+            component.transform = transform * component.transform;
+        }
+        if let Some(image) = &mut self.image {
+            image.transform = transform * image.transform;
+        }
+        if adjust_advance {
+            self.width *= transform.x_scale.abs();
+            self.height *= transform.y_scale.abs();
+        }
+    }
+
     /// Move libs from the lib's `public.objectLibs` into the actual objects.
     /// The key will be removed from the glyph lib.
     fn load_object_libs(&mut self) -> Result<(), ErrorKind> {
@@ -254,11 +379,33 @@ pub struct Contour {
 }
 
 impl Contour {
-    fn is_closed(&self) -> bool {
+    pub(crate) fn is_closed(&self) -> bool {
         self.points.first().map_or(true, |v| v.typ != PointType::Move)
     }
 
+    /// Draw the contour into a [`PointPen`].
+    pub fn draw_points(&self, mut pen: impl PointPen) -> Result<(), ErrorKind> {
+        pen.begin_path(self.identifier.clone())?;
+        for point in &self.points {
+            pen.add_point(
+                (point.x, point.y),
+                point.typ.clone(),
+                point.smooth,
+                point.name.clone(),
+                point.identifier.clone(),
+            )?;
+        }
+        pen.end_path()
+    }
+
+    /// Draw the contour into a segment [`Pen`].
+    pub fn draw(&self, pen: impl Pen) -> Result<(), ErrorKind> {
+        self.draw_points(PointToSegmentPen::new(pen))
+    }
+
     /// Converts the `Contour` to a [`kurbo::BezPath`].
+    ///
+    /// The path of a closed contour ends with a `ClosePath` element.
     #[cfg(feature = "kurbo")]
     pub fn to_kurbo(&self) -> Result<kurbo::BezPath, Error> {
         let mut path = kurbo::BezPath::new();
@@ -306,8 +453,136 @@ impl Contour {
                 }
             }
         }
+        if self.is_closed() && !self.points.is_empty() {
+            path.close_path();
+        }
         Ok(path)
     }
+
+    /// Converts a [`kurbo::BezPath`] containing a single subpath into a `Contour`.
+    ///
+    /// A subpath ending in `ClosePath` becomes a closed contour, any other becomes
+    /// an open contour starting with a [`PointType::Move`] point. Quadratic segments
+    /// become a `QCurve` point with one off-curve, cubic segments a `Curve` point
+    /// with two. An on-curve point is marked smooth if it joins a curve to another
+    /// segment with collinear tangents.
+    ///
+    /// Errors if the path contains more than one subpath; use
+    /// [`Glyph::replace_contours_from_kurbo`] for those.
+    #[cfg(feature = "kurbo")]
+    pub fn from_kurbo(path: &kurbo::BezPath) -> Result<Contour, Error> {
+        let mut contours = contours_from_kurbo(path)?;
+        match contours.len() {
+            0 => Ok(Contour::default()),
+            1 => Ok(contours.remove(0)),
+            _ => Err(Error::ConvertContour(ErrorKind::MultipleSubpaths)),
+        }
+    }
+}
+
+/// The largest sine of the angle between two tangents that we still consider
+/// collinear when inferring smooth points.
+const SMOOTH_TOLERANCE: f64 = 0.01;
+
+/// Splits a [`kurbo::BezPath`] into contours, one per subpath.
+///
+/// Subpaths consisting only of a `MoveTo` are skipped.
+#[cfg(feature = "kurbo")]
+fn contours_from_kurbo(path: &kurbo::BezPath) -> Result<Vec<Contour>, Error> {
+    use kurbo::PathEl;
+
+    fn point(pt: kurbo::Point, typ: PointType) -> ContourPoint {
+        ContourPoint::new(pt.x as f32, pt.y as f32, typ, false, None, None, None)
+    }
+
+    let mut contours = Vec::new();
+    let mut current: Option<Vec<ContourPoint>> = None;
+    for element in path.elements() {
+        match *element {
+            PathEl::MoveTo(pt) => {
+                if let Some(points) = current.take() {
+                    if points.len() > 1 {
+                        contours.push(contour_from_path_points(points, false));
+                    }
+                }
+                current = Some(vec![point(pt, PointType::Move)]);
+            }
+            PathEl::ClosePath => {
+                let points =
+                    current.take().ok_or(Error::ConvertContour(ErrorKind::PenPathNotStarted))?;
+                if points.len() > 1 {
+                    contours.push(contour_from_path_points(points, true));
+                }
+            }
+            _ => {
+                let points =
+                    current.as_mut().ok_or(Error::ConvertContour(ErrorKind::PenPathNotStarted))?;
+                match *element {
+                    PathEl::LineTo(pt) => points.push(point(pt, PointType::Line)),
+                    PathEl::QuadTo(p1, p2) => {
+                        points.push(point(p1, PointType::OffCurve));
+                        points.push(point(p2, PointType::QCurve));
+                    }
+                    PathEl::CurveTo(p1, p2, p3) => {
+                        points.push(point(p1, PointType::OffCurve));
+                        points.push(point(p2, PointType::OffCurve));
+                        points.push(point(p3, PointType::Curve));
+                    }
+                    PathEl::MoveTo(_) | PathEl::ClosePath => unreachable!(),
+                }
+            }
+        }
+    }
+    if let Some(points) = current {
+        if points.len() > 1 {
+            contours.push(contour_from_path_points(points, false));
+        }
+    }
+    Ok(contours)
+}
+
+/// Finishes a contour from the points of one subpath, starting with a move.
+pub(crate) fn contour_from_path_points(mut points: Vec<ContourPoint>, closed: bool) -> Contour {
+    if closed {
+        let last = points.len() - 1;
+        if points[0].x == points[last].x && points[0].y == points[last].y {
+            // The last segment ends where we started; it replaces the move.
+            points.remove(0);
+        } else {
+            // There is an implied line back to the start.
+            points[0].typ = PointType::Line;
+        }
+    }
+
+    let len = points.len();
+    for idx in 0..len {
+        if points[idx].typ == PointType::OffCurve {
+            continue;
+        }
+        let (prev, next) = if closed {
+            ((idx + len - 1) % len, (idx + 1) % len)
+        } else if idx == 0 || idx == len - 1 {
+            continue;
+        } else {
+            (idx - 1, idx + 1)
+        };
+        let (prev, pt, next) = (&points[prev], &points[idx], &points[next]);
+        // Two lines meeting are never smooth.
+        if prev.typ != PointType::OffCurve && next.typ != PointType::OffCurve {
+            continue;
+        }
+        let (ax, ay) = ((pt.x - prev.x) as f64, (pt.y - prev.y) as f64);
+        let (bx, by) = ((next.x - pt.x) as f64, (next.y - pt.y) as f64);
+        let lengths = ax.hypot(ay) * bx.hypot(by);
+        if lengths == 0.0 {
+            continue;
+        }
+        let cross = ax * by - ay * bx;
+        let dot = ax * bx + ay * by;
+        points[idx].smooth = dot > 0.0 && (cross / lengths).abs() < SMOOTH_TOLERANCE;
+    }
+
+    Contour::new(points, None, None)
 }
 
 /// A single point in a [`Contour`].
@@ -602,6 +877,40 @@ impl AffineTransform {
             y_offset: 0.,
         }
     }
+
+    /// Applies the transformation to a point.
+    pub fn transform_point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.x_scale * x + self.yx_scale * y + self.x_offset,
+            self.xy_scale * x + self.y_scale * y + self.y_offset,
+        )
+    }
+
+    /// Applies the transformation to a vector, ignoring the offset.
+    pub(crate) fn transform_vector(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (self.x_scale * x + self.yx_scale * y, self.xy_scale * x + self.y_scale * y)
+    }
+}
+
+/// Composes two transformations. As with matrices, `a * b` applies `b` first,
+/// then `a`.
+impl std::ops::Mul for AffineTransform {
+    type Output = AffineTransform;
+
+    fn mul(self, other: AffineTransform) -> AffineTransform {
+        AffineTransform {
+            x_scale: self.x_scale * other.x_scale + self.yx_scale * other.xy_scale,
+            xy_scale: self.xy_scale * other.x_scale + self.y_scale * other.xy_scale,
+            yx_scale: self.x_scale * other.yx_scale + self.yx_scale * other.y_scale,
+            y_scale: self.xy_scale * other.yx_scale + self.y_scale * other.y_scale,
+            x_offset: self.x_scale * other.x_offset
+                + self.yx_scale * other.y_offset
+                + self.x_offset,
+            y_offset: self.xy_scale * other.x_offset
+                + self.y_scale * other.y_offset
+                + self.y_offset,
+        }
+    }
 }
 
 impl std::default::Default for AffineTransform {
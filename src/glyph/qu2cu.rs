@@ -0,0 +1,93 @@
+//! Converting quadratic splines to cubic curves.
+
+use super::segment::Point;
+use crate::error::CurveConversionError;
+use crate::{Contour, ContourPoint, Glyph, PointType};
+
+impl Contour {
+    /// Convert quadratic curves to cubic curves.
+    ///
+    /// Every quadratic segment becomes a cubic with two off-curves, which is
+    /// exact. The implied on-curve points between consecutive off-curves of a
+    /// [`PointType::QCurve`] spline are made explicit as smooth
+    /// [`PointType::Curve`] points, and a `QCurve` without off-curves becomes
+    /// a [`PointType::Line`]. Other points keep their attributes.
+    pub fn convert_to_cubic(&mut self) -> Result<(), CurveConversionError> {
+        let mut runs = self.point_runs()?;
+        for run in 0..runs.runs.len() {
+            let points = &runs.runs[run];
+            let (end, off_curves) = match points.split_last() {
+                Some((end, off_curves)) => (end, off_curves),
+                None => continue,
+            };
+            let off_curves: Vec<Point> = off_curves.iter().map(Point::from).collect();
+            let converted = match (&end.typ, off_curves.len()) {
+                (PointType::QCurve, 0) => {
+                    let mut end = end.clone();
+                    end.typ = PointType::Line;
+                    vec![end]
+                }
+                (PointType::QCurve, _) | (PointType::Curve, 1) => {
+                    let start = runs.segment_start(run).expect("curves start on-curve");
+                    let mut end = end.clone();
+                    end.typ = PointType::Curve;
+                    quadratic_to_cubic(start.into(), &off_curves, end)
+                }
+                (PointType::OffCurve, _) => {
+                    // A closed contour without on-curve points; it starts
+                    // and ends at the implied point between its last and
+                    // first off-curve.
+                    let off_curves: Vec<Point> = points.iter().map(Point::from).collect();
+                    let start = off_curves[off_curves.len() - 1].midpoint(off_curves[0]);
+                    let end = start.to_contour_point(PointType::Curve, true);
+                    quadratic_to_cubic(start, &off_curves, end)
+                }
+                _ => continue,
+            };
+            runs.runs[run] = converted;
+        }
+        self.points = runs.into_points();
+        Ok(())
+    }
+}
+
+impl Glyph {
+    /// Convert the quadratic curves in all contours to cubic curves.
+    ///
+    /// See [`Contour::convert_to_cubic`] for details. Nothing is changed if
+    /// an error occurs.
+    pub fn convert_to_cubic(&mut self) -> Result<(), CurveConversionError> {
+        let mut contours = self.contours.clone();
+        for contour in &mut contours {
+            contour.convert_to_cubic()?;
+        }
+        self.contours = contours;
+        Ok(())
+    }
+}
+
+/// Convert a quadratic spline to the points of cubic segments, following
+/// the point `start` and ending with `end`.
+fn quadratic_to_cubic(start: Point, off_curves: &[Point], end: ContourPoint) -> Vec<ContourPoint> {
+    let mut points = Vec::with_capacity(off_curves.len() * 3);
+    let mut current = start;
+    for (idx, &control) in off_curves.iter().enumerate() {
+        let next = match off_curves.get(idx + 1) {
+            Some(&next_control) => control.midpoint(next_control),
+            None => (&end).into(),
+        };
+        points.push(
+            (current + (control - current) * (2.0 / 3.0))
+                .to_contour_point(PointType::OffCurve, false),
+        );
+        points.push(
+            (next + (control - next) * (2.0 / 3.0)).to_contour_point(PointType::OffCurve, false),
+        );
+        if idx + 1 < off_curves.len() {
+            points.push(next.to_contour_point(PointType::Curve, true));
+        }
+        current = next;
+    }
+    points.push(end);
+    points
+}
@@ -0,0 +1,209 @@
+//! Rounding coordinates to a grid.
+
+use super::segment::Point;
+use crate::{Contour, ContourPoint, Glyph, PointType};
+
+/// Options for rounding the coordinates of glyphs.
+///
+/// By default, all coordinates are rounded to integers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct RoundingOptions {
+    /// The grid to round to. Coordinates are left alone if this is not positive.
+    pub grid: f32,
+    /// Round only on-curve points, moving off-curves along with the on-curve
+    /// points they are attached to.
+    pub only_on_curve: bool,
+    /// Round component offsets.
+    pub components: bool,
+    /// Round advance widths and heights.
+    pub advance: bool,
+    /// Round kerning values, when rounding a whole font.
+    pub kerning: bool,
+}
+
+impl RoundingOptions {
+    /// Set the grid to round to.
+    pub fn grid(&mut self, grid: f32) -> &mut Self {
+        self.grid = grid;
+        self
+    }
+
+    /// Request that only on-curve points be rounded.
+    pub fn only_on_curve(&mut self, b: bool) -> &mut Self {
+        self.only_on_curve = b;
+        self
+    }
+
+    /// Request that component offsets be rounded.
+    pub fn components(&mut self, b: bool) -> &mut Self {
+        self.components = b;
+        self
+    }
+
+    /// Request that advance widths and heights be rounded.
+    pub fn advance(&mut self, b: bool) -> &mut Self {
+        self.advance = b;
+        self
+    }
+
+    /// Request that kerning values be rounded, when rounding a whole font.
+    pub fn kerning(&mut self, b: bool) -> &mut Self {
+        self.kerning = b;
+        self
+    }
+
+    fn round(&self, value: f32) -> f32 {
+        // adding zero turns -0 into 0
+        (value / self.grid).round() * self.grid + 0.0
+    }
+}
+
+impl Default for RoundingOptions {
+    fn default() -> Self {
+        RoundingOptions {
+            grid: 1.0,
+            only_on_curve: false,
+            components: true,
+            advance: true,
+            kerning: true,
+        }
+    }
+}
+
+impl Glyph {
+    /// Round the coordinates of points and anchors, and optionally those of
+    /// components and the advance, to a grid.
+    ///
+    /// Where rounding would break the collinearity of the handles around a
+    /// smooth point, a nearby grid position that keeps them collinear is
+    /// used for the off-curve instead, if there is one.
+    pub fn round_coordinates(&mut self, options: &RoundingOptions) {
+        if options.grid <= 0.0 || options.grid.is_nan() {
+            return;
+        }
+        for contour in &mut self.contours {
+            round_contour(contour, options);
+        }
+        for anchor in &mut self.anchors {
+            anchor.x = options.round(anchor.x);
+            anchor.y = options.round(anchor.y);
+        }
+        if options.components {
+            for component in &mut self.components {
+                component.transform.x_offset = options.round(component.transform.x_offset);
+                component.transform.y_offset = options.round(component.transform.y_offset);
+            }
+        }
+        if options.advance {
+            self.width = options.round(self.width);
+            self.height = options.round(self.height);
+        }
+    }
+}
+
+fn round_contour(contour: &mut Contour, options: &RoundingOptions) {
+    let len = contour.points.len();
+    let closed = contour.is_closed();
+    let original: Vec<Point> = contour.points.iter().map(Point::from).collect();
+    let neighbor = |idx: usize, forward: bool| -> Option<usize> {
+        match (forward, closed) {
+            (true, true) => Some((idx + 1) % len),
+            (false, true) => Some((idx + len - 1) % len),
+            (true, false) => Some(idx + 1).filter(|&next| next < len),
+            (false, false) => idx.checked_sub(1),
+        }
+    };
+
+    for point in &mut contour.points {
+        if !options.only_on_curve || point.typ != PointType::OffCurve {
+            point.x = options.round(point.x);
+            point.y = options.round(point.y);
+        }
+    }
+
+    if options.only_on_curve {
+        // Off-curves follow their on-curve points, which keeps the handles'
+        // directions; an off-curve between two on-curves moves half as far
+        // as each.
+        for idx in 0..len {
+            if contour.points[idx].typ != PointType::OffCurve {
+                continue;
+            }
+            let deltas: Vec<Point> = [neighbor(idx, false), neighbor(idx, true)]
+                .iter()
+                .flatten()
+                .filter(|&&n| contour.points[n].typ != PointType::OffCurve)
+                .map(|&n| Point::from(&contour.points[n]) - original[n])
+                .collect();
+            if !deltas.is_empty() {
+                let delta = deltas.iter().fold(Point::new(0.0, 0.0), |sum, &d| sum + d)
+                    * (1.0 / deltas.len() as f64);
+                contour.points[idx].x = (original[idx].x + delta.x) as f32;
+                contour.points[idx].y = (original[idx].y + delta.y) as f32;
+            }
+        }
+        return;
+    }
+
+    for idx in 0..len {
+        let point = &contour.points[idx];
+        if !point.smooth || point.typ == PointType::OffCurve {
+            continue;
+        }
+        let (prev, next) = match (neighbor(idx, false), neighbor(idx, true)) {
+            (Some(prev), Some(next)) if prev != next => (prev, next),
+            _ => continue,
+        };
+        let center = Point::from(point);
+        let (a, b) = (Point::from(&contour.points[prev]), Point::from(&contour.points[next]));
+        if (center - a).cross(b - center) == 0.0 {
+            continue;
+        }
+        // Try moving either off-curve, keeping the other neighbor fixed.
+        let best = [(prev, next), (next, prev)]
+            .iter()
+            .filter(|(moved, _)| contour.points[*moved].typ == PointType::OffCurve)
+            .filter_map(|&(moved, fixed)| {
+                let direction = center - Point::from(&contour.points[fixed]);
+                collinear_candidate(
+                    &contour.points[moved],
+                    original[moved],
+                    center,
+                    direction,
+                    options,
+                )
+                .map(|candidate| (moved, candidate))
+            })
+            .min_by(|a, b| (a.1 - original[a.0]).hypot().total_cmp(&(b.1 - original[b.0]).hypot()));
+        if let Some((moved, position)) = best {
+            contour.points[moved].x = position.x as f32;
+            contour.points[moved].y = position.y as f32;
+        }
+    }
+}
+
+/// The grid position next to the rounded off-curve that lies on the ray
+/// from `center` in `direction` and is closest to the off-curve's original
+/// position, if any.
+fn collinear_candidate(
+    rounded: &ContourPoint,
+    original: Point,
+    center: Point,
+    direction: Point,
+    options: &RoundingOptions,
+) -> Option<Point> {
+    if direction.hypot() == 0.0 {
+        return None;
+    }
+    let grid = options.grid as f64;
+    let rounded = Point::from(rounded);
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+        .map(|(dx, dy)| rounded + Point::new(dx as f64 * grid, dy as f64 * grid))
+        .filter(|candidate| {
+            let handle = *candidate - center;
+            handle.cross(direction) == 0.0 && handle.dot(direction) > 0.0
+        })
+        .min_by(|a, b| (*a - original).hypot().total_cmp(&(*b - original).hypot()))
+}
@@ -0,0 +1,450 @@
+//! Crate-internal bezier geometry, used by the outline operations on
+//! [`Contour`] and [`Glyph`].
+//!
+//! [`Glyph`]: crate::Glyph
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::error::ErrorKind;
+use crate::pen::Pen;
+use crate::{AffineTransform, Contour, ContourPoint, GlyphName, PointType};
+
+/// A point in double precision, for intermediate computations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Point {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+}
+
+impl Point {
+    pub(crate) fn new(x: f64, y: f64) -> Self {
+        Point { x, y }
+    }
+
+    pub(crate) fn cross(self, other: Point) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub(crate) fn dot(self, other: Point) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub(crate) fn hypot(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    pub(crate) fn lerp(self, other: Point, t: f64) -> Point {
+        self + (other - self) * t
+    }
+
+    pub(crate) fn midpoint(self, other: Point) -> Point {
+        self.lerp(other, 0.5)
+    }
+}
+
+impl From<(f32, f32)> for Point {
+    fn from((x, y): (f32, f32)) -> Self {
+        Point::new(x as f64, y as f64)
+    }
+}
+
+impl From<&ContourPoint> for Point {
+    fn from(point: &ContourPoint) -> Self {
+        Point::new(point.x as f64, point.y as f64)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f64> for Point {
+    type Output = Point;
+
+    fn mul(self, factor: f64) -> Point {
+        Point::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Point {
+    /// A new contour point at this position, without name or identifier.
+    pub(crate) fn to_contour_point(self, typ: PointType, smooth: bool) -> ContourPoint {
+        ContourPoint::new(self.x as f32, self.y as f32, typ, smooth, None, None, None)
+    }
+}
+
+/// A single line, quadratic or cubic bezier segment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Segment {
+    Line(Point, Point),
+    Quad(Point, Point, Point),
+    Cubic(Point, Point, Point, Point),
+}
+
+impl Segment {
+    pub(crate) fn start(&self) -> Point {
+        match *self {
+            Segment::Line(p0, _) | Segment::Quad(p0, ..) | Segment::Cubic(p0, ..) => p0,
+        }
+    }
+
+    pub(crate) fn end(&self) -> Point {
+        match *self {
+            Segment::Line(_, p1) => p1,
+            Segment::Quad(.., p2) => p2,
+            Segment::Cubic(.., p3) => p3,
+        }
+    }
+
+    /// Evaluate the segment at `t` in `0.0..=1.0`.
+    pub(crate) fn eval(&self, t: f64) -> Point {
+        let mt = 1.0 - t;
+        match *self {
+            Segment::Line(p0, p1) => p0.lerp(p1, t),
+            Segment::Quad(p0, p1, p2) => p0 * (mt * mt) + p1 * (2.0 * mt * t) + p2 * (t * t),
+            Segment::Cubic(p0, p1, p2, p3) => {
+                p0 * (mt * mt * mt)
+                    + p1 * (3.0 * mt * mt * t)
+                    + p2 * (3.0 * mt * t * t)
+                    + p3 * (t * t * t)
+            }
+        }
+    }
+
+    /// The signed area between the segment and the origin, i.e. the
+    /// segment's contribution to the area of a closed path (Green's theorem).
+    pub(crate) fn area(&self) -> f64 {
+        match *self {
+            Segment::Line(p0, p1) => p0.cross(p1) / 2.0,
+            Segment::Quad(p0, p1, p2) => {
+                (2.0 * p0.cross(p1) + 2.0 * p1.cross(p2) + p0.cross(p2)) / 6.0
+            }
+            Segment::Cubic(p0, p1, p2, p3) => {
+                (6.0 * p0.cross(p1)
+                    + 3.0 * p0.cross(p2)
+                    + p0.cross(p3)
+                    + 3.0 * p1.cross(p2)
+                    + 3.0 * p1.cross(p3)
+                    + 6.0 * p2.cross(p3))
+                    / 20.0
+            }
+        }
+    }
+
+    /// The segment traversed in the opposite direction.
+    pub(crate) fn reversed(&self) -> Segment {
+        match *self {
+            Segment::Line(p0, p1) => Segment::Line(p1, p0),
+            Segment::Quad(p0, p1, p2) => Segment::Quad(p2, p1, p0),
+            Segment::Cubic(p0, p1, p2, p3) => Segment::Cubic(p3, p2, p1, p0),
+        }
+    }
+
+    /// The segment with its end points moved to `start` and `end`.
+    pub(crate) fn with_end_points(&self, start: Point, end: Point) -> Segment {
+        match *self {
+            Segment::Line(..) => Segment::Line(start, end),
+            Segment::Quad(_, p1, _) => Segment::Quad(start, p1, end),
+            Segment::Cubic(_, p1, p2, _) => Segment::Cubic(start, p1, p2, end),
+        }
+    }
+
+    /// Split the segment in two at `t`.
+    pub(crate) fn split(&self, t: f64) -> (Segment, Segment) {
+        match *self {
+            Segment::Line(p0, p1) => {
+                let mid = p0.lerp(p1, t);
+                (Segment::Line(p0, mid), Segment::Line(mid, p1))
+            }
+            Segment::Quad(p0, p1, p2) => {
+                let (a, b) = (p0.lerp(p1, t), p1.lerp(p2, t));
+                let mid = a.lerp(b, t);
+                (Segment::Quad(p0, a, mid), Segment::Quad(mid, b, p2))
+            }
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let (a, b, c) = (p0.lerp(p1, t), p1.lerp(p2, t), p2.lerp(p3, t));
+                let (d, e) = (a.lerp(b, t), b.lerp(c, t));
+                let mid = d.lerp(e, t);
+                (Segment::Cubic(p0, a, d, mid), Segment::Cubic(mid, e, c, p3))
+            }
+        }
+    }
+
+    /// The part of the segment between `t0` and `t1`, reversed if `t1` is
+    /// smaller than `t0`.
+    pub(crate) fn subsegment(&self, t0: f64, t1: f64) -> Segment {
+        if t1 < t0 {
+            return self.subsegment(t1, t0).reversed();
+        }
+        let tail = if t0 > 0.0 { self.split(t0).1 } else { *self };
+        if t1 < 1.0 && t0 < 1.0 {
+            tail.split((t1 - t0) / (1.0 - t0)).0
+        } else {
+            tail
+        }
+    }
+
+    /// The number of straight lines needed to approximate the segment within
+    /// `tolerance`.
+    pub(crate) fn flatten_steps(&self, tolerance: f64) -> usize {
+        // The distance between a curve and its chord is bounded by an eighth
+        // of the curve's largest second derivative.
+        let steps = match *self {
+            Segment::Line(..) => return 1,
+            Segment::Quad(p0, p1, p2) => ((p0 - p1 * 2.0 + p2).hypot() / (4.0 * tolerance)).sqrt(),
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let dd = (p0 - p1 * 2.0 + p2).hypot().max((p1 - p2 * 2.0 + p3).hypot());
+                (3.0 * dd / (4.0 * tolerance)).sqrt()
+            }
+        };
+        (steps.ceil() as usize).max(1)
+    }
+
+    /// Approximate the segment with straight lines, appending the points
+    /// after the start point to `out`.
+    pub(crate) fn flatten_into(&self, out: &mut Vec<Point>) {
+        const CURVE_STEPS: usize = 16;
+        match self {
+            Segment::Line(_, p1) => out.push(*p1),
+            _ => out.extend((1..=CURVE_STEPS).map(|i| self.eval(i as f64 / CURVE_STEPS as f64))),
+        }
+    }
+}
+
+impl Contour {
+    /// Decompose the contour into segments.
+    ///
+    /// Quadratic splines are split into individual quadratic segments at
+    /// their implied on-curve points. Closed contours include the closing
+    /// line, if any.
+    pub(crate) fn segments(&self) -> Result<Vec<Segment>, ErrorKind> {
+        let mut pen = SegmentCollector::default();
+        self.draw(&mut pen)?;
+        Ok(pen.segments)
+    }
+
+    /// Approximate the contour as a polygon. The polygon is implicitly closed.
+    pub(crate) fn flatten(&self) -> Result<Vec<Point>, ErrorKind> {
+        let segments = self.segments()?;
+        let mut points = Vec::with_capacity(segments.len() * 4);
+        if let Some(first) = segments.first() {
+            points.push(first.start());
+        }
+        for segment in &segments {
+            segment.flatten_into(&mut points);
+        }
+        Ok(points)
+    }
+}
+
+/// The points of a contour, grouped by segment.
+///
+/// Each run holds the off-curves of a segment followed by the on-curve point
+/// ending it. The only exception is a closed contour made entirely of
+/// off-curves, which is a single run without an on-curve point.
+#[derive(Debug, Clone)]
+pub(crate) struct PointRuns {
+    /// The move point an open contour starts at.
+    pub(crate) start: Option<ContourPoint>,
+    pub(crate) runs: Vec<Vec<ContourPoint>>,
+    /// The number of off-curves a closed contour ended with, which belong
+    /// to the last run.
+    trailing_off_curves: usize,
+}
+
+impl PointRuns {
+    /// The on-curve point the segment of the given run starts at, if any.
+    pub(crate) fn segment_start(&self, run: usize) -> Option<&ContourPoint> {
+        match run.checked_sub(1) {
+            Some(prev) => self.runs[prev].last(),
+            None => self.start.as_ref().or_else(|| self.runs.last()?.last()),
+        }
+        .filter(|point| point.typ != PointType::OffCurve)
+    }
+
+    /// Reassemble the points, in the order of the original contour.
+    pub(crate) fn into_points(self) -> Vec<ContourPoint> {
+        // A closed contour was rotated to end on an on-curve point; undo that.
+        let rotation = match (&self.start, self.runs.last()) {
+            (None, Some(last)) => last.len() - self.trailing_off_curves.min(last.len() - 1),
+            _ => 0,
+        };
+        let mut points: Vec<_> =
+            self.start.into_iter().chain(self.runs.into_iter().flatten()).collect();
+        points.rotate_right(rotation);
+        points
+    }
+}
+
+impl Contour {
+    /// Group the contour's points by segment.
+    pub(crate) fn point_runs(&self) -> Result<PointRuns, ErrorKind> {
+        let mut points = self.points.clone();
+        if !self.is_closed() {
+            let rest = points.split_off(1);
+            if matches!(rest.last(), Some(point) if point.typ == PointType::OffCurve) {
+                return Err(ErrorKind::TrailingOffCurves);
+            }
+            let runs = split_runs(rest);
+            return Ok(PointRuns { start: points.pop(), runs, trailing_off_curves: 0 });
+        }
+        let is_on_curve = |point: &ContourPoint| point.typ != PointType::OffCurve;
+        let runs = match points.iter().position(is_on_curve) {
+            Some(idx) => {
+                points.rotate_left(idx + 1);
+                split_runs(points)
+            }
+            None if points.is_empty() => Vec::new(),
+            None => vec![points],
+        };
+        let trailing_off_curves =
+            self.points.iter().rposition(is_on_curve).map_or(0, |idx| self.points.len() - idx - 1);
+        Ok(PointRuns { start: None, runs, trailing_off_curves })
+    }
+}
+
+/// Split points after each on-curve point.
+fn split_runs(points: Vec<ContourPoint>) -> Vec<Vec<ContourPoint>> {
+    let mut runs = Vec::new();
+    let mut run = Vec::new();
+    for point in points {
+        let is_on_curve = point.typ != PointType::OffCurve;
+        run.push(point);
+        if is_on_curve {
+            runs.push(std::mem::take(&mut run));
+        }
+    }
+    if !run.is_empty() {
+        runs.push(run);
+    }
+    runs
+}
+
+/// The winding number of `polygon` around `point`.
+pub(crate) fn winding_number(polygon: &[Point], point: Point) -> i32 {
+    polygon
+        .iter()
+        .enumerate()
+        .map(|(idx, &p0)| winding_contribution(p0, polygon[(idx + 1) % polygon.len()], point))
+        .sum()
+}
+
+/// How the edge from `p0` to `p1` contributes to the winding number around
+/// `point`: whether it crosses a horizontal ray from `point` to the right,
+/// and in which direction.
+pub(crate) fn winding_contribution(p0: Point, p1: Point, point: Point) -> i32 {
+    let side = (p1 - p0).cross(point - p0);
+    if p0.y <= point.y {
+        if p1.y > point.y && side > 0.0 {
+            return 1;
+        }
+    } else if p1.y <= point.y && side < 0.0 {
+        return -1;
+    }
+    0
+}
+
+/// A [`Pen`] collecting the segments drawn into it.
+#[derive(Debug, Default)]
+struct SegmentCollector {
+    segments: Vec<Segment>,
+    start: Option<Point>,
+    current: Option<Point>,
+}
+
+impl SegmentCollector {
+    fn current(&self) -> Result<Point, ErrorKind> {
+        self.current.ok_or(ErrorKind::PenPathNotStarted)
+    }
+
+    fn push(&mut self, segment: Segment) {
+        self.current = Some(segment.end());
+        self.segments.push(segment);
+    }
+}
+
+impl Pen for SegmentCollector {
+    fn move_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind> {
+        self.start = Some(point.into());
+        self.current = self.start;
+        Ok(())
+    }
+
+    fn line_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind> {
+        let current = self.current()?;
+        self.push(Segment::Line(current, point.into()));
+        Ok(())
+    }
+
+    fn curve_to(
+        &mut self,
+        control1: (f32, f32),
+        control2: (f32, f32),
+        point: (f32, f32),
+    ) -> Result<(), ErrorKind> {
+        let current = self.current()?;
+        self.push(Segment::Cubic(current, control1.into(), control2.into(), point.into()));
+        Ok(())
+    }
+
+    fn qcurve_to(
+        &mut self,
+        off_curves: &[(f32, f32)],
+        on_curve: Option<(f32, f32)>,
+    ) -> Result<(), ErrorKind> {
+        let off_curves: Vec<Point> = off_curves.iter().map(|&pt| pt.into()).collect();
+        let (first, last) = match (off_curves.first(), off_curves.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return on_curve.map_or(Ok(()), |pt| self.line_to(pt)),
+        };
+        let end = match on_curve {
+            Some(pt) => pt.into(),
+            None => {
+                // A contour without on-curve points starts and ends halfway
+                // between its last and first off-curve.
+                let start = last.midpoint(first);
+                self.start = Some(start);
+                self.current = Some(start);
+                start
+            }
+        };
+        let mut current = self.current()?;
+        for (idx, &control) in off_curves.iter().enumerate() {
+            let next = off_curves.get(idx + 1).map_or(end, |&next| control.midpoint(next));
+            self.push(Segment::Quad(current, control, next));
+            current = next;
+        }
+        Ok(())
+    }
+
+    fn close_path(&mut self) -> Result<(), ErrorKind> {
+        if let (Some(start), Some(current)) = (self.start, self.current) {
+            if start != current {
+                self.push(Segment::Line(current, start));
+            }
+        }
+        self.end_path()
+    }
+
+    fn end_path(&mut self) -> Result<(), ErrorKind> {
+        self.start = None;
+        self.current = None;
+        Ok(())
+    }
+
+    fn add_component(&mut self, _: GlyphName, _: AffineTransform) -> Result<(), ErrorKind> {
+        Ok(())
+    }
+}
@@ -83,9 +83,9 @@ impl Glyph {
         if !object_libs.is_empty() {
             let mut new_lib = self.lib.clone();
             new_lib.insert(PUBLIC_OBJECT_LIBS_KEY.into(), plist::Value::Dictionary(object_libs));
-            write_lib_section(&new_lib, &mut writer)?;
+            write_lib_section(&new_lib, &mut writer, 1)?;
         } else if !self.lib.is_empty() {
-            write_lib_section(&self.lib, &mut writer)?;
+            write_lib_section(&self.lib, &mut writer, 1)?;
         }
 
         if let Some(ref note) = self.note {
@@ -110,9 +110,14 @@ impl Glyph {
 ///
 /// We then take this and write it into the middle of our active write session.
 ///
-/// By a lovely coincidence the whitespace is the same in both places; if this
-/// changes we will need to do custom whitespace handling.
-fn write_lib_section<T: Write>(lib: &Plist, writer: &mut Writer<T>) -> Result<(), WriteError> {
+/// The writer is expected to indent with tabs, and `depth` is the nesting
+/// depth of the `<lib>` element, which is one for a glif; the lines of the
+/// plist are indented to match.
+pub(crate) fn write_lib_section<T: Write>(
+    lib: &Plist,
+    writer: &mut Writer<T>,
+    depth: usize,
+) -> Result<(), WriteError> {
     let as_value: plist::Value = lib.to_owned().into();
     let mut out_buffer = Vec::with_capacity(256); // a reasonable min size?
     as_value.to_writer_xml(&mut out_buffer)?;
@@ -126,9 +131,10 @@ fn write_lib_section<T: Write>(lib: &Plist, writer: &mut Writer<T>) -> Result<()
     let end_idx = lib_xml.find(footer).ok_or(WriteError::InternalLibWriteError)?;
     let to_write = &lib_xml[start_idx..end_idx];
 
+    let indent = format!("\n{}", "\t".repeat(depth + 1));
     writer.write_event(Event::Start(BytesStart::borrowed_name(b"lib")))?;
     for line in to_write.lines() {
-        writer.inner().write_all("\n\t\t".as_bytes())?;
+        writer.inner().write_all(indent.as_bytes())?;
         writer.inner().write_all(line.as_bytes())?;
     }
     writer.write_event(Event::End(BytesEnd::borrowed(b"lib")))?;
@@ -263,7 +269,7 @@ impl ContourPoint {
 }
 
 impl PointType {
-    fn as_str(&self) -> &str {
+    pub(crate) fn as_str(&self) -> &str {
         match self {
             PointType::Move => "move",
             PointType::Line => "line",
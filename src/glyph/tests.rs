@@ -1,5 +1,6 @@
 use super::parse::parse_glyph;
 use super::*;
+use crate::error::{CurveConversionError, InterpolationError};
 use std::path::PathBuf;
 
 #[test]
@@ -8,6 +9,68 @@ fn transform() {
     assert_eq!(transform.x_scale, 1.0);
 }
 
+#[test]
+fn compose_transforms() {
+    let scale = AffineTransform { x_scale: 2.0, y_scale: 3.0, ..Default::default() };
+    let shift = AffineTransform { x_offset: 10.0, y_offset: 20.0, ..Default::default() };
+    // Scale first, then shift.
+    assert_eq!((shift * scale).transform_point((1.0, 1.0)), (12.0, 23.0));
+    // Shift first, then scale.
+    assert_eq!((scale * shift).transform_point((1.0, 1.0)), (22.0, 63.0));
+    assert_eq!(scale * AffineTransform::default(), scale);
+}
+
+#[test]
+fn transform_glyph() {
+    let mut glyph = Glyph::new_named("a");
+    glyph.width = 500.0;
+    glyph.contours.push(Contour::new(
+        vec![ContourPoint::new(100.0, 200.0, PointType::Line, false, None, None, None)],
+        None,
+        None,
+    ));
+    glyph.anchors.push(Anchor::new(50.0, 0.0, Some("top".into()), None, None, None));
+    glyph.guidelines.push(Guideline::new(Line::Vertical(10.0), None, None, None, None));
+    glyph.guidelines.push(Guideline::new(Line::Horizontal(10.0), None, None, None, None));
+    glyph.components.push(Component::new(
+        "b".into(),
+        AffineTransform { x_offset: 5.0, ..Default::default() },
+        None,
+        None,
+    ));
+
+    // Mirror horizontally around the middle of the advance.
+    let mirror = AffineTransform { x_scale: -1.0, x_offset: 500.0, ..Default::default() };
+    let mut mirrored = glyph.clone();
+    mirrored.transform(mirror, true);
+    assert_eq!(mirrored.width, 500.0);
+    assert_eq!(
+        (mirrored.contours[0].points[0].x, mirrored.contours[0].points[0].y),
+        (400.0, 200.0)
+    );
+    assert_eq!((mirrored.anchors[0].x, mirrored.anchors[0].y), (450.0, 0.0));
+    assert_eq!(mirrored.guidelines[0].line, Line::Vertical(490.0));
+    assert_eq!(mirrored.guidelines[1].line, Line::Horizontal(10.0));
+    assert_eq!(mirrored.components[0].transform.x_scale, -1.0);
+    assert_eq!(mirrored.components[0].transform.x_offset, 495.0);
+
+    // Scaling adjusts the advance only when asked to.
+    let scale = AffineTransform { x_scale: 2.0, y_scale: 2.0, ..Default::default() };
+    let mut scaled = glyph.clone();
+    scaled.transform(scale, false);
+    assert_eq!(scaled.width, 500.0);
+    scaled.transform(scale, true);
+    assert_eq!(scaled.width, 1000.0);
+
+    // Slanting turns vertical guidelines into angled ones.
+    let slant = AffineTransform { yx_scale: 1.0, ..Default::default() };
+    glyph.transform(slant, true);
+    assert_eq!(glyph.width, 500.0);
+    assert_eq!((glyph.contours[0].points[0].x, glyph.contours[0].points[0].y), (300.0, 200.0));
+    assert_eq!(glyph.guidelines[0].line, Line::Angle { x: 10.0, y: 0.0, degrees: 45.0 });
+    assert_eq!(glyph.guidelines[1].line, Line::Horizontal(10.0));
+}
+
 #[test]
 fn serialize_empty_glyph() {
     let glyph = Glyph::new_named("a");
@@ -447,3 +510,565 @@ fn empty_contours() {
     assert_eq!(test2.components, vec![]);
     assert_eq!(test2.contours, vec![]);
 }
+
+#[cfg(feature = "kurbo")]
+fn kurbo_point(x: f32, y: f32, typ: PointType, smooth: bool) -> ContourPoint {
+    ContourPoint::new(x, y, typ, smooth, None, None, None)
+}
+
+#[test]
+#[cfg(feature = "kurbo")]
+fn kurbo_roundtrip_closed() {
+    let contour = Contour::new(
+        vec![
+            kurbo_point(0.0, 0.0, PointType::Line, false),
+            kurbo_point(0.0, 50.0, PointType::OffCurve, false),
+            kurbo_point(20.0, 100.0, PointType::OffCurve, false),
+            kurbo_point(50.0, 100.0, PointType::Curve, true),
+            kurbo_point(80.0, 100.0, PointType::OffCurve, false),
+            kurbo_point(100.0, 50.0, PointType::OffCurve, false),
+            kurbo_point(100.0, 0.0, PointType::Curve, false),
+        ],
+        None,
+        None,
+    );
+    let path = contour.to_kurbo().unwrap();
+    assert_eq!(Contour::from_kurbo(&path).unwrap(), contour);
+}
+
+#[test]
+#[cfg(feature = "kurbo")]
+fn kurbo_close_path() {
+    use kurbo::PathEl;
+
+    let closed = Contour::new(
+        vec![
+            kurbo_point(0.0, 0.0, PointType::Line, false),
+            kurbo_point(0.0, 100.0, PointType::Line, false),
+            kurbo_point(100.0, 0.0, PointType::Line, false),
+        ],
+        None,
+        None,
+    );
+    assert_eq!(
+        closed.to_kurbo().unwrap().elements(),
+        [
+            PathEl::MoveTo((100.0, 0.0).into()),
+            PathEl::LineTo((0.0, 0.0).into()),
+            PathEl::LineTo((0.0, 100.0).into()),
+            PathEl::LineTo((100.0, 0.0).into()),
+            PathEl::ClosePath,
+        ]
+    );
+
+    let open = Contour::new(
+        vec![
+            kurbo_point(0.0, 0.0, PointType::Move, false),
+            kurbo_point(100.0, 0.0, PointType::Line, false),
+        ],
+        None,
+        None,
+    );
+    assert_eq!(
+        open.to_kurbo().unwrap().elements(),
+        [PathEl::MoveTo((0.0, 0.0).into()), PathEl::LineTo((100.0, 0.0).into())]
+    );
+    assert!(Contour::default().to_kurbo().unwrap().elements().is_empty());
+}
+
+#[test]
+#[cfg(feature = "kurbo")]
+fn kurbo_open_and_quadratic() {
+    let mut path = kurbo::BezPath::new();
+    path.move_to((0.0, 0.0));
+    path.quad_to((50.0, 100.0), (100.0, 0.0));
+    path.line_to((200.0, 0.0));
+    let contour = Contour::from_kurbo(&path).unwrap();
+    assert_eq!(
+        contour.points,
+        vec![
+            kurbo_point(0.0, 0.0, PointType::Move, false),
+            kurbo_point(50.0, 100.0, PointType::OffCurve, false),
+            kurbo_point(100.0, 0.0, PointType::QCurve, false),
+            kurbo_point(200.0, 0.0, PointType::Line, false),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "kurbo")]
+fn kurbo_multiple_subpaths() {
+    let mut path = kurbo::BezPath::new();
+    path.move_to((0.0, 0.0));
+    path.line_to((10.0, 0.0));
+    path.line_to((10.0, 10.0));
+    path.close_path();
+    path.move_to((20.0, 0.0));
+    path.line_to((30.0, 0.0));
+    path.line_to((30.0, 10.0));
+    path.close_path();
+    assert!(matches!(
+        Contour::from_kurbo(&path),
+        Err(Error::ConvertContour(ErrorKind::MultipleSubpaths))
+    ));
+
+    let mut glyph = Glyph::new_named("two");
+    glyph.replace_contours_from_kurbo(&path).unwrap();
+    assert_eq!(glyph.contours.len(), 2);
+    // The implied closing line turns the starting move into a line.
+    assert_eq!(glyph.contours[1].points[0], kurbo_point(20.0, 0.0, PointType::Line, false));
+    assert_eq!(glyph.contours[1].points.len(), 3);
+}
+
+#[test]
+#[cfg(feature = "kurbo")]
+fn kurbo_glyph_with_components() {
+    use crate::Layer;
+
+    let square = Contour::new(
+        vec![
+            kurbo_point(0.0, 0.0, PointType::Line, false),
+            kurbo_point(10.0, 0.0, PointType::Line, false),
+            kurbo_point(10.0, 10.0, PointType::Line, false),
+            kurbo_point(0.0, 10.0, PointType::Line, false),
+        ],
+        None,
+        None,
+    );
+    let mut base = Glyph::new_named("square");
+    base.contours.push(square.clone());
+    let mut nested = Glyph::new_named("nested");
+    let shift = AffineTransform { x_offset: 100.0, ..Default::default() };
+    nested.components.push(Component::new("square".into(), shift, None, None));
+    let mut layer = Layer::default();
+    layer.insert_glyph(base);
+    layer.insert_glyph(nested);
+
+    let mut glyph = Glyph::new_named("composite");
+    glyph.contours.push(square);
+    let scale = AffineTransform { x_scale: 2.0, y_scale: 2.0, ..Default::default() };
+    glyph.components.push(Component::new("nested".into(), scale, None, None));
+
+    let path = glyph.to_kurbo(&layer).unwrap();
+    let bbox = kurbo::Shape::bounding_box(&path);
+    assert_eq!(bbox, kurbo::Rect::new(0.0, 0.0, 220.0, 20.0));
+    assert_eq!(path.elements().iter().filter(|el| **el == kurbo::PathEl::ClosePath).count(), 2);
+
+    glyph.components.push(Component::new("missing".into(), scale, None, None));
+    assert!(matches!(glyph.to_kurbo(&layer), Err(Error::MissingGlyph { .. })));
+
+    let mut cyclic = Glyph::new_named("cyclic");
+    cyclic.components.push(Component::new("cyclic".into(), scale, None, None));
+    layer.insert_glyph(cyclic.clone());
+    assert!(matches!(cyclic.to_kurbo(&layer), Err(Error::ComponentCycle(_))));
+}
+
+fn simple_contour(points: &[(f32, f32, PointType)]) -> Contour {
+    let points = points
+        .iter()
+        .map(|(x, y, typ)| ContourPoint::new(*x, *y, typ.clone(), false, None, None, None))
+        .collect();
+    Contour::new(points, None, None)
+}
+
+fn square(x: f32, y: f32, size: f32) -> Contour {
+    simple_contour(&[
+        (x, y, PointType::Line),
+        (x + size, y, PointType::Line),
+        (x + size, y + size, PointType::Line),
+        (x, y + size, PointType::Line),
+    ])
+}
+
+/// An ellipse around the origin made from four cubics.
+fn ellipse(rx: f32, ry: f32) -> Contour {
+    let k = 0.552_284_8;
+    simple_contour(&[
+        (rx, ry * k, PointType::OffCurve),
+        (rx * k, ry, PointType::OffCurve),
+        (0.0, ry, PointType::Curve),
+        (-rx * k, ry, PointType::OffCurve),
+        (-rx, ry * k, PointType::OffCurve),
+        (-rx, 0.0, PointType::Curve),
+        (-rx, -ry * k, PointType::OffCurve),
+        (-rx * k, -ry, PointType::OffCurve),
+        (0.0, -ry, PointType::Curve),
+        (rx * k, -ry, PointType::OffCurve),
+        (rx, -ry * k, PointType::OffCurve),
+        (rx, 0.0, PointType::Curve),
+    ])
+}
+
+#[test]
+fn contour_signed_area() {
+    let ccw = square(0.0, 0.0, 100.0);
+    assert_eq!(ccw.signed_area().unwrap(), 10000.0);
+    assert!(!ccw.is_clockwise().unwrap());
+
+    let circle = ellipse(100.0, 100.0);
+    let area = circle.signed_area().unwrap();
+    assert!((area - std::f64::consts::PI * 10000.0).abs() < 10.0, "{}", area);
+
+    // A TrueType contour without on-curve points: a diamond of implied
+    // on-curves at the edge midpoints.
+    let mut all_off = simple_contour(&[
+        (0.0, 0.0, PointType::OffCurve),
+        (0.0, 100.0, PointType::OffCurve),
+        (100.0, 100.0, PointType::OffCurve),
+        (100.0, 0.0, PointType::OffCurve),
+    ]);
+    assert!(all_off.is_clockwise().unwrap());
+    let area = all_off.signed_area().unwrap();
+    all_off.reverse();
+    assert_eq!(all_off.signed_area().unwrap(), -area);
+}
+
+#[test]
+fn contour_reverse() {
+    let mut closed = simple_contour(&[
+        (0.0, 0.0, PointType::Line),
+        (50.0, 0.0, PointType::OffCurve),
+        (100.0, 50.0, PointType::OffCurve),
+        (100.0, 100.0, PointType::Curve),
+        (0.0, 100.0, PointType::QCurve),
+    ]);
+    closed.points[3].smooth = true;
+    let original = closed.clone();
+    let area = closed.signed_area().unwrap();
+
+    closed.reverse();
+    let reversed: Vec<_> = closed.points.iter().map(|p| (p.x, p.y, p.typ.clone())).collect();
+    assert_eq!(
+        reversed,
+        vec![
+            (0.0, 0.0, PointType::Curve),
+            (0.0, 100.0, PointType::Line),
+            (100.0, 100.0, PointType::QCurve),
+            (100.0, 50.0, PointType::OffCurve),
+            (50.0, 0.0, PointType::OffCurve),
+        ]
+    );
+    assert!(closed.points[2].smooth);
+    assert_eq!(closed.signed_area().unwrap(), -area);
+    closed.reverse();
+    assert_eq!(closed, original);
+
+    let mut open = simple_contour(&[
+        (0.0, 0.0, PointType::Move),
+        (50.0, 0.0, PointType::Line),
+        (100.0, 0.0, PointType::OffCurve),
+        (100.0, 50.0, PointType::OffCurve),
+        (100.0, 100.0, PointType::Curve),
+    ]);
+    let original = open.clone();
+    open.reverse();
+    let reversed: Vec<_> = open.points.iter().map(|p| (p.x, p.y, p.typ.clone())).collect();
+    assert_eq!(
+        reversed,
+        vec![
+            (100.0, 100.0, PointType::Move),
+            (100.0, 50.0, PointType::OffCurve),
+            (100.0, 0.0, PointType::OffCurve),
+            (50.0, 0.0, PointType::Curve),
+            (0.0, 0.0, PointType::Line),
+        ]
+    );
+    open.reverse();
+    assert_eq!(open, original);
+}
+
+#[test]
+fn glyph_correct_direction() {
+    let mut outer = square(0.0, 0.0, 300.0);
+    outer.reverse();
+    let mut glyph = Glyph::new_named("o");
+    glyph.contours.push(outer);
+    // A counter, and an island inside the counter.
+    glyph.contours.push(square(50.0, 50.0, 200.0));
+    glyph.contours.push(square(100.0, 100.0, 100.0));
+    // A separate shape.
+    glyph.contours.push(square(400.0, 0.0, 100.0));
+
+    glyph.correct_direction(false).unwrap();
+    let clockwise: Vec<_> = glyph.contours.iter().map(|c| c.is_clockwise().unwrap()).collect();
+    assert_eq!(clockwise, vec![false, true, false, false]);
+
+    glyph.correct_direction(true).unwrap();
+    let clockwise: Vec<_> = glyph.contours.iter().map(|c| c.is_clockwise().unwrap()).collect();
+    assert_eq!(clockwise, vec![true, false, true, true]);
+}
+
+fn point_types(contour: &Contour) -> Vec<PointType> {
+    contour.points.iter().map(|p| p.typ.clone()).collect()
+}
+
+#[test]
+fn convert_to_quadratic() {
+    let mut circle = ellipse(100.0, 100.0);
+    circle.points[2].name = Some("top".into());
+    circle.points[2].smooth = true;
+    let original = circle.clone();
+    circle.convert_to_quadratic(0.5).unwrap();
+
+    assert!(circle
+        .points
+        .iter()
+        .all(|p| p.typ == PointType::QCurve || p.typ == PointType::OffCurve));
+    assert!(circle.points.len() > original.points.len());
+    // On-curve points keep their attributes and their place in the contour.
+    let on_curves = |c: &Contour| -> Vec<ContourPoint> {
+        c.points.iter().filter(|p| p.typ != PointType::OffCurve).cloned().collect()
+    };
+    let top = &on_curves(&circle)[0];
+    assert_eq!((top.x, top.y, top.name.as_deref(), top.smooth), (0.0, 100.0, Some("top"), true));
+    assert_eq!(circle.points.last().unwrap().typ, PointType::QCurve);
+    assert_eq!(on_curves(&circle).len(), on_curves(&original).len());
+    // The approximation stays within the tolerance of the circle.
+    for segment in circle.segments().unwrap() {
+        for step in 0..=10 {
+            let radius = segment.eval(step as f64 / 10.0).hypot();
+            assert!((radius - 100.0).abs() < 0.6, "{}", radius);
+        }
+    }
+
+    // Degenerate curves become lines or plain quadratics.
+    let mut contour = simple_contour(&[
+        (0.0, 0.0, PointType::Move),
+        (100.0, 0.0, PointType::Curve),
+        (150.0, 50.0, PointType::OffCurve),
+        (100.0, 100.0, PointType::Curve),
+    ]);
+    contour.convert_to_quadratic(1.0).unwrap();
+    assert_eq!(
+        point_types(&contour),
+        vec![PointType::Move, PointType::Line, PointType::OffCurve, PointType::QCurve]
+    );
+
+    // Contours without cubics are left untouched.
+    let mut quadratic = simple_contour(&[
+        (0.0, 100.0, PointType::OffCurve),
+        (100.0, 100.0, PointType::QCurve),
+        (100.0, 0.0, PointType::Line),
+        (0.0, 0.0, PointType::OffCurve),
+    ]);
+    let original = quadratic.clone();
+    quadratic.convert_to_quadratic(1.0).unwrap();
+    assert_eq!(quadratic, original);
+}
+
+#[test]
+fn convert_to_quadratic_compatible() {
+    let mut light = Glyph::new_named("o");
+    light.contours.push(ellipse(100.0, 100.0));
+    let mut bold = Glyph::new_named("o");
+    bold.contours.push(ellipse(400.0, 100.0));
+
+    let mut alone = bold.clone();
+    alone.convert_to_quadratic(0.5).unwrap();
+    Glyph::convert_to_quadratic_compatible(&mut [&mut light, &mut bold], 0.5).unwrap();
+    assert_eq!(point_types(&light.contours[0]), point_types(&bold.contours[0]));
+    assert_eq!(point_types(&bold.contours[0]), point_types(&alone.contours[0]));
+
+    let mut other = Glyph::new_named("o");
+    other.contours.push(square(0.0, 0.0, 100.0));
+    let mut curved = Glyph::new_named("o");
+    curved.contours.push(ellipse(100.0, 100.0));
+    let before = curved.clone();
+    let result = Glyph::convert_to_quadratic_compatible(&mut [&mut curved, &mut other], 0.5);
+    assert!(matches!(result, Err(CurveConversionError::Incompatible)));
+    assert_eq!(curved, before);
+}
+
+#[test]
+fn convert_to_cubic() {
+    let mut contour = simple_contour(&[
+        (0.0, 100.0, PointType::OffCurve),
+        (100.0, 100.0, PointType::OffCurve),
+        (100.0, 0.0, PointType::QCurve),
+        (0.0, 0.0, PointType::QCurve),
+    ]);
+    contour.points[2].name = Some("corner".into());
+    let original = contour.segments().unwrap();
+    contour.convert_to_cubic().unwrap();
+    assert_eq!(
+        point_types(&contour),
+        vec![
+            PointType::OffCurve,
+            PointType::OffCurve,
+            PointType::Curve,
+            PointType::OffCurve,
+            PointType::OffCurve,
+            PointType::Curve,
+            PointType::Line,
+        ]
+    );
+    // The implied on-curve point is now explicit.
+    assert_eq!((contour.points[2].x, contour.points[2].y), (50.0, 100.0));
+    assert!(contour.points[2].smooth);
+    assert_eq!(contour.points[5].name.as_deref(), Some("corner"));
+    let mut converted = contour.segments().unwrap();
+    assert_eq!(converted.len(), original.len());
+    // Drawing starts at the first on-curve point, which has changed.
+    let offset = converted.iter().position(|s| s.start() == original[0].start()).unwrap();
+    converted.rotate_left(offset);
+    for (a, b) in original.iter().zip(&converted) {
+        for step in 0..=10 {
+            let t = step as f64 / 10.0;
+            assert!((a.eval(t) - b.eval(t)).hypot() < 1e-4);
+        }
+    }
+
+    let mut all_off = simple_contour(&[
+        (0.0, 0.0, PointType::OffCurve),
+        (0.0, 100.0, PointType::OffCurve),
+        (100.0, 100.0, PointType::OffCurve),
+        (100.0, 0.0, PointType::OffCurve),
+    ]);
+    let area = all_off.signed_area().unwrap();
+    all_off.convert_to_cubic().unwrap();
+    assert_eq!(all_off.points.len(), 12);
+    assert_eq!(all_off.points.iter().filter(|p| p.typ == PointType::Curve).count(), 4);
+    assert!((all_off.signed_area().unwrap() - area).abs() < 1e-3);
+
+    let mut glyph = Glyph::new_named("broken");
+    glyph.contours.push(contour.clone());
+    glyph
+        .contours
+        .push(simple_contour(&[(0.0, 0.0, PointType::Move), (0.0, 100.0, PointType::OffCurve)]));
+    let before = glyph.clone();
+    assert!(matches!(
+        glyph.convert_to_cubic(),
+        Err(CurveConversionError::Outline(ErrorKind::TrailingOffCurves))
+    ));
+    assert_eq!(glyph, before);
+}
+
+#[test]
+fn round_coordinates() {
+    let mut glyph = Glyph::new_named("a");
+    glyph.width = 500.4;
+    glyph.height = 99.5;
+    glyph.contours.push(square(10.3, 20.7, 99.8));
+    glyph.anchors.push(Anchor::new(249.6, 0.4, Some("bottom".into()), None, None, None));
+    let transform = AffineTransform { x_offset: 10.5, y_offset: -3.2, ..Default::default() };
+    glyph.components.push(Component::new("b".into(), transform, None, None));
+
+    let mut rounded = glyph.clone();
+    rounded.round_coordinates(&RoundingOptions::default());
+    let points: Vec<_> = rounded.contours[0].points.iter().map(|p| (p.x, p.y)).collect();
+    assert_eq!(points, vec![(10.0, 21.0), (110.0, 21.0), (110.0, 121.0), (10.0, 121.0)]);
+    assert_eq!((rounded.anchors[0].x, rounded.anchors[0].y), (250.0, 0.0));
+    assert_eq!(rounded.components[0].transform.x_offset, 11.0);
+    assert_eq!(rounded.components[0].transform.y_offset, -3.0);
+    assert_eq!((rounded.width, rounded.height), (500.0, 100.0));
+
+    let mut rounded = glyph.clone();
+    rounded
+        .round_coordinates(RoundingOptions::default().grid(10.0).components(false).advance(false));
+    assert_eq!((rounded.contours[0].points[2].x, rounded.contours[0].points[2].y), (110.0, 120.0));
+    assert_eq!((rounded.anchors[0].x, rounded.anchors[0].y), (250.0, 0.0));
+    assert_eq!(rounded.components, glyph.components);
+    assert_eq!((rounded.width, rounded.height), (500.4, 99.5));
+
+    let mut unchanged = glyph.clone();
+    unchanged.round_coordinates(RoundingOptions::default().grid(0.0));
+    assert_eq!(unchanged, glyph);
+
+    // small negative values round to 0, not -0
+    let mut rounded = glyph.clone();
+    rounded.anchors[0].x = -0.3;
+    rounded.round_coordinates(&RoundingOptions::default());
+    assert_eq!(rounded.anchors[0].x.to_string(), "0");
+}
+
+#[test]
+fn round_only_on_curve() {
+    let contour = simple_contour(&[
+        (10.4, 0.3, PointType::Line),
+        (50.2, 0.3, PointType::OffCurve),
+        (90.1, 40.6, PointType::OffCurve),
+        (90.4, 80.2, PointType::Curve),
+    ]);
+    let mut glyph = Glyph::new_named("a");
+    glyph.contours.push(contour.clone());
+    glyph.round_coordinates(RoundingOptions::default().only_on_curve(true));
+    let expected = [(10.0, 0.0), (49.8, 0.0), (89.7, 40.4), (90.0, 80.0)];
+    for (point, (x, y)) in glyph.contours[0].points.iter().zip(expected) {
+        assert!((point.x - x).abs() < 1e-4 && (point.y - y).abs() < 1e-4, "{:?}", point);
+    }
+
+    // Handles move with their on-curve point, keeping their direction.
+    let points = &glyph.contours[0].points;
+    let moved =
+        |idx: usize| (points[idx].x - contour.points[idx].x, points[idx].y - contour.points[idx].y);
+    for &(handle, on_curve) in [(1, 0), (2, 3)].iter() {
+        let ((x, y), (on_x, on_y)) = (moved(handle), moved(on_curve));
+        assert!((x - on_x).abs() < 1e-4 && (y - on_y).abs() < 1e-4, "{}", handle);
+    }
+}
+
+#[test]
+fn round_keeps_smooth_points_collinear() {
+    let mut contour = simple_contour(&[
+        (0.0, 0.0, PointType::Line),
+        (200.4, 100.2, PointType::Line),
+        (300.6, 150.3, PointType::OffCurve),
+        (400.0, 100.0, PointType::OffCurve),
+        (400.0, 0.0, PointType::Curve),
+    ]);
+    contour.points[1].smooth = true;
+    let mut glyph = Glyph::new_named("a");
+    glyph.contours.push(contour);
+    glyph.round_coordinates(&RoundingOptions::default());
+    let points = &glyph.contours[0].points;
+    // Plain rounding would put the handle at (301, 150).
+    assert_eq!((points[1].x, points[1].y), (200.0, 100.0));
+    assert_eq!((points[2].x, points[2].y), (300.0, 150.0));
+}
+
+#[test]
+fn interpolate_glyphs() {
+    let mut light = Glyph::new_named("a");
+    light.width = 400.0;
+    light.contours.push(square(0.0, 0.0, 100.0));
+    light.anchors.push(Anchor::new(50.0, 0.0, Some("bottom".into()), None, None, None));
+    light.guidelines.push(Guideline::new(Line::Horizontal(500.0), None, None, None, None));
+    let transform = AffineTransform { x_offset: 10.0, ..Default::default() };
+    light.components.push(Component::new("b".into(), transform, None, None));
+    let mut bold = light.clone();
+    bold.width = 600.0;
+    bold.contours[0] = square(0.0, 0.0, 200.0);
+    bold.anchors[0].x = 100.0;
+    bold.guidelines[0].line = Line::Horizontal(520.0);
+    bold.components[0].transform.x_offset = 30.0;
+
+    let medium = light.interpolate(&bold, 0.5).unwrap();
+    assert_eq!(medium.width, 500.0);
+    assert_eq!((medium.contours[0].points[2].x, medium.contours[0].points[2].y), (150.0, 150.0));
+    assert_eq!(medium.anchors[0].x, 75.0);
+    assert_eq!(medium.guidelines[0].line, Line::Horizontal(510.0));
+    assert_eq!(medium.components[0].transform.x_offset, 20.0);
+    assert_eq!(light.interpolate(&bold, 0.0).unwrap(), light);
+
+    let black = light.interpolate(&bold, 1.5).unwrap();
+    assert_eq!(black.width, 700.0);
+    assert_eq!(black.contours[0].points[2].x, 250.0);
+
+    // A default master plus a delta.
+    let delta = Glyph::weighted_sum(&[(&bold, 1.0), (&light, -1.0)]).unwrap();
+    let sum = Glyph::weighted_sum(&[(&light, 1.0), (&delta, 0.5)]).unwrap();
+    assert_eq!(sum, medium);
+
+    bold.contours[0].points[1].typ = PointType::Curve;
+    assert_eq!(
+        light.interpolate(&bold, 0.5),
+        Err(InterpolationError::Incompatible(vec![crate::compat::Incompatibility::PointType {
+            master: 1,
+            contour: 0,
+            point: 1,
+            expected: PointType::Line,
+            found: PointType::Curve,
+        }]))
+    );
+    assert_eq!(Glyph::weighted_sum(&[]), Err(InterpolationError::NoMasters));
+}
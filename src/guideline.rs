@@ -2,7 +2,7 @@ use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 use serde::{de, ser};
 
-use crate::{Color, Identifier, Plist};
+use crate::{AffineTransform, Color, Identifier, Plist};
 
 /// A guideline associated with a glyph.
 #[derive(Debug, Clone, PartialEq)]
@@ -33,6 +33,33 @@ pub enum Line {
     Angle { x: f32, y: f32, degrees: f32 },
 }
 
+impl Line {
+    /// Returns the line transformed by `transform`.
+    ///
+    /// Vertical and horizontal lines stay that way if the transformation
+    /// preserves their direction; otherwise they become angled lines.
+    pub(crate) fn transformed(&self, transform: AffineTransform) -> Line {
+        let (point, direction) = match *self {
+            Line::Vertical(x) => ((x, 0.0), (0.0, 1.0)),
+            Line::Horizontal(y) => ((0.0, y), (1.0, 0.0)),
+            Line::Angle { x, y, degrees } => {
+                let radians = degrees.to_radians();
+                ((x, y), (radians.cos(), radians.sin()))
+            }
+        };
+        let (x, y) = transform.transform_point(point);
+        let (dx, dy) = transform.transform_vector(direction);
+        match *self {
+            Line::Vertical(_) if dx == 0.0 => Line::Vertical(x),
+            Line::Horizontal(_) if dy == 0.0 => Line::Horizontal(y),
+            _ => {
+                let degrees = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+                Line::Angle { x, y, degrees }
+            }
+        }
+    }
+}
+
 impl Guideline {
     pub fn new(
         line: Line,
@@ -0,0 +1,284 @@
+//! Interpolating between fonts.
+
+use std::collections::BTreeMap;
+
+use crate::error::InterpolationError;
+use crate::layer::LayerSet;
+use crate::ufo::Kerning;
+use crate::{Font, FontInfo, Glyph, IntegerOrFloat};
+
+/// Set each of the given fields to the sum of its values in the masters,
+/// converted to and from `f64` by the given functions.
+macro_rules! blend_fields {
+    ($result:expr, $masters:expr, $get:expr, $make:expr; $($field:ident),+ $(,)?) => {
+        $(
+            if let Some(value) = blend($masters, |info| info.$field.as_ref().map($get)) {
+                $result.$field = Some($make(value));
+            }
+        )+
+    };
+}
+
+/// Like `blend_fields`, for fields holding lists of numbers.
+macro_rules! blend_list_fields {
+    ($result:expr, $masters:expr; $($field:ident),+ $(,)?) => {
+        $(
+            if let Some(values) = blend_lists($masters, |info| info.$field.as_deref()) {
+                $result.$field = Some(values.into_iter().map(IntegerOrFloat::new).collect());
+            }
+        )+
+    };
+}
+
+impl Font {
+    /// Interpolate between this font and `other`.
+    ///
+    /// A `factor` of `0.0` gives this font and `1.0` gives `other`; values
+    /// outside that range extrapolate. See [`Font::weighted_sum`] for what
+    /// is interpolated.
+    pub fn interpolate(&self, other: &Font, factor: f64) -> Result<Font, InterpolationError> {
+        Font::weighted_sum(&[(self, 1.0 - factor), (other, factor)])
+    }
+
+    /// Returns the sum of several fonts, each multiplied by its weight.
+    ///
+    /// The result has the numeric fields of the [`FontInfo`], the kerning and
+    /// the glyphs of the default layer summed, as described in
+    /// [`FontInfo::weighted_sum`], [`Glyph::weighted_sum`] and below; other
+    /// layers are dropped, and everything else is taken from the first font.
+    ///
+    /// A kerning pair missing from some of the fonts counts as zero in
+    /// those. Glyphs that are missing from some of the fonts, or are not
+    /// compatible across them, are left out of the result;
+    /// [`compat::check_fonts`] lists them.
+    ///
+    /// [`compat::check_fonts`]: crate::compat::check_fonts
+    pub fn weighted_sum(masters: &[(&Font, f64)]) -> Result<Font, InterpolationError> {
+        let (first, _) = masters.first().ok_or(InterpolationError::NoMasters)?;
+
+        let mut layer = first.default_layer().clone();
+        layer.clear();
+        for glyph in first.default_layer().iter() {
+            let glyphs: Option<Vec<(&Glyph, f64)>> = masters
+                .iter()
+                .map(|(font, weight)| {
+                    font.default_layer().get_glyph(&*glyph.name).map(|g| (&**g, *weight))
+                })
+                .collect();
+            if let Some(Ok(glyph)) = glyphs.map(|glyphs| Glyph::weighted_sum(&glyphs)) {
+                layer.insert_glyph(glyph);
+            }
+        }
+
+        let font_info = first.font_info.as_ref().map(|_| {
+            let infos: Vec<_> = masters
+                .iter()
+                .map(|(font, weight)| (font.font_info.clone().unwrap_or_default(), *weight))
+                .collect();
+            let infos: Vec<_> = infos.iter().map(|(info, weight)| (info, *weight)).collect();
+            FontInfo::weighted_sum(&infos).expect("there is at least one master")
+        });
+
+        let kerning = if masters.iter().any(|(font, _)| font.kerning.is_some()) {
+            let empty = Kerning::new();
+            let kernings: Vec<_> = masters
+                .iter()
+                .map(|(font, weight)| (font.kerning.as_ref().unwrap_or(&empty), *weight))
+                .collect();
+            Some(blend_kerning(&kernings))
+        } else {
+            None
+        };
+
+        Ok(Font {
+            meta: Default::default(),
+            font_info,
+            layers: LayerSet::new(vec![layer]),
+            lib: first.lib.clone(),
+            groups: first.groups.clone(),
+            kerning,
+            features: first.features.clone(),
+            data_request: first.data_request,
+        })
+    }
+}
+
+impl FontInfo {
+    /// Returns the sum of several font infos, each multiplied by its weight.
+    ///
+    /// The font-wide metrics are summed: the vertical metrics and blue
+    /// zones, italic angle, stem snaps, underline, strikeout, sub- and
+    /// superscript metrics, and the weight class. A field is only summed if
+    /// it is set in every master, and lists only if they have the same
+    /// length; otherwise, as with all other fields, the value of the first
+    /// master is used. Integer fields are rounded.
+    pub fn weighted_sum(masters: &[(&FontInfo, f64)]) -> Result<FontInfo, InterpolationError> {
+        let (first, _) = masters.first().ok_or(InterpolationError::NoMasters)?;
+        let mut result = (*first).clone();
+        blend_fields!(result, masters, |v: &IntegerOrFloat| v.get(), IntegerOrFloat::new;
+            ascender,
+            cap_height,
+            descender,
+            italic_angle,
+            postscript_blue_fuzz,
+            postscript_blue_shift,
+            postscript_default_width_x,
+            postscript_nominal_width_x,
+            postscript_slant_angle,
+            postscript_underline_position,
+            postscript_underline_thickness,
+            x_height,
+        );
+        blend_fields!(result, masters, |v: &f64| *v, |v: f64| v; postscript_blue_scale);
+        blend_fields!(result, masters, |v: &i32| *v as f64, |v: f64| v.round() as i32;
+            open_type_hhea_ascender,
+            open_type_hhea_caret_offset,
+            open_type_hhea_caret_slope_rise,
+            open_type_hhea_caret_slope_run,
+            open_type_hhea_descender,
+            open_type_hhea_line_gap,
+            open_type_os2_strikeout_position,
+            open_type_os2_strikeout_size,
+            open_type_os2_subscript_x_offset,
+            open_type_os2_subscript_x_size,
+            open_type_os2_subscript_y_offset,
+            open_type_os2_subscript_y_size,
+            open_type_os2_superscript_x_offset,
+            open_type_os2_superscript_x_size,
+            open_type_os2_superscript_y_offset,
+            open_type_os2_superscript_y_size,
+            open_type_os2_typo_ascender,
+            open_type_os2_typo_descender,
+            open_type_os2_typo_line_gap,
+            open_type_vhea_caret_offset,
+            open_type_vhea_caret_slope_rise,
+            open_type_vhea_caret_slope_run,
+            open_type_vhea_vert_typo_ascender,
+            open_type_vhea_vert_typo_descender,
+            open_type_vhea_vert_typo_line_gap,
+        );
+        blend_fields!(result, masters, |v: &u32| *v as f64, |v: f64| v.round().max(0.0) as u32;
+            open_type_os2_weight_class,
+            open_type_os2_win_ascent,
+            open_type_os2_win_descent,
+        );
+        blend_list_fields!(result, masters;
+            postscript_blue_values,
+            postscript_family_blues,
+            postscript_family_other_blues,
+            postscript_other_blues,
+            postscript_stem_snap_h,
+            postscript_stem_snap_v,
+        );
+        Ok(result)
+    }
+}
+
+/// The weighted sum of a field, if it is set in every master.
+fn blend(masters: &[(&FontInfo, f64)], get: impl Fn(&FontInfo) -> Option<f64>) -> Option<f64> {
+    masters.iter().map(|(info, weight)| get(info).map(|value| value * weight)).sum()
+}
+
+/// The weighted sum of a list field, if it is set in every master and has
+/// the same length in all of them.
+fn blend_lists(
+    masters: &[(&FontInfo, f64)],
+    get: impl Fn(&FontInfo) -> Option<&[IntegerOrFloat]>,
+) -> Option<Vec<f64>> {
+    let lists = masters.iter().map(|(info, _)| get(info)).collect::<Option<Vec<_>>>()?;
+    let len = lists.first()?.len();
+    if lists.iter().any(|list| list.len() != len) {
+        return None;
+    }
+    Some(
+        (0..len)
+            .map(|idx| {
+                lists.iter().zip(masters).map(|(list, (_, weight))| list[idx].get() * weight).sum()
+            })
+            .collect(),
+    )
+}
+
+/// The weighted sum of the kerning of all masters, with missing pairs as zero.
+pub(crate) fn blend_kerning(masters: &[(&Kerning, f64)]) -> Kerning {
+    let mut result: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+    for (kerning, weight) in masters {
+        for (first, seconds) in kerning.iter() {
+            let row = result.entry(first.clone()).or_default();
+            for (second, value) in seconds {
+                *row.entry(second.clone()).or_insert(0.0) += *value as f64 * weight;
+            }
+        }
+    }
+    result
+        .into_iter()
+        .map(|(first, seconds)| {
+            (first, seconds.into_iter().map(|(second, value)| (second, value as f32)).collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Contour, ContourPoint, PointType};
+
+    fn font(
+        ascender: f64,
+        hhea_ascender: i32,
+        blues: &[f64],
+        kerning: &[(&str, &str, f32)],
+    ) -> Font {
+        let mut font = Font::new();
+        font.font_info = Some(FontInfo {
+            ascender: Some(IntegerOrFloat::new(ascender)),
+            open_type_hhea_ascender: Some(hhea_ascender),
+            postscript_blue_values: Some(blues.iter().copied().map(IntegerOrFloat::new).collect()),
+            family_name: Some("Test".into()),
+            ..Default::default()
+        });
+        let mut pairs = Kerning::new();
+        for (first, second, value) in kerning {
+            pairs.entry(first.to_string()).or_default().insert(second.to_string(), *value);
+        }
+        font.kerning = Some(pairs);
+        font
+    }
+
+    fn glyph(name: &str, width: f32, typ: PointType) -> Glyph {
+        let mut glyph = Glyph::new_named(name);
+        glyph.width = width;
+        let point = ContourPoint::new(width, 0., typ, false, None, None, None);
+        glyph.contours.push(Contour::new(vec![point], None, None));
+        glyph
+    }
+
+    #[test]
+    fn interpolate_fonts() {
+        let mut light = font(700., 800, &[-10., 0.], &[("A", "V", -40.), ("T", "o", -20.)]);
+        let mut bold = font(800., 901, &[-20., 0., 500.], &[("A", "V", -60.)]);
+        for (font, width) in [(&mut light, 400.), (&mut bold, 600.)] {
+            let layer = font.default_layer_mut();
+            layer.insert_glyph(glyph("a", width, PointType::Line));
+            layer.insert_glyph(glyph("b", width, PointType::Line));
+        }
+        light.default_layer_mut().insert_glyph(glyph("c", 400., PointType::Line));
+        bold.default_layer_mut().insert_glyph(glyph("b", 600., PointType::Move));
+
+        let medium = light.interpolate(&bold, 0.25).unwrap();
+        let info = medium.font_info.as_ref().unwrap();
+        assert_eq!(info.ascender, Some(IntegerOrFloat::new(725.)));
+        assert_eq!(info.open_type_hhea_ascender, Some(825));
+        // Blue values of different lengths can't be interpolated.
+        assert_eq!(info.postscript_blue_values, light.font_info.unwrap().postscript_blue_values);
+        assert_eq!(info.family_name.as_deref(), Some("Test"));
+
+        let kerning = medium.kerning.as_ref().unwrap();
+        assert_eq!(kerning["A"]["V"], -45.);
+        assert_eq!(kerning["T"]["o"], -15.);
+
+        let layer = medium.default_layer();
+        assert_eq!(layer.len(), 1);
+        assert_eq!(layer.get_glyph("a").unwrap().width, 450.);
+    }
+}
@@ -0,0 +1,1091 @@
+//! Looking up kerning between glyphs.
+//!
+//! Kerning pairs in a UFO are between glyphs or kerning groups: groups whose
+//! names start with `public.kern1.` for the first side of a pair and
+//! `public.kern2.` for the second. The kerning between two glyphs is that of
+//! the most specific pair that applies, looking first for the glyphs
+//! themselves, then the first glyph and the second glyph's group, then the
+//! first glyph's group and the second glyph, and finally both groups.
+//!
+//! A pair that overrides a more general pair for some of its glyphs is an
+//! exception. The kerning can be flattened into pairs of glyphs, which
+//! applies the exceptions, and checked against the groups and glyphs of a
+//! font with [`validate_kerning`].
+//!
+//! Glyphs can be moved between kerning groups, and groups renamed, with
+//! methods on [`Font`] that keep each glyph in at most one group per side
+//! and the kerning pointing at the right groups.
+//!
+//! Finally, there are functions for cleaning up kerning, such as after
+//! removing glyphs: [`prune_kerning`], [`remove_zero_pairs`] and
+//! [`remove_empty_groups`], and for [scaling](scale_kerning) and
+//! [rounding](round_kerning) its values. The [`Font`] methods of the same
+//! names apply them to the font's kerning and groups.
+//!
+//! # Examples
+//!
+//! ```
+//! use maplit::btreemap;
+//! use norad::kerning::{KerningIndex, PairKind};
+//!
+//! let groups = btreemap! {
+//!     "public.kern1.O".to_string() => vec!["O".into(), "D".into()],
+//!     "public.kern2.A".to_string() => vec!["A".into(), "Agrave".into()],
+//! };
+//! let kerning = btreemap! {
+//!     "public.kern1.O".to_string() => btreemap! {
+//!         "public.kern2.A".to_string() => -30.,
+//!         "Agrave".to_string() => -20.,
+//!     },
+//! };
+//!
+//! let index = KerningIndex::new(Some(&kerning), Some(&groups));
+//! let pair = index.get("D", "Agrave").unwrap();
+//! assert_eq!((pair.first, pair.second, pair.value), ("public.kern1.O", "Agrave", -20.));
+//! assert_eq!(pair.kind, PairKind::GroupGlyph);
+//! assert_eq!(index.get("O", "A").unwrap().value, -30.);
+//! assert_eq!(index.get("A", "O"), None);
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::error::KerningGroupError;
+pub use crate::ufo::{Groups, Kerning};
+use crate::{Error, Font, GlyphName, Layer};
+
+/// The prefix of the names of groups on the first side of kerning pairs.
+pub const KERN1_PREFIX: &str = "public.kern1.";
+/// The prefix of the names of groups on the second side of kerning pairs.
+pub const KERN2_PREFIX: &str = "public.kern2.";
+
+/// A kerning value, and the pair it was found under.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KernPair<'a> {
+    /// The first side of the pair, a glyph or group name.
+    pub first: &'a str,
+    /// The second side of the pair, a glyph or group name.
+    pub second: &'a str,
+    /// The kerning value.
+    pub value: f32,
+    /// Which sides of the pair are groups.
+    pub kind: PairKind,
+}
+
+/// Which sides of a kerning pair are groups, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PairKind {
+    /// A pair of glyphs.
+    GlyphGlyph,
+    /// A glyph followed by a group.
+    GlyphGroup,
+    /// A group followed by a glyph.
+    GroupGlyph,
+    /// A pair of groups.
+    GroupGroup,
+}
+
+/// A side of a kerning pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KerningSide {
+    /// The first, or left in left-to-right text, side.
+    First,
+    /// The second side.
+    Second,
+}
+
+impl KerningSide {
+    /// The prefix of the names of kerning groups for this side.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            KerningSide::First => KERN1_PREFIX,
+            KerningSide::Second => KERN2_PREFIX,
+        }
+    }
+
+    /// Returns the side a group is for, if its name is that of a kerning
+    /// group.
+    pub fn of_group(name: &str) -> Option<KerningSide> {
+        [KerningSide::First, KerningSide::Second]
+            .iter()
+            .copied()
+            .find(|side| name.starts_with(side.prefix()) && name.len() > side.prefix().len())
+    }
+}
+
+/// A problem with a font's kerning, found by [`validate_kerning`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum KerningIssue {
+    /// A side of a pair is a glyph that is not in the font.
+    UnknownGlyph { first: String, second: String, name: String },
+    /// A side of a pair is a kerning group that is not in the font.
+    UnknownGroup { first: String, second: String, name: String },
+    /// A second-side group is used on the first side of a pair, or the
+    /// other way round.
+    WrongSide { first: String, second: String, name: String },
+    /// A glyph-group pair and a group-glyph pair both apply to the same two
+    /// glyphs with different values, and no pair of the glyphs themselves
+    /// decides between them. The glyph-group pair takes precedence.
+    Conflict { glyph_group: (String, String), group_glyph: (String, String) },
+}
+
+/// An index of the kerning groups of a font, for looking up the kerning
+/// between many pairs of glyphs.
+#[derive(Debug, Clone, Default)]
+pub struct KerningIndex<'a> {
+    kerning: Option<&'a Kerning>,
+    groups: Option<&'a Groups>,
+    first_groups: HashMap<&'a str, &'a str>,
+    second_groups: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> KerningIndex<'a> {
+    /// Create an index for the given kerning and groups.
+    pub fn new(kerning: Option<&'a Kerning>, groups: Option<&'a Groups>) -> Self {
+        let mut first_groups = HashMap::new();
+        let mut second_groups = HashMap::new();
+        for (name, glyphs) in groups.into_iter().flatten() {
+            let side = if name.starts_with(KERN1_PREFIX) {
+                &mut first_groups
+            } else if name.starts_with(KERN2_PREFIX) {
+                &mut second_groups
+            } else {
+                continue;
+            };
+            for glyph in glyphs {
+                side.insert(&**glyph, name.as_str());
+            }
+        }
+        KerningIndex { kerning, groups, first_groups, second_groups }
+    }
+
+    /// Returns the kerning between two glyphs, and the pair it comes from.
+    pub fn get(&self, first: &str, second: &str) -> Option<KernPair<'a>> {
+        self.find(first, second, None)
+    }
+
+    /// Returns the value a pair would have without it: that of the most
+    /// specific of the more general pairs that apply to it.
+    ///
+    /// Either side may be a glyph or a group. A pair that exists and has a
+    /// group value is an exception.
+    pub fn group_value(&self, first: &str, second: &str) -> Option<KernPair<'a>> {
+        self.find(first, second, Some((first, second)))
+    }
+
+    /// Returns `true` if the pair exists and overrides a more general pair.
+    pub fn is_exception(&self, first: &str, second: &str) -> bool {
+        let exists = matches!(self.kerning, Some(kerning) if kerning
+            .get(first)
+            .map(|row| row.contains_key(second))
+            .unwrap_or(false));
+        exists && self.group_value(first, second).is_some()
+    }
+
+    /// Returns the exceptions that could each be removed without changing
+    /// the kerning: those with the same value as the pair they override,
+    /// where every pair of glyphs they apply to keeps its value once they
+    /// are gone.
+    ///
+    /// An exception between a glyph and a group also overrides pairs of
+    /// the glyph's group and the other group's members, so it is only
+    /// redundant if those have the same value too.
+    ///
+    /// Removing one exception may change whether another is redundant; use
+    /// [`Font::remove_redundant_kerning_exceptions`] to remove them all.
+    pub fn redundant_exceptions(&self) -> Vec<(&'a str, &'a str)> {
+        let mut result = Vec::new();
+        for (first, row) in self.kerning.into_iter().flatten() {
+            for (second, value) in row {
+                if self.is_redundant(first, second, *value) {
+                    result.push((first.as_str(), second.as_str()));
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns `true` if the pair is an exception with the value of the pair
+    /// it overrides, and removing it changes no pair of glyphs.
+    fn is_redundant(&self, first: &str, second: &str, value: f32) -> bool {
+        if !matches!(self.group_value(first, second), Some(pair) if pair.value == value) {
+            return false;
+        }
+        let seconds = self.members(second);
+        self.members(first).into_iter().all(|glyph| {
+            seconds.iter().all(|other| {
+                let value = self.find(glyph, other, None).map(|pair| pair.value);
+                value == self.find(glyph, other, Some((first, second))).map(|pair| pair.value)
+            })
+        })
+    }
+
+    /// Expand the kerning into pairs of glyphs, with the value of each
+    /// pair resolved as by [`KerningIndex::get`].
+    ///
+    /// Only glyphs in the groups are known to the index; pairs of groups
+    /// are expanded to all of their members, and pairs of groups that do
+    /// not exist are left out.
+    pub fn flatten(&self) -> Kerning {
+        let mut pairs = BTreeSet::new();
+        for (first, row) in self.kerning.into_iter().flatten() {
+            for second in row.keys() {
+                for first in self.members(first) {
+                    for second in self.members(second) {
+                        pairs.insert((first, second));
+                    }
+                }
+            }
+        }
+        let mut result = Kerning::new();
+        for (first, second) in pairs {
+            if let Some(pair) = self.get(first, second) {
+                result.entry(first.to_string()).or_default().insert(second.to_string(), pair.value);
+            }
+        }
+        result
+    }
+
+    /// Look up the most specific pair that applies to `first` and `second`,
+    /// each of which may be a glyph or a group, as if the pair `removed`
+    /// was not there.
+    fn find(
+        &self,
+        first: &str,
+        second: &str,
+        removed: Option<(&str, &str)>,
+    ) -> Option<KernPair<'a>> {
+        let kerning = self.kerning?;
+        let first_group = self.first_group(first);
+        let second_group = self.second_group(second);
+        let candidates = [
+            (Some(first), Some(second)),
+            (Some(first), second_group),
+            (first_group, Some(second)),
+            (first_group, second_group),
+        ];
+        candidates.iter().find_map(|(first, second)| {
+            let (first, row) = kerning.get_key_value((*first)?)?;
+            let (second, value) = row.get_key_value((*second)?)?;
+            if removed == Some((first.as_str(), second.as_str())) {
+                return None;
+            }
+            let kind = match (is_group(first), is_group(second)) {
+                (false, false) => PairKind::GlyphGlyph,
+                (false, true) => PairKind::GlyphGroup,
+                (true, false) => PairKind::GroupGlyph,
+                (true, true) => PairKind::GroupGroup,
+            };
+            Some(KernPair { first, second, value: *value, kind })
+        })
+    }
+
+    /// The glyphs a side of a pair stands for: the members of a group, none
+    /// for a group that does not exist, or the glyph itself.
+    fn members<'b>(&self, name: &'b str) -> Vec<&'b str>
+    where
+        'a: 'b,
+    {
+        if !is_group(name) {
+            return vec![name];
+        }
+        match self.groups.and_then(|groups| groups.get(name)) {
+            Some(glyphs) => glyphs.iter().map(|glyph| &**glyph).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the group a glyph belongs to on the given side of pairs.
+    pub fn group(&self, glyph: &str, side: KerningSide) -> Option<&'a str> {
+        match side {
+            KerningSide::First => self.first_group(glyph),
+            KerningSide::Second => self.second_group(glyph),
+        }
+    }
+
+    /// Returns the group a glyph belongs to on the first side of pairs.
+    pub fn first_group(&self, glyph: &str) -> Option<&'a str> {
+        self.first_groups.get(glyph).copied()
+    }
+
+    /// Returns the group a glyph belongs to on the second side of pairs.
+    pub fn second_group(&self, glyph: &str) -> Option<&'a str> {
+        self.second_groups.get(glyph).copied()
+    }
+}
+
+/// Check kerning against the groups and glyphs it refers to.
+///
+/// Every side of a pair must be a kerning group that exists and is meant
+/// for that side, or a glyph in `glyphs`; if `glyphs` is `None`, glyph names
+/// are not checked. Conflicting exceptions are reported too.
+pub fn validate_kerning(
+    kerning: &Kerning,
+    groups: Option<&Groups>,
+    glyphs: Option<&Layer>,
+) -> Vec<KerningIssue> {
+    let mut issues = Vec::new();
+    let has_group = |name: &str| matches!(groups, Some(groups) if groups.contains_key(name));
+    for (first, row) in kerning {
+        for second in row.keys() {
+            let sides = [(first, KERN1_PREFIX, KERN2_PREFIX), (second, KERN2_PREFIX, KERN1_PREFIX)];
+            for (name, prefix, other_prefix) in sides.iter() {
+                let pair = || (first.clone(), second.clone());
+                let name = name.to_string();
+                if name.starts_with(other_prefix) {
+                    let (first, second) = pair();
+                    issues.push(KerningIssue::WrongSide { first, second, name });
+                } else if name.starts_with(prefix) {
+                    if !has_group(&name) {
+                        let (first, second) = pair();
+                        issues.push(KerningIssue::UnknownGroup { first, second, name });
+                    }
+                } else if matches!(glyphs, Some(layer) if !layer.contains_glyph(&name)) {
+                    let (first, second) = pair();
+                    issues.push(KerningIssue::UnknownGlyph { first, second, name });
+                }
+            }
+        }
+    }
+
+    let index = KerningIndex::new(Some(kerning), groups);
+    for (glyph, row) in kerning.iter().filter(|(first, _)| !is_group(first)) {
+        let first_group = match index.first_group(glyph) {
+            Some(group) => group,
+            None => continue,
+        };
+        let group_row = match kerning.get(first_group) {
+            Some(row) => row,
+            None => continue,
+        };
+        for (second_group, value) in row.iter().filter(|(second, _)| is_group(second)) {
+            for (other, other_value) in group_row {
+                if !is_group(other)
+                    && index.second_group(other) == Some(second_group.as_str())
+                    && value != other_value
+                    && !row.contains_key(other)
+                {
+                    issues.push(KerningIssue::Conflict {
+                        glyph_group: (glyph.clone(), second_group.clone()),
+                        group_glyph: (first_group.to_string(), other.clone()),
+                    });
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Remove the pairs that refer to groups not in `groups`, or to glyphs not
+/// in `glyphs`, returning how many were removed.
+pub fn prune_kerning(kerning: &mut Kerning, groups: Option<&Groups>, glyphs: &Layer) -> usize {
+    let exists = |name: &str| {
+        if is_group(name) {
+            matches!(groups, Some(groups) if groups.contains_key(name))
+        } else {
+            glyphs.contains_glyph(name)
+        }
+    };
+    retain_pairs(kerning, |first, second, _| exists(first) && exists(second))
+}
+
+/// Remove the pairs with a value of zero, returning how many were removed.
+///
+/// Note that a zero pair may be an exception that turns off the kerning of
+/// a more general pair; this removes those too.
+pub fn remove_zero_pairs(kerning: &mut Kerning) -> usize {
+    retain_pairs(kerning, |_, _, value| value != 0.0)
+}
+
+/// Multiply all kerning values by `factor`.
+pub fn scale_kerning(kerning: &mut Kerning, factor: f32) {
+    for value in kerning.values_mut().flat_map(|row| row.values_mut()) {
+        *value *= factor;
+    }
+}
+
+/// Round all kerning values to a grid. Values are left alone if `grid` is
+/// not positive.
+pub fn round_kerning(kerning: &mut Kerning, grid: f32) {
+    if grid <= 0.0 || grid.is_nan() {
+        return;
+    }
+    for value in kerning.values_mut().flat_map(|row| row.values_mut()) {
+        // adding zero turns -0 into 0
+        *value = (*value / grid).round() * grid + 0.0;
+    }
+}
+
+/// Remove the groups without any glyphs, and the pairs that refer to them,
+/// returning the names of the groups removed.
+pub fn remove_empty_groups(groups: &mut Groups, kerning: Option<&mut Kerning>) -> Vec<String> {
+    let empty: Vec<String> = groups
+        .iter()
+        .filter(|(_, glyphs)| glyphs.is_empty())
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in &empty {
+        groups.remove(name);
+    }
+    if let Some(kerning) = kerning {
+        let is_empty = |name: &str| empty.iter().any(|group| group == name);
+        retain_pairs(kerning, |first, second, _| !is_empty(first) && !is_empty(second));
+    }
+    empty
+}
+
+/// Keep only the pairs for which `f` returns `true`, removing rows that are
+/// left empty. Returns the number of pairs removed.
+fn retain_pairs(kerning: &mut Kerning, mut f: impl FnMut(&str, &str, f32) -> bool) -> usize {
+    let mut removed = 0;
+    for (first, row) in kerning.iter_mut() {
+        let len = row.len();
+        row.retain(|second, value| f(first, second, *value));
+        removed += len - row.len();
+    }
+    kerning.retain(|_, row| !row.is_empty());
+    removed
+}
+
+/// Returns `true` if the name is that of a kerning group.
+fn is_group(name: &str) -> bool {
+    name.starts_with(KERN1_PREFIX) || name.starts_with(KERN2_PREFIX)
+}
+
+impl Font {
+    /// Remove the kerning pairs that refer to groups or glyphs that are not
+    /// in the font, returning how many were removed.
+    ///
+    /// Glyphs are looked up in the default layer. See [`prune_kerning`].
+    pub fn prune_kerning(&mut self) -> usize {
+        let layers = &self.layers;
+        match self.kerning.as_mut() {
+            Some(kerning) => prune_kerning(kerning, self.groups.as_ref(), layers.default_layer()),
+            None => 0,
+        }
+    }
+
+    /// Remove the kerning pairs with a value of zero, returning how many
+    /// were removed.
+    ///
+    /// See [`remove_zero_pairs`].
+    pub fn remove_zero_pairs(&mut self) -> usize {
+        self.kerning.as_mut().map(remove_zero_pairs).unwrap_or(0)
+    }
+
+    /// Multiply all kerning values by `factor`, such as when changing the
+    /// units per em.
+    pub fn scale_kerning(&mut self, factor: f32) {
+        if let Some(kerning) = self.kerning.as_mut() {
+            scale_kerning(kerning, factor);
+        }
+    }
+
+    /// Remove the groups without any glyphs, and the kerning pairs that
+    /// refer to them, returning the names of the groups removed.
+    pub fn remove_empty_groups(&mut self) -> Vec<String> {
+        match self.groups.as_mut() {
+            Some(groups) => remove_empty_groups(groups, self.kerning.as_mut()),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the kerning group a glyph belongs to on the given side.
+    pub fn kerning_group(&self, glyph: &str, side: KerningSide) -> Option<&str> {
+        self.groups
+            .iter()
+            .flatten()
+            .find(|(name, glyphs)| {
+                name.starts_with(side.prefix()) && glyphs.iter().any(|g| &**g == glyph)
+            })
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Move a glyph to a kerning group on the given side, or out of its
+    /// group if `group` is `None`.
+    ///
+    /// The glyph is removed from any other group on that side, which is
+    /// kept even if it is left empty, and the group is created if it does
+    /// not exist. Returns the group the glyph was in before.
+    pub fn set_kerning_group(
+        &mut self,
+        glyph: &str,
+        side: KerningSide,
+        group: Option<&str>,
+    ) -> Result<Option<String>, Error> {
+        if let Some(group) = group {
+            if KerningSide::of_group(group) != Some(side) {
+                return Err(Error::KerningGroup(KerningGroupError::WrongSide(group.into())));
+            }
+        }
+        let groups = match (self.groups.as_mut(), group) {
+            (Some(groups), _) => groups,
+            (None, Some(_)) => self.groups.get_or_insert_with(Default::default),
+            (None, None) => return Ok(None),
+        };
+        let mut previous = None;
+        for (name, glyphs) in groups.iter_mut().filter(|(name, _)| name.starts_with(side.prefix()))
+        {
+            let len = glyphs.len();
+            glyphs.retain(|g| &**g != glyph);
+            if glyphs.len() != len {
+                previous = Some(name.clone());
+            }
+        }
+        if let Some(group) = group {
+            groups.entry(group.into()).or_default().push(GlyphName::from(glyph));
+        }
+        Ok(previous)
+    }
+
+    /// Rename a kerning group, and the pairs that refer to it.
+    ///
+    /// The new name must be that of a group for the same side that does not
+    /// exist yet.
+    pub fn rename_kerning_group(&mut self, old: &str, new: &str) -> Result<(), Error> {
+        let error = |e| Err(Error::KerningGroup(e));
+        let side = match KerningSide::of_group(old) {
+            Some(side) => side,
+            None => return error(KerningGroupError::WrongSide(old.into())),
+        };
+        if KerningSide::of_group(new) != Some(side) {
+            return error(KerningGroupError::WrongSide(new.into()));
+        }
+        let groups = match self.groups.as_mut() {
+            Some(groups) => groups,
+            None => return error(KerningGroupError::MissingGroup(old.into())),
+        };
+        if groups.contains_key(new) {
+            return error(KerningGroupError::DuplicateGroup(new.into()));
+        }
+        let glyphs = match groups.remove(old) {
+            Some(glyphs) => glyphs,
+            None => return error(KerningGroupError::MissingGroup(old.into())),
+        };
+        groups.insert(new.into(), glyphs);
+
+        let kerning = match self.kerning.as_mut() {
+            Some(kerning) => kerning,
+            None => return Ok(()),
+        };
+        match side {
+            KerningSide::First => {
+                if let Some(row) = kerning.remove(old) {
+                    kerning.insert(new.into(), row);
+                }
+            }
+            KerningSide::Second => {
+                for row in kerning.values_mut() {
+                    if let Some(value) = row.remove(old) {
+                        row.insert(new.into(), value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Font {
+    /// Returns the kerning between two glyphs, and the pair it comes from.
+    ///
+    /// This looks through the font's groups on each call; use
+    /// [`Font::kerning_index`] to look up many pairs.
+    pub fn kerning_value(&self, first: &str, second: &str) -> Option<KernPair<'_>> {
+        self.kerning_index().get(first, second)
+    }
+
+    /// Returns an index of the font's kerning groups, for looking up the
+    /// kerning between many pairs of glyphs.
+    pub fn kerning_index(&self) -> KerningIndex<'_> {
+        KerningIndex::new(self.kerning.as_ref(), self.groups.as_ref())
+    }
+
+    /// Check the font's kerning against its groups and the glyphs in the
+    /// default layer.
+    ///
+    /// Glyph names are not checked if the layers were not loaded, and whether
+    /// groups exist if the groups were not loaded. See [`validate_kerning`] for
+    /// details.
+    pub fn validate_kerning(&self) -> Vec<KerningIssue> {
+        let glyphs = if self.data_request.layers { Some(self.default_layer()) } else { None };
+        let mut issues = match &self.kerning {
+            Some(kerning) => validate_kerning(kerning, self.groups.as_ref(), glyphs),
+            None => Vec::new(),
+        };
+        if !self.data_request.groups {
+            issues.retain(|issue| !matches!(issue, KerningIssue::UnknownGroup { .. }));
+        }
+        issues
+    }
+
+    /// Fail with [`Error::InvalidKerning`] if the kerning has problems.
+    pub(crate) fn check_kerning(&self) -> Result<(), Error> {
+        let issues = self.validate_kerning();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidKerning(issues))
+        }
+    }
+
+    /// Returns the kerning expanded into pairs of glyphs.
+    ///
+    /// See [`KerningIndex::flatten`] for details.
+    pub fn flattened_kerning(&self) -> Kerning {
+        self.kerning_index().flatten()
+    }
+
+    /// Add an exception to the kerning, overriding the more general pair
+    /// that applies to `first` and `second`, each of which may be a glyph or
+    /// a group.
+    ///
+    /// Returns the value of the overridden pair. If there is no such pair,
+    /// the exception would just be a new pair, and nothing is added.
+    pub fn add_kerning_exception(&mut self, first: &str, second: &str, value: f32) -> Option<f32> {
+        let group_value = self.kerning_index().group_value(first, second)?.value;
+        let kerning = self.kerning.get_or_insert_with(Default::default);
+        kerning.entry(first.to_string()).or_default().insert(second.to_string(), value);
+        Some(group_value)
+    }
+
+    /// Remove an exception from the kerning, so that the more general pair
+    /// applies again.
+    ///
+    /// Returns the value of the removed exception, or `None` if the pair is
+    /// not an exception.
+    pub fn remove_kerning_exception(&mut self, first: &str, second: &str) -> Option<f32> {
+        if !self.kerning_index().is_exception(first, second) {
+            return None;
+        }
+        remove_pair(self.kerning.as_mut()?, first, second)
+    }
+
+    /// Remove the exceptions that can go without changing the kerning,
+    /// returning how many were removed.
+    ///
+    /// See [`KerningIndex::redundant_exceptions`] for which those are.
+    pub fn remove_redundant_kerning_exceptions(&mut self) -> usize {
+        // An exception between a glyph and a group only falls back to a pair
+        // of groups, but one between glyphs may fall back to one between a
+        // glyph and a group, so those have to be removed first. Removing one
+        // exception can make another one matter, so each is checked again
+        // just before it is removed.
+        let mut count = 0;
+        for with_group in [true, false].iter() {
+            let redundant: Vec<(String, String)> = self
+                .kerning_index()
+                .redundant_exceptions()
+                .into_iter()
+                .filter(|(first, second)| (is_group(first) || is_group(second)) == *with_group)
+                .map(|(first, second)| (first.to_string(), second.to_string()))
+                .collect();
+            for (first, second) in &redundant {
+                let value =
+                    self.kerning.as_ref().and_then(|kerning| kerning.get(first)?.get(second));
+                if !matches!(value, Some(value) if self.kerning_index().is_redundant(first, second, *value))
+                {
+                    continue;
+                }
+                if let Some(kerning) = self.kerning.as_mut() {
+                    remove_pair(kerning, first, second);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+}
+
+impl fmt::Display for KerningIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KerningIssue::UnknownGlyph { first, second, name } => {
+                write!(f, "pair '{}' '{}' refers to unknown glyph '{}'", first, second, name)
+            }
+            KerningIssue::UnknownGroup { first, second, name } => {
+                write!(f, "pair '{}' '{}' refers to unknown group '{}'", first, second, name)
+            }
+            KerningIssue::WrongSide { first, second, name } => {
+                write!(f, "pair '{}' '{}' uses group '{}' on the wrong side", first, second, name)
+            }
+            KerningIssue::Conflict { glyph_group, group_glyph } => write!(
+                f,
+                "pairs '{}' '{}' and '{}' '{}' conflict",
+                glyph_group.0, glyph_group.1, group_glyph.0, group_glyph.1
+            ),
+        }
+    }
+}
+
+/// Remove a pair, and the row for its first side if it is left empty.
+fn remove_pair(kerning: &mut Kerning, first: &str, second: &str) -> Option<f32> {
+    let row = kerning.get_mut(first)?;
+    let value = row.remove(second);
+    if row.is_empty() {
+        kerning.remove(first);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::btreemap;
+
+    #[test]
+    fn precedence() {
+        let mut font = Font::new();
+        font.groups = Some(btreemap! {
+            "public.kern1.T".into() => vec!["T".into(), "Tcedilla".into()],
+            "public.kern2.o".into() => vec!["o".into(), "oacute".into()],
+            "public.kern2.T".into() => vec!["T".into()],
+            "accents".into() => vec!["oacute".into()],
+        });
+        font.kerning = Some(btreemap! {
+            "public.kern1.T".into() => btreemap! {
+                "public.kern2.o".into() => -80.,
+                "oacute".into() => -40.,
+            },
+            "T".into() => btreemap! {
+                "public.kern2.o".into() => -70.,
+                "o".into() => -60.,
+            },
+        });
+
+        let pair = |first, second| {
+            font.kerning_value(first, second).map(|pair| (pair.first, pair.second, pair.value))
+        };
+        assert_eq!(pair("T", "o"), Some(("T", "o", -60.)));
+        assert_eq!(pair("T", "oacute"), Some(("T", "public.kern2.o", -70.)));
+        assert_eq!(pair("Tcedilla", "oacute"), Some(("public.kern1.T", "oacute", -40.)));
+        assert_eq!(pair("Tcedilla", "o"), Some(("public.kern1.T", "public.kern2.o", -80.)));
+        assert_eq!(pair("o", "T"), None);
+        assert_eq!(font.kerning_value("T", "o").unwrap().kind, PairKind::GlyphGlyph);
+
+        let index = font.kerning_index();
+        assert_eq!(index.first_group("T"), Some("public.kern1.T"));
+        assert_eq!(index.second_group("T"), Some("public.kern2.T"));
+        assert_eq!(index.second_group("oacute"), Some("public.kern2.o"));
+        assert_eq!(index.get("Tcedilla", "oacute").unwrap().kind, PairKind::GroupGlyph);
+
+        font.kerning = None;
+        assert_eq!(font.kerning_value("T", "o"), None);
+    }
+
+    #[test]
+    fn exceptions() {
+        let mut font = Font::new();
+        font.groups = Some(btreemap! {
+            "public.kern1.T".into() => vec!["T".into(), "Tcedilla".into()],
+            "public.kern2.o".into() => vec!["o".into(), "oacute".into()],
+        });
+        font.kerning = Some(btreemap! {
+            "public.kern1.T".into() => btreemap! {
+                "public.kern2.o".into() => -80.,
+                "oacute".into() => -40.,
+            },
+            "T".into() => btreemap! { "oacute".into() => -40., "a".into() => -20. },
+        });
+
+        let index = font.kerning_index();
+        assert!(index.is_exception("public.kern1.T", "oacute"));
+        assert!(index.is_exception("T", "oacute"));
+        assert!(!index.is_exception("T", "a"));
+        assert!(!index.is_exception("public.kern1.T", "public.kern2.o"));
+        assert_eq!(index.group_value("T", "oacute").unwrap().first, "public.kern1.T");
+        assert_eq!(index.redundant_exceptions(), vec![("T", "oacute")]);
+
+        let flat = font.flattened_kerning();
+        assert_eq!(
+            flat,
+            btreemap! {
+                "T".into() => btreemap! {
+                    "a".into() => -20.,
+                    "o".into() => -80.,
+                    "oacute".into() => -40.,
+                },
+                "Tcedilla".into() => btreemap! { "o".into() => -80., "oacute".into() => -40. },
+            }
+        );
+
+        assert_eq!(font.remove_redundant_kerning_exceptions(), 1);
+        assert_eq!(font.kerning_value("T", "oacute").unwrap().first, "public.kern1.T");
+        assert_eq!(font.flattened_kerning(), flat);
+
+        // Both of these are redundant, but removing both would let the
+        // group-glyph pair apply to "T" and "oacute".
+        let kerning = font.kerning.as_mut().unwrap();
+        kerning.get_mut("T").unwrap().insert("public.kern2.o".into(), -80.);
+        kerning.get_mut("T").unwrap().insert("oacute".into(), -80.);
+        let flat = font.flattened_kerning();
+        assert_eq!(font.remove_redundant_kerning_exceptions(), 1);
+        assert_eq!(font.flattened_kerning(), flat);
+        assert_eq!(font.kerning_value("T", "oacute").unwrap().first, "T");
+
+        assert_eq!(font.add_kerning_exception("Tcedilla", "o", -60.), Some(-80.));
+        assert_eq!(font.kerning_value("Tcedilla", "o").unwrap().value, -60.);
+        assert_eq!(font.add_kerning_exception("a", "o", -10.), None);
+        assert_eq!(font.kerning_value("a", "o"), None);
+
+        assert_eq!(font.remove_kerning_exception("T", "a"), None);
+        assert_eq!(font.remove_kerning_exception("Tcedilla", "o"), Some(-60.));
+        assert_eq!(font.kerning.as_ref().unwrap().get("Tcedilla"), None);
+        assert_eq!(font.kerning_value("Tcedilla", "o").unwrap().value, -80.);
+
+        // This glyph-group pair has the value of the group pair, but it also
+        // overrides the group-glyph pair for "T" and "oacute".
+        font.kerning = Some(btreemap! {
+            "public.kern1.T".into() => btreemap! {
+                "public.kern2.o".into() => -80.,
+                "oacute".into() => -40.,
+            },
+            "T".into() => btreemap! { "public.kern2.o".into() => -80. },
+        });
+        assert!(font.kerning_index().redundant_exceptions().is_empty());
+        let flat = font.flattened_kerning();
+        assert_eq!(flat["T"]["oacute"], -80.);
+        assert_eq!(font.remove_redundant_kerning_exceptions(), 0);
+        assert_eq!(font.flattened_kerning(), flat);
+
+        // Pairs of groups that are not defined stand for no glyphs.
+        font.kerning = Some(btreemap! {
+            "public.kern1.X".into() => btreemap! { "o".into() => -10. },
+            "T".into() => btreemap! { "public.kern2.X".into() => -20., "o".into() => -30. },
+        });
+        assert_eq!(
+            font.flattened_kerning(),
+            btreemap! { "T".into() => btreemap! { "o".into() => -30. } }
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let mut font = Font::new();
+        for name in &["A", "V", "T", "o", "oacute"] {
+            font.default_layer_mut().insert_glyph(crate::Glyph::new_named(*name));
+        }
+        font.groups = Some(btreemap! {
+            "public.kern1.T".into() => vec!["T".into()],
+            "public.kern2.o".into() => vec!["o".into(), "oacute".into()],
+        });
+        font.kerning = Some(btreemap! {
+            "A".into() => btreemap! { "V".into() => -40., "W".into() => -30. },
+            "public.kern2.o".into() => btreemap! { "T".into() => -10. },
+            "public.kern1.V".into() => btreemap! { "o".into() => -20. },
+            "public.kern1.T".into() => btreemap! { "oacute".into() => -50. },
+            "T".into() => btreemap! { "public.kern2.o".into() => -60. },
+        });
+        let pair = |first: &str, second: &str| (first.to_string(), second.to_string());
+        assert_eq!(
+            font.validate_kerning(),
+            vec![
+                KerningIssue::UnknownGlyph {
+                    first: "A".into(),
+                    second: "W".into(),
+                    name: "W".into()
+                },
+                KerningIssue::UnknownGroup {
+                    first: "public.kern1.V".into(),
+                    second: "o".into(),
+                    name: "public.kern1.V".into()
+                },
+                KerningIssue::WrongSide {
+                    first: "public.kern2.o".into(),
+                    second: "T".into(),
+                    name: "public.kern2.o".into()
+                },
+                KerningIssue::Conflict {
+                    glyph_group: pair("T", "public.kern2.o"),
+                    group_glyph: pair("public.kern1.T", "oacute"),
+                },
+            ]
+        );
+
+        let kerning = font.kerning.as_mut().unwrap();
+        kerning.get_mut("T").unwrap().insert("oacute".into(), -55.);
+        kerning.remove("public.kern1.V");
+        kerning.remove("public.kern2.o");
+        kerning.get_mut("A").unwrap().remove("W");
+        assert_eq!(font.validate_kerning(), vec![]);
+
+        font.data_request.layers = false;
+        font.kerning.as_mut().unwrap().get_mut("A").unwrap().insert("W".into(), -30.);
+        assert_eq!(font.validate_kerning(), vec![]);
+    }
+
+    #[test]
+    fn validate_on_save_and_load() {
+        let dir = tempdir::TempDir::new("Kerning.ufo").unwrap();
+        let mut font = Font::new();
+        font.default_layer_mut().insert_glyph(crate::Glyph::new_named("A"));
+        font.kerning = Some(btreemap! { "A".into() => btreemap! { "V".into() => -40. } });
+        font.save(&dir).unwrap();
+        assert!(Font::load(&dir).is_ok());
+
+        let mut request = crate::DataRequest::default();
+        request.validate_kerning(true);
+        match Font::with_fields(request).load_ufo(&dir) {
+            Err(Error::InvalidKerning(issues)) => assert_eq!(issues.len(), 1),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        font.data_request = request;
+        assert!(matches!(font.save(&dir), Err(Error::InvalidKerning(_))));
+        // The existing UFO is left alone.
+        assert!(Font::load(&dir).is_ok());
+
+        // Groups are not looked up if they were not loaded.
+        font.data_request = crate::DataRequest::default();
+        font.default_layer_mut().insert_glyph(crate::Glyph::new_named("V"));
+        font.groups = Some(btreemap! { "public.kern2.V".into() => vec!["V".into()] });
+        font.kerning = Some(btreemap! {
+            "A".into() => btreemap! { "public.kern2.W".into() => -40. },
+        });
+        font.save(&dir).unwrap();
+        request.groups(false);
+        assert!(Font::with_fields(request).load_ufo(&dir).is_ok());
+        request.groups(true);
+        match Font::with_fields(request).load_ufo(&dir) {
+            Err(Error::InvalidKerning(issues)) => assert_eq!(issues.len(), 1),
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        // but the side of a group is known from its name alone
+        font.kerning = Some(btreemap! {
+            "public.kern2.V".into() => btreemap! { "A".into() => -10. },
+        });
+        font.save(&dir).unwrap();
+        request.groups(false);
+        match Font::with_fields(request).load_ufo(&dir) {
+            Err(Error::InvalidKerning(issues)) => {
+                assert!(matches!(issues.as_slice(), [KerningIssue::WrongSide { .. }]))
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn kerning_groups() {
+        let mut font = Font::new();
+        font.groups = Some(btreemap! {
+            "public.kern1.O".into() => vec!["O".into(), "D".into()],
+            "public.kern2.O".into() => vec!["O".into()],
+            "round".into() => vec!["O".into()],
+        });
+        font.kerning = Some(btreemap! {
+            "public.kern1.O".into() => btreemap! { "public.kern2.O".into() => -10. },
+            "A".into() => btreemap! { "public.kern2.O".into() => -20. },
+        });
+
+        assert_eq!(font.kerning_group("O", KerningSide::First), Some("public.kern1.O"));
+        assert_eq!(font.kerning_group("O", KerningSide::Second), Some("public.kern2.O"));
+        assert_eq!(font.kerning_group("D", KerningSide::Second), None);
+        assert_eq!(font.kerning_index().group("D", KerningSide::First), Some("public.kern1.O"));
+
+        let moved = font.set_kerning_group("D", KerningSide::First, Some("public.kern1.D"));
+        assert_eq!(moved.unwrap().as_deref(), Some("public.kern1.O"));
+        let groups = font.groups.as_ref().unwrap();
+        assert_eq!(groups["public.kern1.O"], vec![GlyphName::from("O")]);
+        assert_eq!(groups["public.kern1.D"], vec![GlyphName::from("D")]);
+        assert_eq!(
+            font.set_kerning_group("D", KerningSide::First, None).unwrap().as_deref(),
+            Some("public.kern1.D")
+        );
+        assert_eq!(font.kerning_group("D", KerningSide::First), None);
+        assert!(matches!(
+            font.set_kerning_group("D", KerningSide::First, Some("public.kern2.D")),
+            Err(Error::KerningGroup(KerningGroupError::WrongSide(_)))
+        ));
+
+        font.rename_kerning_group("public.kern2.O", "public.kern2.round").unwrap();
+        assert_eq!(font.kerning_group("O", KerningSide::Second), Some("public.kern2.round"));
+        assert_eq!(font.kerning_value("A", "O").unwrap().second, "public.kern2.round");
+        assert_eq!(font.kerning_value("O", "O").unwrap().value, -10.);
+        font.rename_kerning_group("public.kern1.O", "public.kern1.round").unwrap();
+        assert_eq!(font.kerning_value("O", "O").unwrap().first, "public.kern1.round");
+
+        let rename = |font: &mut Font, old, new| match font.rename_kerning_group(old, new) {
+            Err(Error::KerningGroup(e)) => e,
+            other => panic!("unexpected result {:?}", other),
+        };
+        assert_eq!(
+            rename(&mut font, "public.kern1.round", "public.kern2.round"),
+            KerningGroupError::WrongSide("public.kern2.round".into())
+        );
+        assert_eq!(
+            rename(&mut font, "public.kern1.O", "public.kern1.P"),
+            KerningGroupError::MissingGroup("public.kern1.O".into())
+        );
+        assert_eq!(
+            rename(&mut font, "public.kern1.round", "public.kern1.D"),
+            KerningGroupError::DuplicateGroup("public.kern1.D".into())
+        );
+        assert_eq!(
+            rename(&mut font, "round", "public.kern1.O"),
+            KerningGroupError::WrongSide("round".into())
+        );
+
+        // Failing, or doing nothing, leaves a font without groups alone.
+        let mut font = Font::new();
+        assert_eq!(
+            rename(&mut font, "public.kern1.O", "public.kern1.P"),
+            KerningGroupError::MissingGroup("public.kern1.O".into())
+        );
+        assert_eq!(font.set_kerning_group("O", KerningSide::First, None).unwrap(), None);
+        assert_eq!(font.groups, None);
+        font.set_kerning_group("O", KerningSide::First, Some("public.kern1.O")).unwrap();
+        assert_eq!(font.kerning_group("O", KerningSide::First), Some("public.kern1.O"));
+    }
+
+    #[test]
+    fn clean_up() {
+        let mut font = Font::new();
+        for name in &["A", "V", "T"] {
+            font.default_layer_mut().insert_glyph(crate::Glyph::new_named(*name));
+        }
+        font.groups = Some(btreemap! {
+            "public.kern1.T".into() => vec!["T".into()],
+            "public.kern2.o".into() => vec![],
+        });
+        font.kerning = Some(btreemap! {
+            "A".into() => btreemap! { "V".into() => -40.4, "W".into() => -30., "T".into() => 0. },
+            "Aacute".into() => btreemap! { "V".into() => -40. },
+            "public.kern1.T".into() => btreemap! {
+                "public.kern2.o".into() => -60.,
+                "public.kern2.a".into() => -50.,
+                "A".into() => -20.6,
+            },
+        });
+
+        assert_eq!(font.prune_kerning(), 3);
+        assert_eq!(font.remove_zero_pairs(), 1);
+        assert_eq!(font.remove_empty_groups(), vec!["public.kern2.o".to_string()]);
+        assert_eq!(
+            font.kerning,
+            Some(btreemap! {
+                "A".into() => btreemap! { "V".into() => -40.4 },
+                "public.kern1.T".into() => btreemap! { "A".into() => -20.6 },
+            })
+        );
+        assert_eq!(font.groups.as_ref().unwrap().len(), 1);
+
+        font.scale_kerning(2.0);
+        font.round_coordinates(&crate::RoundingOptions::default());
+        assert_eq!(
+            font.kerning,
+            Some(btreemap! {
+                "A".into() => btreemap! { "V".into() => -81. },
+                "public.kern1.T".into() => btreemap! { "A".into() => -41. },
+            })
+        );
+        let kerning = font.kerning.as_mut().unwrap();
+        kerning.get_mut("A").unwrap().insert("W".into(), -4.);
+        round_kerning(kerning, 10.0);
+        assert_eq!(kerning["A"]["V"], -80.);
+        assert_eq!(kerning["A"]["W"].to_string(), "0");
+        kerning.get_mut("A").unwrap().remove("W");
+        scale_kerning(kerning, 0.5);
+        assert_eq!(kerning["A"]["V"], -40.);
+    }
+}
@@ -1,5 +1,5 @@
 use std::borrow::Borrow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -11,7 +11,7 @@ use rayon::prelude::*;
 use crate::glyph::GlyphName;
 use crate::names::NameList;
 use crate::shared_types::Color;
-use crate::{Error, Glyph, Plist};
+use crate::{Error, Glyph, Plist, RoundingOptions};
 
 static CONTENTS_FILE: &str = "contents.plist";
 static LAYER_INFO_FILE: &str = "layerinfo.plist";
@@ -121,6 +121,11 @@ impl LayerSet {
         self.layers.iter()
     }
 
+    /// Iterate over all layers, mutably.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Layer> {
+        self.layers.iter_mut()
+    }
+
     /// Iterate over the names of all layers.
     pub fn names(&self) -> impl Iterator<Item = &LayerName> {
         self.layers.iter().map(|l| &l.name)
@@ -454,6 +459,61 @@ impl Layer {
         self.glyphs.values_mut().map(Arc::make_mut)
     }
 
+    /// Convert the cubic curves of all glyphs to quadratic splines.
+    ///
+    /// See [`Contour::convert_to_quadratic`] for details. Glyphs converted
+    /// before an error occurs stay converted.
+    ///
+    /// [`Contour::convert_to_quadratic`]: crate::Contour::convert_to_quadratic
+    pub fn convert_to_quadratic(&mut self, max_error: f64) -> Result<(), Error> {
+        Layer::convert_to_quadratic_compatible(&mut [self], max_error)
+    }
+
+    /// Round the coordinates of all glyphs to a grid.
+    ///
+    /// See [`Glyph::round_coordinates`] for details.
+    pub fn round_coordinates(&mut self, options: &RoundingOptions) {
+        for glyph in self.iter_mut() {
+            glyph.round_coordinates(options);
+        }
+    }
+
+    /// Convert the quadratic curves of all glyphs to cubic curves.
+    ///
+    /// See [`Contour::convert_to_cubic`] for details. Glyphs converted before
+    /// an error occurs stay converted.
+    ///
+    /// [`Contour::convert_to_cubic`]: crate::Contour::convert_to_cubic
+    pub fn convert_to_cubic(&mut self) -> Result<(), Error> {
+        for glyph in self.iter_mut() {
+            glyph
+                .convert_to_cubic()
+                .map_err(|inner| Error::ConvertCurves { glyph: glyph.name.to_string(), inner })?;
+        }
+        Ok(())
+    }
+
+    /// Convert the cubic curves of several master layers to quadratic splines,
+    /// keeping them compatible for interpolation.
+    ///
+    /// Each glyph is converted together with the glyphs of the same name in
+    /// the other layers, as with [`Glyph::convert_to_quadratic_compatible`].
+    /// Glyphs converted before an error occurs stay converted.
+    pub fn convert_to_quadratic_compatible(
+        layers: &mut [&mut Layer],
+        max_error: f64,
+    ) -> Result<(), Error> {
+        let names: BTreeSet<GlyphName> =
+            layers.iter().flat_map(|layer| layer.glyphs.keys().cloned()).collect();
+        for name in names {
+            let mut glyphs: Vec<&mut Glyph> =
+                layers.iter_mut().filter_map(|layer| layer.get_glyph_mut(&*name)).collect();
+            Glyph::convert_to_quadratic_compatible(&mut glyphs, max_error)
+                .map_err(|inner| Error::ConvertCurves { glyph: name.to_string(), inner })?;
+        }
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn get_path(&self, name: &str) -> Option<&Path> {
         self.contents.get(name).map(PathBuf::as_path)
@@ -585,4 +645,36 @@ mod tests {
             vec!["A".to_string()]
         );
     }
+
+    #[test]
+    fn convert_layers_to_quadratic() {
+        let period = Glyph::load("testdata/sample_period.glif").unwrap();
+        let mut bold_period = period.clone();
+        for point in bold_period.contours.iter_mut().flat_map(|c| c.points.iter_mut()) {
+            point.x *= 1.5;
+        }
+        let mut light = Layer::default();
+        light.insert_glyph(period);
+        let mut bold = Layer::default();
+        bold.insert_glyph(bold_period);
+        // A glyph only present in one master is converted on its own.
+        let mut sparse = Glyph::load("testdata/sample_period.glif").unwrap();
+        sparse.name = "sparse".into();
+        bold.insert_glyph(sparse);
+
+        Layer::convert_to_quadratic_compatible(&mut [&mut light, &mut bold], 1.0).unwrap();
+        let point_types = |layer: &Layer, name: &str| -> Vec<_> {
+            let glyph = layer.get_glyph(name).unwrap();
+            glyph.contours[0].points.iter().map(|p| p.typ.clone()).collect()
+        };
+        assert_eq!(point_types(&light, "period"), point_types(&bold, "period"));
+        assert!(!point_types(&bold, "sparse").contains(&crate::PointType::Curve));
+
+        let mut incompatible = Glyph::new_named("period");
+        incompatible.contours.push(Default::default());
+        incompatible.contours.push(Default::default());
+        light.insert_glyph(incompatible);
+        let err = Layer::convert_to_quadratic_compatible(&mut [&mut light, &mut bold], 1.0);
+        assert!(matches!(err, Err(Error::ConvertCurves { glyph, .. }) if glyph == "period"));
+    }
 }
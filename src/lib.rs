@@ -21,27 +21,36 @@ extern crate serde_derive;
 #[macro_use]
 extern crate serde_repr;
 
+pub mod boolean;
+pub mod compat;
+pub mod designspace;
 pub mod error;
+pub mod features;
 pub mod fontinfo;
 mod glyph;
 mod guideline;
 mod identifier;
+mod interpolate;
+pub mod kerning;
 mod layer;
 mod names;
+pub mod pen;
 mod shared_types;
 mod ufo;
 mod upconversion;
 pub mod util;
+pub mod varmodel;
 
 pub use error::Error;
 pub use fontinfo::FontInfo;
 pub use glyph::{
     AffineTransform, Anchor, Component, Contour, ContourPoint, GlifVersion, Glyph, GlyphName,
-    Image, PointType,
+    Image, PointType, RoundingOptions,
 };
 pub use guideline::{Guideline, Line};
 pub use identifier::Identifier;
 pub use layer::{Layer, LayerSet};
+pub use pen::{Pen, PointPen};
 pub use shared_types::{Color, IntegerOrFloat, NonNegativeIntegerOrFloat, Plist};
 pub use ufo::{DataRequest, Font, FormatVersion, MetaInfo};
 
@@ -0,0 +1,655 @@
+//! Pens, for drawing glyph outlines.
+//!
+//! This module provides two drawing protocols, modeled after the ones
+//! found in [fontTools]:
+//!
+//! - a [`PointPen`] receives the outline point by point, exactly as it is
+//!   stored in a [`Contour`]. This is lossless.
+//! - a [`Pen`] receives the outline segment by segment (`move_to`, `line_to`,
+//!   `curve_to`, ...), which is what most renderers and path libraries expect.
+//!
+//! [`PointToSegmentPen`] and [`SegmentToPointPen`] convert between the two,
+//! and a [`GlyphPointPen`] appends whatever is drawn into it to a [`Glyph`].
+//!
+//! [fontTools]: https://fonttools.readthedocs.io/en/latest/pens/index.html
+//! [`Contour`]: crate::Contour
+
+use crate::error::ErrorKind;
+use crate::glyph::builder::OutlineBuilder;
+use crate::{AffineTransform, Glyph, GlyphName, Identifier, PointType};
+
+/// A contour being buffered by one of the adapter pens.
+type PointBuffer = Vec<((f32, f32), PointType)>;
+
+/// A pen that receives outlines one point at a time.
+///
+/// The methods mirror the structure of a `.glif` outline: each contour is
+/// bracketed by [`begin_path`] and [`end_path`], with the points added in
+/// between. Components are added separately.
+///
+/// [`begin_path`]: PointPen::begin_path
+/// [`end_path`]: PointPen::end_path
+pub trait PointPen {
+    /// Start a new contour.
+    fn begin_path(&mut self, identifier: Option<Identifier>) -> Result<(), ErrorKind>;
+
+    /// End the current contour.
+    fn end_path(&mut self) -> Result<(), ErrorKind>;
+
+    /// Add a point to the current contour.
+    fn add_point(
+        &mut self,
+        point: (f32, f32),
+        segment_type: PointType,
+        smooth: bool,
+        name: Option<String>,
+        identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind>;
+
+    /// Add a sub-glyph.
+    fn add_component(
+        &mut self,
+        base: GlyphName,
+        transform: AffineTransform,
+        identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind>;
+}
+
+/// A pen that receives outlines one segment at a time.
+pub trait Pen {
+    /// Begin a new sub-path at `point`.
+    fn move_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind>;
+
+    /// Draw a straight line from the current point to `point`.
+    fn line_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind>;
+
+    /// Draw a cubic bezier from the current point to `point`.
+    fn curve_to(
+        &mut self,
+        control1: (f32, f32),
+        control2: (f32, f32),
+        point: (f32, f32),
+    ) -> Result<(), ErrorKind>;
+
+    /// Draw a sequence of quadratic curves from the current point, using the
+    /// TrueType "implied on-curve point" rule between consecutive off-curves.
+    ///
+    /// `on_curve` is `None` only for a closed contour made entirely of
+    /// off-curve points, in which case no `move_to` precedes this call.
+    fn qcurve_to(
+        &mut self,
+        off_curves: &[(f32, f32)],
+        on_curve: Option<(f32, f32)>,
+    ) -> Result<(), ErrorKind>;
+
+    /// Close the current sub-path, drawing a line back to its start if needed.
+    fn close_path(&mut self) -> Result<(), ErrorKind>;
+
+    /// End the current sub-path without closing it.
+    fn end_path(&mut self) -> Result<(), ErrorKind>;
+
+    /// Add a sub-glyph.
+    fn add_component(
+        &mut self,
+        base: GlyphName,
+        transform: AffineTransform,
+    ) -> Result<(), ErrorKind>;
+}
+
+impl<T: PointPen + ?Sized> PointPen for &mut T {
+    fn begin_path(&mut self, identifier: Option<Identifier>) -> Result<(), ErrorKind> {
+        (**self).begin_path(identifier)
+    }
+
+    fn end_path(&mut self) -> Result<(), ErrorKind> {
+        (**self).end_path()
+    }
+
+    fn add_point(
+        &mut self,
+        point: (f32, f32),
+        segment_type: PointType,
+        smooth: bool,
+        name: Option<String>,
+        identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind> {
+        (**self).add_point(point, segment_type, smooth, name, identifier)
+    }
+
+    fn add_component(
+        &mut self,
+        base: GlyphName,
+        transform: AffineTransform,
+        identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind> {
+        (**self).add_component(base, transform, identifier)
+    }
+}
+
+impl<T: Pen + ?Sized> Pen for &mut T {
+    fn move_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind> {
+        (**self).move_to(point)
+    }
+
+    fn line_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind> {
+        (**self).line_to(point)
+    }
+
+    fn curve_to(
+        &mut self,
+        control1: (f32, f32),
+        control2: (f32, f32),
+        point: (f32, f32),
+    ) -> Result<(), ErrorKind> {
+        (**self).curve_to(control1, control2, point)
+    }
+
+    fn qcurve_to(
+        &mut self,
+        off_curves: &[(f32, f32)],
+        on_curve: Option<(f32, f32)>,
+    ) -> Result<(), ErrorKind> {
+        (**self).qcurve_to(off_curves, on_curve)
+    }
+
+    fn close_path(&mut self) -> Result<(), ErrorKind> {
+        (**self).close_path()
+    }
+
+    fn end_path(&mut self) -> Result<(), ErrorKind> {
+        (**self).end_path()
+    }
+
+    fn add_component(
+        &mut self,
+        base: GlyphName,
+        transform: AffineTransform,
+    ) -> Result<(), ErrorKind> {
+        (**self).add_component(base, transform)
+    }
+}
+
+/// Adapts a [`Pen`] so that it can be drawn into with the [`PointPen`] protocol.
+///
+/// Point names, identifiers and smooth flags have no equivalent in the segment
+/// protocol and are dropped.
+#[derive(Debug)]
+pub struct PointToSegmentPen<P> {
+    pen: P,
+    points: Option<PointBuffer>,
+}
+
+impl<P: Pen> PointToSegmentPen<P> {
+    /// Create a new adapter, drawing into `pen`.
+    pub fn new(pen: P) -> Self {
+        PointToSegmentPen { pen, points: None }
+    }
+
+    /// Consume the adapter and return the wrapped pen.
+    pub fn into_inner(self) -> P {
+        self.pen
+    }
+
+    fn flush(&mut self, points: PointBuffer) -> Result<(), ErrorKind> {
+        if points.is_empty() {
+            return Ok(());
+        }
+
+        let closed = points[0].1 != PointType::Move;
+        let points = if closed {
+            // Rotate the points so that the last one is on-curve; that is where
+            // the pen starts drawing.
+            match points.iter().position(|(_, typ)| *typ != PointType::OffCurve) {
+                Some(idx) => {
+                    let mut points = points;
+                    points.rotate_left(idx + 1);
+                    points
+                }
+                None => {
+                    // A closed contour of only off-curves, TrueType-style.
+                    let off_curves: Vec<_> = points.iter().map(|(pt, _)| *pt).collect();
+                    self.pen.qcurve_to(&off_curves, None)?;
+                    return self.pen.close_path();
+                }
+            }
+        } else {
+            points
+        };
+
+        let start = if closed { points[points.len() - 1].0 } else { points[0].0 };
+        self.pen.move_to(start)?;
+
+        let segments = if closed { &points[..] } else { &points[1..] };
+        let mut off_curves = Vec::new();
+        for (idx, (pt, typ)) in segments.iter().enumerate() {
+            match typ {
+                PointType::OffCurve => off_curves.push(*pt),
+                // A line closing the contour is implied by `close_path`.
+                PointType::Line if closed && idx == segments.len() - 1 => (),
+                PointType::Line => self.pen.line_to(*pt)?,
+                PointType::Curve => match off_curves.as_slice() {
+                    [] => self.pen.line_to(*pt)?,
+                    [c] => self.pen.qcurve_to(&[*c], Some(*pt))?,
+                    [c1, c2] => self.pen.curve_to(*c1, *c2, *pt)?,
+                    _ => return Err(ErrorKind::TooManyOffCurves),
+                },
+                PointType::QCurve => self.pen.qcurve_to(&off_curves, Some(*pt))?,
+                PointType::Move => return Err(ErrorKind::UnexpectedMove),
+            }
+            if *typ != PointType::OffCurve {
+                off_curves.clear();
+            }
+        }
+        if !off_curves.is_empty() {
+            return Err(ErrorKind::TrailingOffCurves);
+        }
+
+        if closed {
+            self.pen.close_path()
+        } else {
+            self.pen.end_path()
+        }
+    }
+}
+
+impl<P: Pen> PointPen for PointToSegmentPen<P> {
+    fn begin_path(&mut self, _identifier: Option<Identifier>) -> Result<(), ErrorKind> {
+        if self.points.is_some() {
+            return Err(ErrorKind::UnfinishedDrawing);
+        }
+        self.points = Some(Vec::new());
+        Ok(())
+    }
+
+    fn end_path(&mut self) -> Result<(), ErrorKind> {
+        let points = self.points.take().ok_or(ErrorKind::PenPathNotStarted)?;
+        self.flush(points)
+    }
+
+    fn add_point(
+        &mut self,
+        point: (f32, f32),
+        segment_type: PointType,
+        _smooth: bool,
+        _name: Option<String>,
+        _identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind> {
+        let points = self.points.as_mut().ok_or(ErrorKind::PenPathNotStarted)?;
+        points.push((point, segment_type));
+        Ok(())
+    }
+
+    fn add_component(
+        &mut self,
+        base: GlyphName,
+        transform: AffineTransform,
+        _identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind> {
+        self.pen.add_component(base, transform)
+    }
+}
+
+/// Adapts a [`PointPen`] so that it can be drawn into with the [`Pen`] protocol.
+///
+/// All points are added with the smooth flag unset.
+#[derive(Debug)]
+pub struct SegmentToPointPen<P> {
+    pen: P,
+    points: Option<PointBuffer>,
+}
+
+impl<P: PointPen> SegmentToPointPen<P> {
+    /// Create a new adapter, drawing into `pen`.
+    pub fn new(pen: P) -> Self {
+        SegmentToPointPen { pen, points: None }
+    }
+
+    /// Consume the adapter and return the wrapped pen.
+    pub fn into_inner(self) -> P {
+        self.pen
+    }
+
+    fn current(&mut self) -> Result<&mut PointBuffer, ErrorKind> {
+        self.points.as_mut().ok_or(ErrorKind::PenPathNotStarted)
+    }
+
+    fn flush(&mut self, points: PointBuffer) -> Result<(), ErrorKind> {
+        self.pen.begin_path(None)?;
+        for (pt, typ) in points {
+            self.pen.add_point(pt, typ, false, None, None)?;
+        }
+        self.pen.end_path()
+    }
+}
+
+impl<P: PointPen> Pen for SegmentToPointPen<P> {
+    fn move_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind> {
+        if self.points.is_some() {
+            return Err(ErrorKind::UnfinishedDrawing);
+        }
+        self.points = Some(vec![(point, PointType::Move)]);
+        Ok(())
+    }
+
+    fn line_to(&mut self, point: (f32, f32)) -> Result<(), ErrorKind> {
+        self.current()?.push((point, PointType::Line));
+        Ok(())
+    }
+
+    fn curve_to(
+        &mut self,
+        control1: (f32, f32),
+        control2: (f32, f32),
+        point: (f32, f32),
+    ) -> Result<(), ErrorKind> {
+        let points = self.current()?;
+        points.push((control1, PointType::OffCurve));
+        points.push((control2, PointType::OffCurve));
+        points.push((point, PointType::Curve));
+        Ok(())
+    }
+
+    fn qcurve_to(
+        &mut self,
+        off_curves: &[(f32, f32)],
+        on_curve: Option<(f32, f32)>,
+    ) -> Result<(), ErrorKind> {
+        if on_curve.is_none() {
+            if self.points.is_some() {
+                return Err(ErrorKind::UnfinishedDrawing);
+            }
+            self.points = Some(Vec::new());
+        }
+        let points = self.current()?;
+        points.extend(off_curves.iter().map(|pt| (*pt, PointType::OffCurve)));
+        if let Some(pt) = on_curve {
+            points.push((pt, PointType::QCurve));
+        }
+        Ok(())
+    }
+
+    fn close_path(&mut self) -> Result<(), ErrorKind> {
+        let mut points = self.points.take().ok_or(ErrorKind::PenPathNotStarted)?;
+        if points.first().map(|(_, typ)| *typ == PointType::Move).unwrap_or(false) {
+            if points.len() > 1 && points[0].0 == points[points.len() - 1].0 {
+                // The last segment ends where we started; it replaces the move.
+                points.remove(0);
+            } else {
+                // There is an implied line back to the start.
+                points[0].1 = PointType::Line;
+            }
+        }
+        self.flush(points)
+    }
+
+    fn end_path(&mut self) -> Result<(), ErrorKind> {
+        let points = self.points.take().ok_or(ErrorKind::PenPathNotStarted)?;
+        self.flush(points)
+    }
+
+    fn add_component(
+        &mut self,
+        base: GlyphName,
+        transform: AffineTransform,
+    ) -> Result<(), ErrorKind> {
+        self.pen.add_component(base, transform, None)
+    }
+}
+
+/// A [`PointPen`] that adds everything drawn into it to a [`Glyph`].
+///
+/// The outline is validated the same way as when loading a `.glif` file. Nothing
+/// is added to the glyph until [`GlyphPointPen::finish`] is called.
+///
+/// # Example
+///
+/// ```
+/// use norad::pen::{GlyphPointPen, PointPen};
+/// use norad::{Glyph, PointType};
+///
+/// let mut glyph = Glyph::new_named("period");
+/// let mut pen = GlyphPointPen::new(&mut glyph);
+/// pen.begin_path(None).unwrap();
+/// pen.add_point((0.0, 0.0), PointType::Line, false, None, None).unwrap();
+/// pen.add_point((100.0, 0.0), PointType::Line, false, None, None).unwrap();
+/// pen.add_point((100.0, 100.0), PointType::Line, false, None, None).unwrap();
+/// pen.end_path().unwrap();
+/// pen.finish().unwrap();
+///
+/// assert_eq!(glyph.contours.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct GlyphPointPen<'a> {
+    glyph: &'a mut Glyph,
+    builder: OutlineBuilder,
+}
+
+impl<'a> GlyphPointPen<'a> {
+    /// Create a new pen that draws into `glyph`.
+    pub fn new(glyph: &'a mut Glyph) -> Self {
+        GlyphPointPen { glyph, builder: OutlineBuilder::new() }
+    }
+
+    /// Append the drawn contours and components to the glyph.
+    ///
+    /// Errors if a path has been begun but not ended.
+    pub fn finish(self) -> Result<(), ErrorKind> {
+        let (mut outline, _) = self.builder.finish()?;
+        self.glyph.contours.append(&mut outline.contours);
+        self.glyph.components.append(&mut outline.components);
+        Ok(())
+    }
+}
+
+impl PointPen for GlyphPointPen<'_> {
+    fn begin_path(&mut self, identifier: Option<Identifier>) -> Result<(), ErrorKind> {
+        self.builder.begin_path(identifier).map(|_| ())
+    }
+
+    fn end_path(&mut self) -> Result<(), ErrorKind> {
+        self.builder.end_path().map(|_| ())
+    }
+
+    fn add_point(
+        &mut self,
+        point: (f32, f32),
+        segment_type: PointType,
+        smooth: bool,
+        name: Option<String>,
+        identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind> {
+        self.builder.add_point(point, segment_type, smooth, name, identifier).map(|_| ())
+    }
+
+    fn add_component(
+        &mut self,
+        base: GlyphName,
+        transform: AffineTransform,
+        identifier: Option<Identifier>,
+    ) -> Result<(), ErrorKind> {
+        self.builder.add_component(base, transform, identifier).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Contour, ContourPoint};
+
+    /// Records segment pen calls as strings.
+    #[derive(Debug, Default)]
+    struct RecordingPen(Vec<String>);
+
+    impl Pen for RecordingPen {
+        fn move_to(&mut self, (x, y): (f32, f32)) -> Result<(), ErrorKind> {
+            self.0.push(format!("M {} {}", x, y));
+            Ok(())
+        }
+
+        fn line_to(&mut self, (x, y): (f32, f32)) -> Result<(), ErrorKind> {
+            self.0.push(format!("L {} {}", x, y));
+            Ok(())
+        }
+
+        fn curve_to(
+            &mut self,
+            (x1, y1): (f32, f32),
+            (x2, y2): (f32, f32),
+            (x, y): (f32, f32),
+        ) -> Result<(), ErrorKind> {
+            self.0.push(format!("C {} {} {} {} {} {}", x1, y1, x2, y2, x, y));
+            Ok(())
+        }
+
+        fn qcurve_to(
+            &mut self,
+            off_curves: &[(f32, f32)],
+            on_curve: Option<(f32, f32)>,
+        ) -> Result<(), ErrorKind> {
+            let mut s = String::from("Q");
+            for (x, y) in off_curves.iter().chain(on_curve.iter()) {
+                s.push_str(&format!(" {} {}", x, y));
+            }
+            self.0.push(s);
+            Ok(())
+        }
+
+        fn close_path(&mut self) -> Result<(), ErrorKind> {
+            self.0.push("Z".into());
+            Ok(())
+        }
+
+        fn end_path(&mut self) -> Result<(), ErrorKind> {
+            self.0.push("E".into());
+            Ok(())
+        }
+
+        fn add_component(
+            &mut self,
+            base: GlyphName,
+            transform: AffineTransform,
+        ) -> Result<(), ErrorKind> {
+            self.0.push(format!("component {} {}", base, transform.x_offset));
+            Ok(())
+        }
+    }
+
+    fn point(x: f32, y: f32, typ: PointType) -> ContourPoint {
+        ContourPoint::new(x, y, typ, false, None, None, None)
+    }
+
+    fn test_glyph() -> Glyph {
+        let mut glyph = Glyph::new_named("test");
+        glyph.contours.push(Contour::new(
+            vec![
+                point(0.0, 0.0, PointType::Line),
+                point(0.0, 50.0, PointType::OffCurve),
+                point(50.0, 100.0, PointType::OffCurve),
+                point(100.0, 100.0, PointType::Curve),
+                point(100.0, 0.0, PointType::Line),
+            ],
+            None,
+            None,
+        ));
+        glyph.contours.push(Contour::new(
+            vec![
+                point(0.0, 0.0, PointType::Move),
+                point(10.0, 10.0, PointType::OffCurve),
+                point(20.0, 10.0, PointType::OffCurve),
+                point(30.0, 0.0, PointType::QCurve),
+            ],
+            None,
+            None,
+        ));
+        glyph.components.push(crate::Component::new(
+            "a".into(),
+            AffineTransform { x_offset: 5.0, ..Default::default() },
+            None,
+            None,
+        ));
+        glyph
+    }
+
+    #[test]
+    fn draw_segments() {
+        let glyph = test_glyph();
+        let mut pen = RecordingPen::default();
+        glyph.draw(&mut pen).unwrap();
+        assert_eq!(
+            pen.0,
+            vec![
+                "M 0 0",
+                "C 0 50 50 100 100 100",
+                "L 100 0",
+                "Z",
+                "M 0 0",
+                "Q 10 10 20 10 30 0",
+                "E",
+                "component a 5",
+            ]
+        );
+    }
+
+    #[test]
+    fn draw_only_off_curves() {
+        let mut glyph = Glyph::new_named("o");
+        glyph.contours.push(Contour::new(
+            vec![
+                point(0.0, 0.0, PointType::OffCurve),
+                point(0.0, 10.0, PointType::OffCurve),
+                point(10.0, 10.0, PointType::OffCurve),
+                point(10.0, 0.0, PointType::OffCurve),
+            ],
+            None,
+            None,
+        ));
+        let mut pen = RecordingPen::default();
+        glyph.draw(&mut pen).unwrap();
+        assert_eq!(pen.0, vec!["Q 0 0 0 10 10 10 10 0", "Z"]);
+    }
+
+    #[test]
+    fn point_pen_roundtrip() {
+        let glyph = test_glyph();
+        let mut new_glyph = Glyph::new_named("test");
+        let mut pen = GlyphPointPen::new(&mut new_glyph);
+        glyph.draw_points(&mut pen).unwrap();
+        pen.finish().unwrap();
+        assert_eq!(glyph, new_glyph);
+    }
+
+    #[test]
+    fn segment_pen_roundtrip() {
+        let glyph = test_glyph();
+        let mut new_glyph = Glyph::new_named("test");
+        let mut pen = SegmentToPointPen::new(GlyphPointPen::new(&mut new_glyph));
+        glyph.draw(&mut pen).unwrap();
+        pen.into_inner().finish().unwrap();
+
+        // Smooth flags, names and identifiers are lost, but there are none here.
+        assert_eq!(new_glyph, glyph);
+    }
+
+    #[test]
+    fn segment_pen_implied_closing_line() {
+        let mut glyph = Glyph::new_named("triangle");
+        let mut pen = SegmentToPointPen::new(GlyphPointPen::new(&mut glyph));
+        pen.move_to((0.0, 0.0)).unwrap();
+        pen.line_to((10.0, 0.0)).unwrap();
+        pen.line_to((10.0, 10.0)).unwrap();
+        pen.close_path().unwrap();
+        pen.into_inner().finish().unwrap();
+
+        let types: Vec<_> = glyph.contours[0].points.iter().map(|p| p.typ.clone()).collect();
+        assert_eq!(types, vec![PointType::Line, PointType::Line, PointType::Line]);
+    }
+
+    #[test]
+    fn unfinished_path() {
+        let mut glyph = Glyph::new_named("test");
+        let mut pen = GlyphPointPen::new(&mut glyph);
+        pen.begin_path(None).unwrap();
+        assert!(matches!(pen.begin_path(None), Err(ErrorKind::UnfinishedDrawing)));
+        assert!(matches!(pen.finish(), Err(ErrorKind::UnfinishedDrawing)));
+    }
+}
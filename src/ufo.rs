@@ -15,18 +15,19 @@ use crate::error::GroupsValidationError;
 use crate::fontinfo::FontInfo;
 use crate::glyph::{Glyph, GlyphName};
 use crate::guideline::Guideline;
+use crate::kerning;
 use crate::layer::{Layer, LayerSet, LAYER_CONTENTS_FILE};
 use crate::names::NameList;
 use crate::shared_types::{Plist, PUBLIC_OBJECT_LIBS_KEY};
 use crate::upconversion;
-use crate::Error;
+use crate::{Error, RoundingOptions};
 
 static METAINFO_FILE: &str = "metainfo.plist";
 static FONTINFO_FILE: &str = "fontinfo.plist";
 static LIB_FILE: &str = "lib.plist";
 static GROUPS_FILE: &str = "groups.plist";
 static KERNING_FILE: &str = "kerning.plist";
-static FEATURES_FILE: &str = "features.fea";
+pub(crate) static FEATURES_FILE: &str = "features.fea";
 static DEFAULT_METAINFO_CREATOR: &str = "org.linebender.norad";
 
 /// Groups is a map of group name to a list of glyph names. It's a BTreeMap because we need sorting
@@ -71,11 +72,22 @@ pub struct DataRequest {
     pub groups: bool,
     pub kerning: bool,
     pub features: bool,
+    /// Whether to check the kerning against the groups and glyphs when
+    /// loading and saving, failing with [`Error::InvalidKerning`] if there are
+    /// problems. This is off by default.
+    pub validate_kerning: bool,
 }
 
 impl DataRequest {
     fn from_bool(b: bool) -> Self {
-        DataRequest { layers: b, lib: b, groups: b, kerning: b, features: b }
+        DataRequest {
+            layers: b,
+            lib: b,
+            groups: b,
+            kerning: b,
+            features: b,
+            validate_kerning: false,
+        }
     }
 
     /// Returns a `DataRequest` requesting all UFO data.
@@ -118,6 +130,15 @@ impl DataRequest {
         self.features = b;
         self
     }
+
+    /// Request that the kerning be checked against the groups and glyphs
+    /// when loading and saving.
+    ///
+    /// See [`Font::validate_kerning`] for what is checked.
+    pub fn validate_kerning(&mut self, b: bool) -> &mut Self {
+        self.validate_kerning = b;
+        self
+    }
 }
 
 impl Default for DataRequest {
@@ -275,7 +296,7 @@ impl Font {
 
             meta.format_version = FormatVersion::V3;
 
-            Ok(Font {
+            let font = Font {
                 layers,
                 meta,
                 font_info,
@@ -284,7 +305,11 @@ impl Font {
                 kerning,
                 features,
                 data_request: ufo.data_request,
-            })
+            };
+            if self.data_request.validate_kerning {
+                font.check_kerning()?;
+            }
+            Ok(font)
         };
 
         load_impl(&self, path)
@@ -312,6 +337,10 @@ impl Font {
             return Err(Error::PreexistingPublicObjectLibsKey);
         }
 
+        if self.data_request.validate_kerning {
+            self.check_kerning()?;
+        }
+
         if path.exists() {
             fs::remove_dir_all(path)?;
         }
@@ -428,6 +457,19 @@ impl Font {
         self.default_layer().len()
     }
 
+    /// Round the coordinates of the glyphs in all layers to a grid, and
+    /// optionally the kerning values.
+    ///
+    /// See [`Glyph::round_coordinates`] for details.
+    pub fn round_coordinates(&mut self, options: &RoundingOptions) {
+        for layer in self.layers.iter_mut() {
+            layer.round_coordinates(options);
+        }
+        if let (true, Some(kerning)) = (options.kerning, self.kerning.as_mut()) {
+            kerning::round_kerning(kerning, options.grid);
+        }
+    }
+
     /// Return the font's global guidelines, stored in [`FontInfo`].
     pub fn guidelines(&self) -> &[Guideline] {
         self.font_info.as_ref().and_then(|info| info.guidelines.as_deref()).unwrap_or(&[])
@@ -0,0 +1,595 @@
+//! The OpenType variation model.
+//!
+//! This is a port of the `VariationModel` of fontTools' [varLib]. Given the
+//! normalized locations of a set of masters, it works out the region of the
+//! design space each master's delta applies to, and how much.
+//!
+//! Any value that can be summed with weights can be varied with a model; the
+//! [`Variable`] trait is implemented for numbers, [`Glyph`]s (which covers
+//! their points and anchors), [`Kerning`], [`FontInfo`] metrics and whole
+//! [`Font`]s.
+//!
+//! ```
+//! use norad::varmodel::{NormalizedLocation, VariationModel};
+//!
+//! let location = |wght: f64| -> NormalizedLocation {
+//!     std::iter::once(("wght".to_string(), wght)).collect()
+//! };
+//! let locations = [location(0.0), location(1.0), location(0.5)];
+//! let model = VariationModel::new(&locations, &["wght".into()]).unwrap();
+//!
+//! // The deltas are in sorted order, with the middle master before the bold
+//! // one; the middle master's delta does not reach the bold master.
+//! let deltas = model.deltas(&[&100.0, &300.0, &250.0]).unwrap();
+//! assert_eq!(deltas, vec![100.0, 150.0, 200.0]);
+//! let value: f64 = model.interpolate_from_deltas(&location(0.25), &deltas).unwrap();
+//! assert_eq!(value, 175.0);
+//! ```
+//!
+//! [varLib]: https://github.com/fonttools/fonttools/blob/main/Lib/fontTools/varLib/models.py
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::error::{InterpolationError, VariationModelError};
+use crate::kerning::Kerning;
+use crate::{Font, FontInfo, Glyph};
+
+/// A location with each axis normalized to the range -1 to 1, where 0 is the
+/// default. Axes that are not mentioned are at their default.
+pub type NormalizedLocation = BTreeMap<String, f64>;
+
+/// The region of the design space a master's delta applies to, as the
+/// lower bound, peak and upper bound on each axis.
+pub type Support = BTreeMap<String, (f64, f64, f64)>;
+
+/// A variation model over a set of master locations.
+#[derive(Debug, Clone)]
+pub struct VariationModel {
+    /// The locations, sorted so that each master only depends on the ones
+    /// before it. The base master comes first.
+    locations: Vec<NormalizedLocation>,
+    /// The index in the original order of each sorted location.
+    reverse_mapping: Vec<usize>,
+    supports: Vec<Support>,
+    /// For each master, the weights of the deltas of earlier masters at its
+    /// location.
+    delta_weights: Vec<Vec<(usize, f64)>>,
+}
+
+impl VariationModel {
+    /// Create a model for masters at the given locations.
+    ///
+    /// One of the locations must be the default location. Axes are ordered
+    /// as in `axis_order`, and then by name.
+    pub fn new(
+        locations: &[NormalizedLocation],
+        axis_order: &[String],
+    ) -> Result<VariationModel, VariationModelError> {
+        let locations: Vec<NormalizedLocation> = locations
+            .iter()
+            .map(|loc| {
+                loc.iter().filter(|(_, v)| **v != 0.0).map(|(k, v)| (k.clone(), *v)).collect()
+            })
+            .collect();
+        if !locations.iter().any(BTreeMap::is_empty) {
+            return Err(VariationModelError::MissingBaseMaster);
+        }
+        for (idx, loc) in locations.iter().enumerate() {
+            if locations[..idx].contains(loc) {
+                return Err(VariationModelError::DuplicateLocation);
+            }
+        }
+
+        let key = sort_key(&locations, axis_order);
+        let mut order: Vec<usize> = (0..locations.len()).collect();
+        order.sort_by(|a, b| key(&locations[*a]).cmp(&key(&locations[*b])));
+        let locations: Vec<_> = order.iter().map(|idx| locations[*idx].clone()).collect();
+
+        let mut model = VariationModel {
+            supports: compute_supports(&locations),
+            locations,
+            reverse_mapping: order,
+            delta_weights: Vec::new(),
+        };
+        model.delta_weights = model
+            .locations
+            .iter()
+            .enumerate()
+            .map(|(idx, loc)| {
+                model.supports[..idx]
+                    .iter()
+                    .enumerate()
+                    .map(|(j, support)| (j, support_scalar(loc, support)))
+                    .filter(|(_, scalar)| *scalar != 0.0)
+                    .collect()
+            })
+            .collect();
+        Ok(model)
+    }
+
+    /// The master locations, in the order the model sorted them in.
+    pub fn locations(&self) -> &[NormalizedLocation] {
+        &self.locations
+    }
+
+    /// The support of each master, in sorted order.
+    pub fn supports(&self) -> &[Support] {
+        &self.supports
+    }
+
+    /// The index in the original order of each master in sorted order.
+    pub fn reverse_mapping(&self) -> &[usize] {
+        &self.reverse_mapping
+    }
+
+    /// How much the delta of each master applies at `location`, in sorted
+    /// order.
+    pub fn scalars(&self, location: &NormalizedLocation) -> Vec<f64> {
+        self.supports.iter().map(|support| support_scalar(location, support)).collect()
+    }
+
+    /// The weight of each master's value in the value at `location`, in the
+    /// order the master locations were given.
+    pub fn master_weights(&self, location: &NormalizedLocation) -> Vec<f64> {
+        let mut result = vec![0.0; self.locations.len()];
+        for (delta, scalar) in self.delta_coefficients().iter().zip(self.scalars(location)) {
+            if scalar == 0.0 {
+                continue;
+            }
+            for (idx, value) in delta.iter().enumerate() {
+                result[self.reverse_mapping[idx]] += scalar * value;
+            }
+        }
+        result
+    }
+
+    /// Compute the delta of each master from their values, which are in the
+    /// order the master locations were given. The deltas are in sorted
+    /// order; the first is the value of the base master.
+    pub fn deltas<T: Variable>(&self, values: &[&T]) -> Result<Vec<T>, InterpolationError> {
+        if values.len() != self.locations.len() {
+            return Err(InterpolationError::MasterCount {
+                expected: self.locations.len(),
+                found: values.len(),
+            });
+        }
+        self.delta_coefficients()
+            .iter()
+            .enumerate()
+            .map(|(idx, delta)| {
+                // The master's own value goes first, for the parts of the
+                // value that are not summed.
+                let terms: Vec<(&T, f64)> =
+                    std::iter::once((values[self.reverse_mapping[idx]], 1.0))
+                        .chain(
+                            delta.iter().enumerate().filter(|(j, c)| *j != idx && **c != 0.0).map(
+                                |(j, coefficient)| (values[self.reverse_mapping[j]], *coefficient),
+                            ),
+                        )
+                        .collect();
+                T::weighted_sum(&terms)
+            })
+            .collect()
+    }
+
+    /// Compute the value at `location` from the deltas returned by
+    /// [`VariationModel::deltas`].
+    pub fn interpolate_from_deltas<T: Variable>(
+        &self,
+        location: &NormalizedLocation,
+        deltas: &[T],
+    ) -> Result<T, InterpolationError> {
+        let terms: Vec<(&T, f64)> = deltas
+            .iter()
+            .zip(self.scalars(location))
+            .enumerate()
+            .filter(|(idx, (_, scalar))| *idx == 0 || *scalar != 0.0)
+            .map(|(_, term)| term)
+            .collect();
+        T::weighted_sum(&terms)
+    }
+
+    /// Each delta as a combination of master values, all in sorted order.
+    fn delta_coefficients(&self) -> Vec<Vec<f64>> {
+        let count = self.locations.len();
+        let mut deltas: Vec<Vec<f64>> = Vec::with_capacity(count);
+        for (idx, weights) in self.delta_weights.iter().enumerate() {
+            let mut delta = vec![0.0; count];
+            delta[idx] = 1.0;
+            for (j, weight) in weights {
+                for (value, earlier) in delta.iter_mut().zip(&deltas[*j]) {
+                    *value -= weight * earlier;
+                }
+            }
+            deltas.push(delta);
+        }
+        deltas
+    }
+}
+
+/// A value that can be varied by a [`VariationModel`].
+///
+/// Deltas and interpolated values are computed as sums of master values,
+/// each multiplied by a weight; the weights need not add up to one, and may
+/// be negative.
+pub trait Variable: Sized {
+    /// Returns the sum of the values, each multiplied by its weight.
+    ///
+    /// Parts of the value that can't be summed should be taken from the
+    /// first value.
+    fn weighted_sum(values: &[(&Self, f64)]) -> Result<Self, InterpolationError>;
+}
+
+impl Variable for f64 {
+    fn weighted_sum(values: &[(&Self, f64)]) -> Result<Self, InterpolationError> {
+        if values.is_empty() {
+            return Err(InterpolationError::NoMasters);
+        }
+        Ok(values.iter().map(|(value, weight)| **value * weight).sum())
+    }
+}
+
+impl Variable for f32 {
+    fn weighted_sum(values: &[(&Self, f64)]) -> Result<Self, InterpolationError> {
+        if values.is_empty() {
+            return Err(InterpolationError::NoMasters);
+        }
+        Ok(values.iter().map(|(value, weight)| **value as f64 * weight).sum::<f64>() as f32)
+    }
+}
+
+impl Variable for Glyph {
+    fn weighted_sum(values: &[(&Self, f64)]) -> Result<Self, InterpolationError> {
+        Glyph::weighted_sum(values)
+    }
+}
+
+impl Variable for FontInfo {
+    fn weighted_sum(values: &[(&Self, f64)]) -> Result<Self, InterpolationError> {
+        FontInfo::weighted_sum(values)
+    }
+}
+
+impl Variable for Font {
+    fn weighted_sum(values: &[(&Self, f64)]) -> Result<Self, InterpolationError> {
+        Font::weighted_sum(values)
+    }
+}
+
+/// Kerning pairs missing from some of the values count as zero in those.
+impl Variable for Kerning {
+    fn weighted_sum(values: &[(&Self, f64)]) -> Result<Self, InterpolationError> {
+        if values.is_empty() {
+            return Err(InterpolationError::NoMasters);
+        }
+        Ok(crate::interpolate::blend_kerning(values))
+    }
+}
+
+/// Normalize a value on an axis with the given minimum, default and
+/// maximum to the range -1 to 1, with the default at 0. Values outside the
+/// range are clamped to it.
+pub fn normalize_value(value: f64, min: f64, default: f64, max: f64) -> f64 {
+    let value = value.max(min).min(max);
+    if value < default {
+        (value - default) / (default - min)
+    } else if value > default {
+        (value - default) / (max - default)
+    } else {
+        0.0
+    }
+}
+
+/// The sort key of fontTools' `getMasterLocationsSortKeyFunc`: masters on
+/// fewer axes come first, then those at the extremes of single axes.
+fn sort_key<'a>(
+    locations: &[NormalizedLocation],
+    axis_order: &'a [String],
+) -> impl Fn(&NormalizedLocation) -> SortKey + 'a {
+    let mut axis_points: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for loc in locations.iter().filter(|loc| loc.len() == 1) {
+        let (axis, value) = loc.iter().next().expect("checked length");
+        axis_points.entry(axis.clone()).or_insert_with(|| vec![0.0]).push(*value);
+    }
+    move |loc| {
+        let on_point_axes = loc
+            .iter()
+            .filter(|(axis, value)| {
+                matches!(axis_points.get(*axis), Some(points) if points.contains(value))
+            })
+            .count();
+        let mut ordered_axes: Vec<&String> =
+            axis_order.iter().filter(|axis| loc.contains_key(*axis)).collect();
+        ordered_axes.extend(loc.keys().filter(|axis| !axis_order.contains(axis)));
+        SortKey {
+            rank: loc.len(),
+            on_point_axes,
+            axis_indices: ordered_axes
+                .iter()
+                .map(|axis| axis_order.iter().position(|a| a == *axis).unwrap_or(0x10000))
+                .collect(),
+            axes: ordered_axes.iter().map(|axis| (*axis).clone()).collect(),
+            signs: ordered_axes.iter().map(|axis| loc[*axis].signum() as i8).collect(),
+            magnitudes: ordered_axes.iter().map(|axis| loc[*axis].abs()).collect(),
+        }
+    }
+}
+
+struct SortKey {
+    rank: usize,
+    on_point_axes: usize,
+    axis_indices: Vec<usize>,
+    axes: Vec<String>,
+    signs: Vec<i8>,
+    magnitudes: Vec<f64>,
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey {}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank
+            .cmp(&other.rank)
+            .then(other.on_point_axes.cmp(&self.on_point_axes))
+            .then_with(|| self.axis_indices.cmp(&other.axis_indices))
+            .then_with(|| self.axes.cmp(&other.axes))
+            .then_with(|| self.signs.cmp(&other.signs))
+            .then_with(|| {
+                let pairs = self.magnitudes.iter().zip(&other.magnitudes);
+                pairs
+                    .map(|(a, b)| a.total_cmp(b))
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or_else(|| self.magnitudes.len().cmp(&other.magnitudes.len()))
+            })
+    }
+}
+
+/// Compute the support of each master, which starts out as the box from the
+/// default location to the extremes of the axes, and is cut down wherever
+/// an earlier master lies inside it.
+fn compute_supports(locations: &[NormalizedLocation]) -> Vec<Support> {
+    let mut min: BTreeMap<&str, f64> = BTreeMap::new();
+    let mut max: BTreeMap<&str, f64> = BTreeMap::new();
+    for (axis, value) in locations.iter().flatten() {
+        let min = min.entry(axis).or_insert(*value);
+        *min = min.min(*value);
+        let max = max.entry(axis).or_insert(*value);
+        *max = max.max(*value);
+    }
+    let regions: Vec<Support> = locations
+        .iter()
+        .map(|loc| {
+            loc.iter()
+                .map(|(axis, &value)| {
+                    let bounds = if value > 0.0 {
+                        (0.0, value, max[axis.as_str()])
+                    } else {
+                        (min[axis.as_str()], value, 0.0)
+                    };
+                    (axis.clone(), bounds)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut supports: Vec<Support> = Vec::with_capacity(regions.len());
+    for (idx, region) in regions.iter().enumerate() {
+        let mut region = region.clone();
+        let axes: BTreeSet<&String> = regions[idx].keys().collect();
+        for prev in &regions[..idx] {
+            // Masters on axes this one isn't on don't participate.
+            if !prev.keys().all(|axis| axes.contains(axis)) {
+                continue;
+            }
+            // Nor do masters outside of the box.
+            let relevant = region.iter().all(|(axis, &(lower, peak, upper))| {
+                let value = prev.get(axis).map_or(0.0, |bounds| bounds.1);
+                value == peak || (lower < value && value < upper)
+            });
+            if !relevant {
+                continue;
+            }
+            // Split the box away from the earlier master, along the axes
+            // where that gives the largest ratio.
+            let mut best_axes = Vec::new();
+            let mut best_ratio = -1.0;
+            for (axis, &(_, value, _)) in prev {
+                let (lower, peak, upper) = region[axis];
+                let (bounds, ratio) = match value.partial_cmp(&peak) {
+                    Some(Ordering::Less) => ((value, peak, upper), (value - peak) / (lower - peak)),
+                    Some(Ordering::Greater) => {
+                        ((lower, peak, value), (value - peak) / (upper - peak))
+                    }
+                    _ => continue,
+                };
+                if ratio > best_ratio {
+                    best_axes.clear();
+                    best_ratio = ratio;
+                }
+                if ratio == best_ratio {
+                    best_axes.push((axis.clone(), bounds));
+                }
+            }
+            region.extend(best_axes);
+        }
+        supports.push(region);
+    }
+    supports
+}
+
+/// How much a delta with the given support applies at `location`.
+fn support_scalar(location: &NormalizedLocation, support: &Support) -> f64 {
+    let mut scalar = 1.0;
+    for (axis, &(lower, peak, upper)) in support {
+        if peak == 0.0 || lower > peak || peak > upper || (lower < 0.0 && upper > 0.0) {
+            continue;
+        }
+        let value = location.get(axis).copied().unwrap_or(0.0);
+        if value == peak {
+            continue;
+        }
+        if value <= lower || upper <= value {
+            return 0.0;
+        }
+        scalar *= if value < peak {
+            (value - lower) / (peak - lower)
+        } else {
+            (value - upper) / (peak - upper)
+        };
+    }
+    scalar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loc(values: &[(&str, f64)]) -> NormalizedLocation {
+        values.iter().map(|(axis, value)| (axis.to_string(), *value)).collect()
+    }
+
+    #[test]
+    fn supports() {
+        // The example from the fontTools documentation.
+        let locations = [
+            loc(&[("wght", 100.0)]),
+            loc(&[("wght", -100.0)]),
+            loc(&[("wght", -180.0)]),
+            loc(&[("wdth", 0.3)]),
+            loc(&[("wght", 120.0), ("wdth", 0.3)]),
+            loc(&[("wght", 120.0), ("wdth", 0.2)]),
+            loc(&[]),
+            loc(&[("wght", 180.0), ("wdth", 0.3)]),
+            loc(&[("wght", 180.0)]),
+        ];
+        let model = VariationModel::new(&locations, &["wght".to_string()]).unwrap();
+        assert_eq!(model.reverse_mapping, vec![6, 1, 2, 0, 8, 3, 7, 4, 5]);
+
+        let support = |bounds: &[(&str, (f64, f64, f64))]| -> Support {
+            bounds.iter().map(|(axis, bounds)| (axis.to_string(), *bounds)).collect()
+        };
+        let expected = vec![
+            support(&[]),
+            support(&[("wght", (-180.0, -100.0, 0.0))]),
+            support(&[("wght", (-180.0, -180.0, -100.0))]),
+            support(&[("wght", (0.0, 100.0, 180.0))]),
+            support(&[("wght", (100.0, 180.0, 180.0))]),
+            support(&[("wdth", (0.0, 0.3, 0.3))]),
+            support(&[("wdth", (0.0, 0.3, 0.3)), ("wght", (0.0, 180.0, 180.0))]),
+            support(&[("wdth", (0.0, 0.3, 0.3)), ("wght", (0.0, 120.0, 180.0))]),
+            support(&[("wdth", (0.0, 0.2, 0.3)), ("wght", (0.0, 120.0, 180.0))]),
+        ];
+        assert_eq!(model.supports, expected);
+
+        let weights: Vec<Vec<(usize, f64)>> = model
+            .delta_weights
+            .iter()
+            .map(|weights| weights.iter().map(|(j, w)| (*j, (w * 1e6).round() / 1e6)).collect())
+            .collect();
+        assert_eq!(
+            weights,
+            vec![
+                vec![],
+                vec![(0, 1.0)],
+                vec![(0, 1.0)],
+                vec![(0, 1.0)],
+                vec![(0, 1.0)],
+                vec![(0, 1.0)],
+                vec![(0, 1.0), (4, 1.0), (5, 1.0)],
+                vec![(0, 1.0), (3, 0.75), (4, 0.25), (5, 1.0), (6, 0.666667)],
+                vec![(0, 1.0), (3, 0.75), (4, 0.25), (5, 0.666667), (6, 0.444444), (7, 0.666667)],
+            ]
+        );
+    }
+
+    #[test]
+    fn weights() {
+        let locations = [loc(&[]), loc(&[("wght", 1.0)]), loc(&[("wght", -1.0)])];
+        let model = VariationModel::new(&locations, &[]).unwrap();
+        assert_eq!(model.master_weights(&loc(&[])), vec![1.0, 0.0, 0.0]);
+        assert_eq!(model.master_weights(&loc(&[("wght", 0.25)])), vec![0.75, 0.25, 0.0]);
+        assert_eq!(model.master_weights(&loc(&[("wght", -0.5)])), vec![0.5, 0.0, 0.5]);
+
+        // A master in the corner of two axes only corrects the sum of the
+        // masters on the axes.
+        let locations = [
+            loc(&[]),
+            loc(&[("wght", 1.0)]),
+            loc(&[("wdth", 1.0)]),
+            loc(&[("wght", 1.0), ("wdth", 1.0)]),
+        ];
+        let model = VariationModel::new(&locations, &[]).unwrap();
+        let weights = model.master_weights(&loc(&[("wght", 0.5), ("wdth", 0.5)]));
+        assert_eq!(weights, vec![0.25, 0.25, 0.25, 0.25]);
+
+        assert!(matches!(
+            VariationModel::new(&[loc(&[("wght", 1.0)])], &[]),
+            Err(VariationModelError::MissingBaseMaster)
+        ));
+    }
+
+    #[test]
+    fn deltas() {
+        let locations = [loc(&[]), loc(&[("wght", 1.0)]), loc(&[("wdth", 1.0)])];
+        let model = VariationModel::new(&locations, &["wght".into(), "wdth".into()]).unwrap();
+
+        let glyph = |width: f32, anchor_x: f32| {
+            let mut glyph = Glyph::new_named("a");
+            glyph.width = width;
+            glyph.anchors.push(crate::Anchor::new(
+                anchor_x,
+                0.,
+                Some("top".into()),
+                None,
+                None,
+                None,
+            ));
+            glyph
+        };
+        let glyphs = [glyph(400., 200.), glyph(600., 300.), glyph(300., 150.)];
+        let deltas = model.deltas(&[&glyphs[0], &glyphs[1], &glyphs[2]]).unwrap();
+        assert_eq!(deltas[1].width, 200.);
+        assert_eq!(deltas[2].anchors[0].x, -50.);
+        let location = loc(&[("wght", 0.5), ("wdth", 1.0)]);
+        let value = model.interpolate_from_deltas(&location, &deltas).unwrap();
+        assert_eq!((value.width, value.anchors[0].x), (400., 200.));
+        let value = model.interpolate_from_deltas(&loc(&[("wght", 1.0)]), &deltas).unwrap();
+        assert_eq!(value, glyphs[1]);
+
+        let kerning = |pairs: &[(&str, &str, f32)]| -> Kerning {
+            let mut kerning = Kerning::new();
+            for (first, second, value) in pairs {
+                kerning.entry(first.to_string()).or_default().insert(second.to_string(), *value);
+            }
+            kerning
+        };
+        let kernings = [
+            kerning(&[("A", "V", -50.)]),
+            kerning(&[("A", "V", -80.)]),
+            kerning(&[("T", "o", -10.)]),
+        ];
+        let deltas = model.deltas(&[&kernings[0], &kernings[1], &kernings[2]]).unwrap();
+        assert_eq!(deltas[1], kerning(&[("A", "V", -30.)]));
+        assert_eq!(deltas[2], kerning(&[("A", "V", 50.), ("T", "o", -10.)]));
+        let value = model.interpolate_from_deltas(&loc(&[("wdth", 0.5)]), &deltas).unwrap();
+        assert_eq!(value, kerning(&[("A", "V", -25.), ("T", "o", -5.)]));
+
+        assert_eq!(
+            model.deltas(&[&kernings[0], &kernings[1]]),
+            Err(InterpolationError::MasterCount { expected: 3, found: 2 })
+        );
+    }
+}